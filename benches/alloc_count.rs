@@ -0,0 +1,60 @@
+//! Counts allocations made while interning short symbols
+//!
+//! A fresh symbol should take a single allocation (plus occasional resizes
+//! of the pool), a repeated one none at all.
+extern crate string_intern;
+
+use std::alloc::{GlobalAlloc, Layout, System};
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+use string_intern::{Validator, Symbol};
+
+struct Counter;
+
+static ALLOCATIONS: AtomicUsize = AtomicUsize::new(0);
+
+unsafe impl GlobalAlloc for Counter {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        ALLOCATIONS.fetch_add(1, Ordering::SeqCst);
+        System.alloc(layout)
+    }
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        System.dealloc(ptr, layout)
+    }
+}
+
+#[global_allocator]
+static GLOBAL: Counter = Counter;
+
+struct AnyString;
+
+impl Validator for AnyString {
+    type Err = ::std::string::ParseError;
+    fn validate_symbol(_: &str) -> Result<(), Self::Err> {
+        Ok(())
+    }
+}
+
+type Atom = Symbol<AnyString>;
+
+const SYMBOLS: usize = 10000;
+
+fn main() {
+    let names = (0..SYMBOLS).map(|i| format!("ident_{}", i))
+        .collect::<Vec<_>>();
+    let mut symbols = Vec::with_capacity(SYMBOLS * 2);
+    let before = ALLOCATIONS.load(Ordering::SeqCst);
+    for name in &names {
+        symbols.push(name.parse::<Atom>().unwrap());
+    }
+    let fresh = ALLOCATIONS.load(Ordering::SeqCst) - before;
+    let before = ALLOCATIONS.load(Ordering::SeqCst);
+    for name in &names {
+        symbols.push(name.parse::<Atom>().unwrap());
+    }
+    let repeated = ALLOCATIONS.load(Ordering::SeqCst) - before;
+    println!("fresh short symbols: {:.2} allocations per symbol",
+        fresh as f64 / SYMBOLS as f64);
+    println!("repeated short symbols: {:.2} allocations per symbol",
+        repeated as f64 / SYMBOLS as f64);
+}
@@ -0,0 +1,54 @@
+//! Measures interning of hot strings from many threads
+//!
+//! Compare the output of `cargo bench --bench contention` with and
+//! without `--features arc-swap` or `--features sharded`.
+extern crate string_intern;
+
+use std::sync::{Arc, Barrier};
+use std::thread;
+use std::time::Instant;
+
+use string_intern::{Validator, Symbol};
+
+struct AnyString;
+
+impl Validator for AnyString {
+    type Err = ::std::string::ParseError;
+    fn validate_symbol(_: &str) -> Result<(), Self::Err> {
+        Ok(())
+    }
+}
+
+type Atom = Symbol<AnyString>;
+
+const THREADS: usize = 8;
+const ITERATIONS: usize = 200000;
+
+fn main() {
+    let names = Arc::new((0..16).map(|i| format!("hot_{}", i))
+        .collect::<Vec<_>>());
+    // keep the values alive, so the workload is read-only
+    let _keep = names.iter().map(|n| n.parse::<Atom>().unwrap())
+        .collect::<Vec<_>>();
+    let barrier = Arc::new(Barrier::new(THREADS + 1));
+    let threads = (0..THREADS).map(|_| {
+        let names = names.clone();
+        let barrier = barrier.clone();
+        thread::spawn(move || {
+            barrier.wait();
+            for i in 0..ITERATIONS {
+                let sym: Atom = names[i % names.len()].parse().unwrap();
+                drop(sym);
+            }
+        })
+    }).collect::<Vec<_>>();
+    barrier.wait();
+    let start = Instant::now();
+    for t in threads {
+        t.join().unwrap();
+    }
+    let elapsed = start.elapsed();
+    println!("{} threads x {} lookups: {:?} ({:.1} ns per lookup)",
+        THREADS, ITERATIONS, elapsed,
+        elapsed.as_nanos() as f64 / (THREADS * ITERATIONS) as f64);
+}
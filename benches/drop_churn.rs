@@ -0,0 +1,59 @@
+//! Measures interning and dropping of short-lived symbols
+//!
+//! Runs the same workload with both drop policies, see
+//! `Symbol::set_drop_policy`.
+extern crate string_intern;
+
+use std::sync::{Arc, Barrier};
+use std::thread;
+use std::time::Instant;
+
+use string_intern::{Validator, Symbol, DropPolicy};
+
+struct AnyString;
+
+impl Validator for AnyString {
+    type Err = ::std::string::ParseError;
+    fn validate_symbol(_: &str) -> Result<(), Self::Err> {
+        Ok(())
+    }
+}
+
+type Atom = Symbol<AnyString>;
+
+const THREADS: usize = 8;
+const ITERATIONS: usize = 100000;
+
+fn run(policy: DropPolicy) {
+    Atom::set_drop_policy(policy);
+    let barrier = Arc::new(Barrier::new(THREADS + 1));
+    let threads = (0..THREADS).map(|t| {
+        let barrier = barrier.clone();
+        thread::spawn(move || {
+            let names = (0..1000).map(|i| format!("churn_{}_{}", t, i))
+                .collect::<Vec<_>>();
+            barrier.wait();
+            for i in 0..ITERATIONS {
+                let sym: Atom = names[i % names.len()].parse().unwrap();
+                drop(sym);
+            }
+        })
+    }).collect::<Vec<_>>();
+    barrier.wait();
+    let start = Instant::now();
+    for t in threads {
+        t.join().unwrap();
+    }
+    let elapsed = start.elapsed();
+    let pruned = Atom::prune();
+    println!("{:?}: {} threads x {} intern+drop: {:?} \
+        ({:.1} ns per iteration), {} entries pruned",
+        policy, THREADS, ITERATIONS, elapsed,
+        elapsed.as_nanos() as f64 / (THREADS * ITERATIONS) as f64,
+        pruned);
+}
+
+fn main() {
+    run(DropPolicy::Immediate);
+    run(DropPolicy::Deferred);
+}
@@ -0,0 +1,161 @@
+//! Derive macro for the `Validator` trait of string-intern
+//!
+//! Use it through the `derive` feature of `string-intern`:
+//!
+//! ```ignore
+//! use string_intern::{Symbol, SymbolValidator};
+//!
+//! #[derive(SymbolValidator)]
+//! #[symbol(pattern = "^[a-z][a-z0-9_]*$", max_len = 64, lowercase)]
+//! pub struct Name;
+//!
+//! type NameSymbol = Symbol<Name>;
+//! ```
+//!
+//! Supported attributes:
+//!
+//! * `pattern = "..."` -- values must match the regex (requires the `regex`
+//!   feature of `string-intern`), the regex is compiled on first use, and
+//!   it is also `Validator::PATTERN`
+//! * `max_len = N` -- sets `Validator::MAX_LEN`
+//! * `lowercase` -- values are converted to lowercase before validation
+//!
+//! The error type named after the struct with the `Error` suffix (e.g.
+//! `NameError`) is generated next to it.
+extern crate proc_macro;
+extern crate proc_macro2;
+extern crate quote;
+extern crate syn;
+
+use proc_macro::TokenStream;
+use proc_macro2::Span;
+use quote::quote;
+use syn::{parse_macro_input, DeriveInput, Data, Fields, Ident, LitInt, LitStr};
+
+
+#[derive(Default)]
+struct Options {
+    pattern: Option<LitStr>,
+    max_len: Option<LitInt>,
+    lowercase: bool,
+}
+
+fn parse_options(input: &DeriveInput) -> syn::Result<Options> {
+    let mut options = Options::default();
+    for attr in &input.attrs {
+        if !attr.path().is_ident("symbol") {
+            continue;
+        }
+        attr.parse_nested_meta(|meta| {
+            if meta.path.is_ident("pattern") {
+                options.pattern = Some(meta.value()?.parse()?);
+            } else if meta.path.is_ident("max_len") {
+                let value: LitInt = meta.value()?.parse()?;
+                value.base10_parse::<usize>()?;
+                options.max_len = Some(value);
+            } else if meta.path.is_ident("lowercase") {
+                options.lowercase = true;
+            } else {
+                return Err(meta.error("unknown symbol attribute"));
+            }
+            Ok(())
+        })?;
+    }
+    Ok(options)
+}
+
+/// Implements `Validator` for a unit struct, see the crate docs
+#[proc_macro_derive(SymbolValidator, attributes(symbol))]
+pub fn derive_symbol_validator(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+    expand(&input).unwrap_or_else(|e| e.to_compile_error()).into()
+}
+
+fn expand(input: &DeriveInput) -> syn::Result<proc_macro2::TokenStream> {
+    match input.data {
+        Data::Struct(ref data) if matches!(data.fields, Fields::Unit) => {}
+        _ => return Err(syn::Error::new_spanned(&input.ident,
+            "SymbolValidator can only be derived for unit structs")),
+    }
+    if !input.generics.params.is_empty() {
+        return Err(syn::Error::new_spanned(&input.generics,
+            "SymbolValidator can't be derived for generic structs"));
+    }
+    let options = parse_options(input)?;
+    let name = &input.ident;
+    let vis = &input.vis;
+    let error = Ident::new(&format!("{}Error", name), Span::call_site());
+    let error_doc = format!("Error of the `{}` validator", name);
+
+    let mismatch_variant = options.pattern.as_ref().map(|_| quote! {
+        /// Value doesn't match the pattern
+        Mismatch,
+    });
+    let mismatch_display = options.pattern.as_ref().map(|p| {
+        let message = format!("value doesn't match pattern {:?}", p.value());
+        quote! { #error::Mismatch => f.write_str(#message), }
+    });
+    let check_pattern = options.pattern.as_ref().map(|p| quote! {
+        static REGEX: ::std::sync::OnceLock<
+            ::string_intern::validators::__Regex> =
+            ::std::sync::OnceLock::new();
+        ::string_intern::validators::match_pattern(&REGEX, #p, value)
+            .map_err(|_| #error::Mismatch)?;
+    });
+    let pattern = options.pattern.as_ref().map(|p| quote! {
+        const PATTERN: Option<&'static str> = Some(#p);
+    });
+    let max_len = options.max_len.as_ref().map(|n| quote! {
+        const MAX_LEN: Option<usize> = Some(#n);
+    });
+    let normalize = if options.lowercase {
+        Some(quote! {
+            fn normalize(value: &str) -> ::std::borrow::Cow<'_, str> {
+                if value.chars().any(|c| c.is_uppercase()) {
+                    ::std::borrow::Cow::Owned(value.to_lowercase())
+                } else {
+                    ::std::borrow::Cow::Borrowed(value)
+                }
+            }
+        })
+    } else {
+        None
+    };
+
+    Ok(quote! {
+        #[doc = #error_doc]
+        #[derive(Debug, Clone, PartialEq, Eq)]
+        #vis enum #error {
+            /// Value of specified length (in bytes) is too long
+            TooLong(usize),
+            #mismatch_variant
+        }
+
+        impl ::std::fmt::Display for #error {
+            fn fmt(&self, f: &mut ::std::fmt::Formatter) -> ::std::fmt::Result {
+                match *self {
+                    #error::TooLong(len) => {
+                        write!(f, "value of {} bytes is too long", len)
+                    }
+                    #mismatch_display
+                }
+            }
+        }
+
+        impl ::std::error::Error for #error {}
+
+        impl ::string_intern::Validator for #name {
+            type Err = #error;
+            #max_len
+            #pattern
+            fn validate_symbol(value: &str) -> Result<(), Self::Err> {
+                let _ = value;
+                #check_pattern
+                Ok(())
+            }
+            const TOO_LONG: Option<fn(::string_intern::TooLong) -> #error> =
+                Some(|e| #error::TooLong(e.len));
+            #normalize
+        }
+    })
+}
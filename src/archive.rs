@@ -0,0 +1,88 @@
+use std::error::Error;
+use std::fmt;
+
+use rkyv::{Archive, Deserialize, Place, Serialize, SerializeUnsized};
+use rkyv::rancor::{Fallible, Source};
+use rkyv::string::{ArchivedString, StringResolver};
+
+use {Validator, Symbol};
+
+
+/// Archived as an `ArchivedString`
+///
+/// The archive can be read in place as a plain string. Deserialization
+/// validates and interns the string, same as `FromStr`.
+impl<V: Validator + ?Sized> Archive for Symbol<V> {
+    type Archived = ArchivedString;
+    type Resolver = StringResolver;
+
+    fn resolve(&self, resolver: Self::Resolver, out: Place<Self::Archived>) {
+        ArchivedString::resolve_from_str(self, resolver, out);
+    }
+}
+
+impl<V, S> Serialize<S> for Symbol<V>
+    where V: Validator + ?Sized, S: Fallible + ?Sized, S::Error: Source,
+          str: SerializeUnsized<S>,
+{
+    fn serialize(&self, serializer: &mut S)
+        -> Result<Self::Resolver, S::Error>
+    {
+        ArchivedString::serialize_from_str(self, serializer)
+    }
+}
+
+impl<V, D> Deserialize<Symbol<V>, D> for ArchivedString
+    where V: Validator + ?Sized, D: Fallible + ?Sized, D::Error: Source,
+{
+    fn deserialize(&self, _: &mut D) -> Result<Symbol<V>, D::Error> {
+        self.as_str().parse().map_err(|e: V::Err| {
+            Source::new(InvalidSymbol(e.to_string()))
+        })
+    }
+}
+
+/// The validator error, which may be neither `Error` nor `Send`
+#[derive(Debug)]
+struct InvalidSymbol(String);
+
+impl fmt::Display for InvalidSymbol {
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+        write!(fmt, "invalid symbol: {}", self.0)
+    }
+}
+
+impl Error for InvalidSymbol {}
+
+#[cfg(test)]
+mod test {
+    use rkyv::rancor;
+    use rkyv::string::ArchivedString;
+    use Symbol;
+    use validators::{Identifier, NonEmpty};
+
+    #[test]
+    fn roundtrip() {
+        let syms = vec![Symbol::<NonEmpty>::from("rkyv_a"),
+                        Symbol::from("a_longer_rkyv_value_out_of_line")];
+        let bytes = rkyv::to_bytes::<rancor::Error>(&syms).unwrap();
+        let archived = rkyv::access::<rkyv::Archived<Vec<Symbol<NonEmpty>>>,
+                                      rancor::Error>(&bytes).unwrap();
+        assert_eq!(archived[0].as_str(), "rkyv_a");
+        let back = rkyv::deserialize::<Vec<Symbol<NonEmpty>>,
+                                       rancor::Error>(archived).unwrap();
+        assert_eq!(back, syms);
+        assert_eq!(back[1].as_ptr(), syms[1].as_ptr());
+    }
+
+    #[test]
+    fn invalid() {
+        let bytes = rkyv::to_bytes::<rancor::Error>(
+            &String::from("not an identifier")).unwrap();
+        let archived = rkyv::access::<ArchivedString, rancor::Error>(&bytes)
+            .unwrap();
+        let err = rkyv::deserialize::<Symbol<Identifier>, rancor::Error>(
+            archived).unwrap_err();
+        assert!(err.to_string().starts_with("invalid symbol: "));
+    }
+}
@@ -1,23 +1,29 @@
-use std::cmp::Ordering;
-use std::fmt;
-use std::ops::{Deref, Drop};
-use std::hash::{Hash, Hasher};
-use std::str::FromStr;
-use std::marker::PhantomData;
-use std::borrow::Borrow;
-use std::sync::{Arc, RwLock, Weak};
-use std::collections::HashMap;
-use std::collections::hash_map::Entry::{Occupied, Vacant};
+use core::cmp::Ordering;
+use core::fmt;
+use core::str;
+use core::ops::{Deref, Drop};
+use core::hash::{BuildHasher, Hash, Hasher};
+use core::mem;
+use core::ptr;
+use core::str::FromStr;
+use core::convert::TryFrom;
+use core::marker::PhantomData;
+use alloc::borrow::{Borrow, Cow};
+use alloc::sync::{Arc, Weak};
 
 #[cfg(feature = "serde")] use serde::ser::{Serialize, Serializer};
 #[cfg(feature = "serde")] use serde::de::{self, Deserialize, Deserializer, Visitor};
 #[cfg(feature = "rustc-serialize")] use rustc_serialize::{Decoder, Decodable, Encoder, Encodable};
-use {Validator};
-
-lazy_static! {
-    static ref ATOMS: RwLock<HashMap<Buf, Weak<Value>>> =
-        RwLock::new(HashMap::new());
-}
+use {Validator, SymbolParseError, FromBufError, TryInternError};
+use ValidationError;
+use {PoolLimits, PoolStats, DropPolicy};
+use validator::{validate, validate_limited, fold};
+use pool::{pool, BuildHash, Item, Pool, PoolRef, ShardGuard};
+use scope;
+#[cfg(feature = "arc-swap")] use snapshot;
+#[cfg(feature = "thread-cache")] use thread_cache;
+#[cfg(all(feature = "serde", feature = "std"))] use dedup;
+use compat::prelude::*;
 
 /// Base symbol type
 ///
@@ -26,14 +32,148 @@ lazy_static! {
 /// ```ignore
 /// type MySymbol = Symbol<MyValidator>;
 /// ```
-// TODO(tailhook) optimize Eq to compare pointers
-pub struct Symbol<V: Validator + ?Sized>(Arc<Value>, PhantomData<V>);
+#[cfg_attr(feature = "diesel",
+    derive(diesel::expression::AsExpression,
+           diesel::deserialize::FromSqlRow),
+    diesel(sql_type = diesel::sql_types::Text))]
+pub struct Symbol<V: Validator + ?Sized>(
+    pub(crate) Arc<Value>, pub(crate) PhantomData<V>);
+
+/// Result of `Symbol::entry`
+///
+/// Tells whether the symbol was already interned, so that side data can be
+/// initialized exactly once per value, with a single trip to the pool.
+pub enum SymbolEntry<V: Validator + ?Sized> {
+    /// Some symbol with this value was alive already
+    Existing(Symbol<V>),
+    /// The value was just added to the pool
+    Inserted(Symbol<V>),
+}
+
+/// Interned value: the header and the string in a single allocation
+///
+/// So the reference counters, the header and the bytes are reached by
+/// a single pointer. Made by `Value::new`, as there is no safe way to
+/// allocate an unsized struct.
+#[repr(C, align(8))]
+pub(crate) struct Value {
+    head: Head,
+    // The value, followed by the key if `Validator::fold` returned
+    // something else
+    bytes: str,
+}
+
+struct Head {
+    // Length of the value at the start of `bytes`
+    len: usize,
+    // Points into `bytes`, as they aren't reachable from `Head::drop`
+    key: KeyPtr,
+    // The pool to remove the value from when it's dropped
+    pool: PoolRef,
+    // Incremented by `Symbol::clear`, values from different epochs may be
+    // equal even if they are from the same pool
+    epoch: u32,
+    id: u32,
+    // Hash of the key by `HASHER`, the same for all pools
+    hash: u64,
+}
+
+/// Allocation unit of `Value`, which has the same alignment
+#[repr(align(8))]
+struct Word(#[allow(dead_code)] [u8; 8]);
+
+/// Key of the value in the pool
+///
+/// Points to the bytes of the value, the weak reference keeps them after
+/// the value is dropped, as nothing in `bytes` needs dropping.
+#[derive(Clone)]
+pub(crate) struct Buf {
+    key: KeyPtr,
+    _value: Weak<Value>,
+}
+
+#[derive(Clone, Copy)]
+struct KeyPtr(*const str);
+
+// The bytes are never changed after the value is made
+unsafe impl Send for KeyPtr {}
+unsafe impl Sync for KeyPtr {}
+
+lazy_static! {
+    static ref HASHER: BuildHash = BuildHash::default();
+}
+
+impl Value {
+    /// Allocates the value, `key` is only given if it's not `value` itself
+    fn new(value: &str, key: Option<&str>, pool: &PoolRef, id: u32,
+        hash: u64)
+        -> Arc<Value>
+    {
+        let len = value.len() + key.map_or(0, str::len);
+        // `Arc<[Word]>` of this size has the same layout as `Arc<Value>`
+        let words = (mem::size_of::<Head>() + len).div_ceil(8);
+        let buf = Arc::<[Word]>::new_uninit_slice(words);
+        let ptr = Arc::into_raw(buf) as *mut u8;
+        unsafe {
+            let bytes = ptr.add(mem::size_of::<Head>());
+            ptr::copy_nonoverlapping(value.as_ptr(), bytes, value.len());
+            let key = match key {
+                Some(key) => {
+                    let start = bytes.add(value.len());
+                    ptr::copy_nonoverlapping(key.as_ptr(), start, key.len());
+                    ptr::slice_from_raw_parts(start, key.len())
+                }
+                None => ptr::slice_from_raw_parts(bytes, value.len()),
+            };
+            ptr::write(ptr as *mut Head, Head {
+                len: value.len(),
+                key: KeyPtr(key as *const str),
+                pool: pool.clone(),
+                epoch: pool.epoch(),
+                id,
+                hash,
+            });
+            Arc::from_raw(ptr::slice_from_raw_parts(ptr, len) as *const Value)
+        }
+    }
+
+    pub(crate) fn as_str(&self) -> &str {
+        &self.bytes[..self.head.len]
+    }
+
+    pub(crate) fn key(&self) -> &str {
+        // points into `bytes` of this value
+        unsafe { &*self.head.key.0 }
+    }
+
+    #[cfg(feature = "thread-cache")]
+    pub(crate) fn epoch(&self) -> u32 {
+        self.head.epoch
+    }
+}
 
-#[derive(PartialEq, Eq, Hash)]
-struct Buf(Arc<String>);
+impl Deref for Buf {
+    type Target = str;
+    fn deref(&self) -> &str {
+        // the allocation is kept by `_value`
+        unsafe { &*self.key.0 }
+    }
+}
+
+impl PartialEq for Buf {
+    fn eq(&self, other: &Buf) -> bool {
+        **self == **other
+    }
+}
+
+impl Eq for Buf {}
 
-#[derive(Clone, PartialEq, Eq, Hash, PartialOrd, Ord)]
-struct Value(Arc<String>);
+// Must match the hash of `str` for `Borrow<str>` of `Buf`
+impl Hash for Buf {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        (**self).hash(state)
+    }
+}
 
 impl<V: Validator + ?Sized> Clone for Symbol<V> {
     fn clone(&self) -> Symbol<V> {
@@ -41,174 +181,411 @@ impl<V: Validator + ?Sized> Clone for Symbol<V> {
     }
 }
 
+// Values are unique within a pool, so comparing pointers is enough, unless
+// symbols come from different pools (i.e. one of them is from `Interner`)
+// or the value was evicted from the pool (or the pool was cleared)
 impl<V: Validator + ?Sized> PartialEq for Symbol<V> {
     fn eq(&self, other: &Symbol<V>) -> bool {
-        self.0.eq(&other.0)
+        let (a, b) = (&self.0, &other.0);
+        Arc::ptr_eq(a, b) ||
+            (!a.head.pool.same(&b.head.pool) || a.head.epoch != b.head.epoch)
+                && a.key() == b.key()
     }
 }
 impl<V: Validator + ?Sized> Eq for Symbol<V> {}
 
+// `Borrow<str>` requires hash of a symbol to be equal to the hash of the
+// string, so lookups by `&str` and by `&String` work in hash maps. This must
+// hold even if equality is checked by comparing pointers. The key is the
+// value itself unless the validator overrides `fold`.
 impl<V: Validator + ?Sized> Hash for Symbol<V> {
     fn hash<H: Hasher>(&self, hasher: &mut H) {
-        self.0.hash(hasher)
+        self.0.key().hash(hasher)
     }
 }
 
 impl<V: Validator + ?Sized> PartialOrd for Symbol<V> {
     fn partial_cmp(&self, other: &Symbol<V>) -> Option<Ordering> {
-        self.0.partial_cmp(&other.0)
+        Some(self.cmp(other))
     }
 }
 
 impl<V: Validator + ?Sized> Ord for Symbol<V> {
     fn cmp(&self, other: &Symbol<V>) -> Ordering {
-        self.0.cmp(&other.0)
+        V::compare(&self[..], &other[..])
+    }
+}
+
+
+impl<V: Validator + ?Sized> PartialEq<str> for Symbol<V> {
+    fn eq(&self, other: &str) -> bool {
+        self.0.key() == V::fold(other)
+    }
+}
+
+impl<'a, V: Validator + ?Sized> PartialEq<&'a str> for Symbol<V> {
+    fn eq(&self, other: &&'a str) -> bool {
+        self.eq(*other)
+    }
+}
+
+impl<V: Validator + ?Sized> PartialEq<Symbol<V>> for str {
+    fn eq(&self, other: &Symbol<V>) -> bool {
+        other.eq(self)
+    }
+}
+
+impl<V: Validator + ?Sized> PartialEq<Symbol<V>> for &str {
+    fn eq(&self, other: &Symbol<V>) -> bool {
+        other.eq(*self)
+    }
+}
+
+impl<V: Validator + ?Sized> PartialOrd<str> for Symbol<V> {
+    fn partial_cmp(&self, other: &str) -> Option<Ordering> {
+        Some(V::compare(&self[..], other))
+    }
+}
+
+impl<'a, V: Validator + ?Sized> PartialOrd<&'a str> for Symbol<V> {
+    fn partial_cmp(&self, other: &&'a str) -> Option<Ordering> {
+        Some(V::compare(&self[..], other))
+    }
+}
+
+impl<V: Validator + ?Sized> PartialOrd<Symbol<V>> for str {
+    fn partial_cmp(&self, other: &Symbol<V>) -> Option<Ordering> {
+        Some(V::compare(self, &other[..]))
     }
 }
 
+impl<V: Validator + ?Sized> PartialOrd<Symbol<V>> for &str {
+    fn partial_cmp(&self, other: &Symbol<V>) -> Option<Ordering> {
+        Some(V::compare(self, &other[..]))
+    }
+}
 
 impl<V: Validator + ?Sized> FromStr for Symbol<V> {
     type Err = V::Err;
     fn from_str(s: &str) -> Result<Symbol<V>, Self::Err> {
-        V::validate_symbol(s)?;
-        if let Some(a) = ATOMS.read().expect("atoms locked").get(s) {
-            if let Some(a) = a.upgrade() {
-                return Ok(Symbol(a.clone(), PhantomData));
-            }
-            // We may get a race condition where atom has no strong references
-            // any more, but weak reference is still no removed because
-            // destructor is waiting for a lock in another thread.
-            //
-            // That's fine we'll get a write lock and recheck it later.
-        }
-        let buf = Arc::new(String::from(s));
-        let mut atoms = ATOMS.write().expect("atoms locked");
-        let val = match atoms.entry(Buf(buf.clone())) {
-            Occupied(mut e) => match e.get().upgrade() {
-                Some(a) => a,
-                None => {
-                    let result = Arc::new(Value(buf));
-                    e.insert(Arc::downgrade(&result));
-                    result
-                }
-            },
-            Vacant(e) => {
-                let result = Arc::new(Value(buf));
-                e.insert(Arc::downgrade(&result));
-                result
-            }
-        };
-        Ok(Symbol(val, PhantomData))
+        let s = validate::<V>(s)?;
+        Ok(Symbol(intern::<V>(s).0, PhantomData))
+    }
+}
+
+/// Same as `from_str`
+impl<'a, V: Validator + ?Sized> TryFrom<&'a str> for Symbol<V> {
+    type Error = V::Err;
+    fn try_from(s: &'a str) -> Result<Symbol<V>, Self::Error> {
+        FromStr::from_str(s)
+    }
+}
+
+/// Borrowed strings are only copied if the value is not interned yet
+impl<'a, V: Validator + ?Sized> TryFrom<Cow<'a, str>> for Symbol<V> {
+    type Error = V::Err;
+    fn try_from(s: Cow<'a, str>) -> Result<Symbol<V>, Self::Error> {
+        let value = validate::<V>(&s)?;
+        Ok(Symbol(intern::<V>(value).0, PhantomData))
+    }
+}
+
+/// Same as `Symbol::from_string`
+impl<V: Validator + ?Sized> TryFrom<String> for Symbol<V> {
+    type Error = V::Err;
+    fn try_from(s: String) -> Result<Symbol<V>, Self::Error> {
+        Symbol::from_string(s)
+    }
+}
+
+/// Copies the value out of the pool
+impl<V: Validator + ?Sized> From<Symbol<V>> for String {
+    fn from(sym: Symbol<V>) -> String {
+        sym.to_owned_string()
+    }
+}
+
+/// Finds or inserts a value into the pool of `V`
+///
+/// The string must be validated by the caller. Nothing is allocated if the
+/// value is interned already. Returns `true` if value was already interned.
+fn intern<V: Validator + ?Sized>(s: Cow<str>) -> (Arc<Value>, bool) {
+    let key = fold::<V>(&s);
+    if let Some(atoms) = V::static_atoms() {
+        if let Some(sym) = atoms.lookup(key.as_deref().unwrap_or(&s)) {
+            return (sym.0.clone(), true);
+        }
+    }
+    intern_dynamic::<V>(s, key)
+}
+
+/// Same as `intern` but doesn't look into `Validator::static_atoms`
+pub(crate) fn intern_dynamic<V: Validator + ?Sized>(s: Cow<str>,
+    key: Option<String>)
+    -> (Arc<Value>, bool)
+{
+    let pool = pool::<V>();
+    #[cfg(feature = "thread-cache")]
+    {
+        if !pool.thread_cache() || !scope::thread_cache() {
+            return intern_shared(pool, s, key);
+        }
+        let k = key.as_deref().unwrap_or(&s);
+        if let Some(a) = thread_cache::lookup(pool, k) {
+            scope::pin(&a);
+            return (a, true);
+        }
+    }
+    let result = intern_shared(pool, s, key);
+    #[cfg(feature = "thread-cache")]
+    thread_cache::insert(pool, &result.0);
+    result
+}
+
+fn intern_shared(pool: &'static Pool, s: Cow<str>, key: Option<String>)
+    -> (Arc<Value>, bool)
+{
+    #[cfg(feature = "arc-swap")]
+    {
+        if let Some(a) = pool.snapshot.lookup(key.as_deref().unwrap_or(&s)) {
+            scope::pin(&a);
+            return (a, true);
+        }
+        let result = intern_slow(pool, s, key.as_deref());
+        snapshot::missed(pool);
+        result
+    }
+    #[cfg(not(feature = "arc-swap"))]
+    intern_slow(pool, s, key.as_deref())
+}
+
+fn intern_slow(pool: &'static Pool, s: Cow<str>, key: Option<&str>)
+    -> (Arc<Value>, bool)
+{
+    let k = key.unwrap_or(&s);
+    if let Some(a) = pool.lookup(k) {
+        scope::pin(&a);
+        return (a, true);
+    }
+    intern_locked(&mut pool.write(k), s, key, &pool.shared_ref())
+}
+
+/// Finds or inserts a value into the pool, when write lock is already held
+///
+/// The value is looked up by `key` (as returned by `validator::fold`) if
+/// it's given, and by the value itself otherwise.
+pub(crate) fn intern_locked(atoms: &mut ShardGuard, s: Cow<str>,
+    key: Option<&str>, pool: &PoolRef)
+    -> (Arc<Value>, bool)
+{
+    let k = key.unwrap_or(&s);
+    if let Some(a) = atoms.find(k) {
+        scope::pin(&a);
+        return (a, true);
+    }
+    let hash = BuildHasher::hash_one(&*HASHER, k);
+    let result = atoms.insert_with(k.len(), |id| {
+        Value::new(&s, key, pool, id, hash)
+    });
+    scope::pin(&result);
+    (result, false)
+}
+
+impl Item for Value {
+    type Key = Buf;
+    type Query = str;
+    fn entry_key(value: &Arc<Value>) -> Buf {
+        Buf { key: value.head.key, _value: Arc::downgrade(value) }
+    }
+    fn size(key: &str) -> usize {
+        key.len()
     }
 }
 
-impl Drop for Value {
+// `Value` itself can't have a destructor, as `&mut Value` would cover the
+// bytes, which the pool reads while looking for the entry to remove
+impl Drop for Head {
     fn drop(&mut self) {
-        let mut atoms = ATOMS.write().expect("atoms locked");
-        atoms.remove(&self.0[..]);
+        // bytes are not dropped, and the allocation is freed only after
+        // the destructor
+        let key = unsafe { &*self.key.0 };
+        self.pool.release(key, self.id);
     }
 }
 
 impl<V: Validator + ?Sized> AsRef<str> for Symbol<V> {
     fn as_ref(&self) -> &str {
-        &(self.0).0[..]
+        self.0.as_str()
     }
 }
 
+// Borrowed as the key rather than the value, to agree with `Eq` and `Hash`
+// when the validator overrides `fold`
 impl<V: Validator + ?Sized> Borrow<str> for Symbol<V> {
     fn borrow(&self) -> &str {
-        &(self.0).0[..]
+        self.0.key()
     }
 }
 
 impl<V: Validator + ?Sized> Borrow<String> for Symbol<V> {
     fn borrow(&self) -> &String {
-        &(self.0).0
+        let head = &self.0.head;
+        let key = self.0.key();
+        let folded = key.as_ptr() != self.as_ptr();
+        // same as in `as_string`
+        unsafe { &*head.pool.string(head.id, folded, key) }
     }
 }
 
 impl Borrow<str> for Buf {
     fn borrow(&self) -> &str {
-        &self.0
+        self
     }
 }
 
-impl Borrow<String> for Buf {
-    fn borrow(&self) -> &String {
-        &self.0
+
+impl<V: Validator + ?Sized> fmt::Debug for Symbol<V> {
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+        V::debug(self, fmt)
     }
 }
 
-
-impl<V: Validator + ?Sized> fmt::Debug for Symbol<V> {
+impl<V: Validator + ?Sized> fmt::Display for Symbol<V> {
     fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
         V::display(self, fmt)
     }
 }
 
-impl<V: Validator + ?Sized> fmt::Display for Symbol<V> {
+impl<V: Validator + ?Sized> SymbolEntry<V> {
+    /// Returns `true` if value was just added to the pool
+    pub fn is_inserted(&self) -> bool {
+        match *self {
+            SymbolEntry::Existing(_) => false,
+            SymbolEntry::Inserted(_) => true,
+        }
+    }
+    /// Returns the symbol
+    pub fn symbol(&self) -> &Symbol<V> {
+        match *self {
+            SymbolEntry::Existing(ref s) => s,
+            SymbolEntry::Inserted(ref s) => s,
+        }
+    }
+    /// Unwraps the symbol
+    pub fn into_symbol(self) -> Symbol<V> {
+        match self {
+            SymbolEntry::Existing(s) => s,
+            SymbolEntry::Inserted(s) => s,
+        }
+    }
+}
+
+impl<V: Validator + ?Sized> fmt::Debug for SymbolEntry<V> {
     fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
-        (self.0).0.fmt(fmt)
+        match *self {
+            SymbolEntry::Existing(ref s) => {
+                fmt.debug_tuple("Existing").field(s).finish()
+            }
+            SymbolEntry::Inserted(ref s) => {
+                fmt.debug_tuple("Inserted").field(s).finish()
+            }
+        }
     }
 }
 
 #[cfg(feature = "rustc-serialize")]
-impl<V: Validator> Decodable for Symbol<V> {
+impl<V: Validator + ?Sized> Decodable for Symbol<V> {
     fn decode<D: Decoder>(d: &mut D) -> Result<Self, D::Error> {
-        use std::error::Error;
         d.read_str()?
         .parse::<Symbol<V>>()
-        .map_err(|e| d.error(e.description()))
+        .map_err(|e| d.error(&e.to_string()))
     }
 }
 
 #[cfg(feature = "rustc-serialize")]
-impl<V: Validator> Encodable for Symbol<V> {
+impl<V: Validator + ?Sized> Encodable for Symbol<V> {
     fn encode<E: Encoder>(&self, d: &mut E) -> Result<(), E::Error> {
-        d.emit_str(&(self.0).0)
+        d.emit_str(self.0.as_str())
     }
 }
 
 #[cfg(feature = "serde")]
-struct SymbolVisitor<V: Validator>(PhantomData<V>);
+struct SymbolVisitor<V: Validator + ?Sized>(PhantomData<V>);
 
 #[cfg(feature = "serde")]
-impl<'de, V: Validator> Visitor<'de> for SymbolVisitor<V> {
+impl<'de, V: Validator + ?Sized> Visitor<'de> for SymbolVisitor<V> {
     type Value = Symbol<V>;
 
     fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
         formatter.write_str("a valid symbol")
     }
 
+    // borrowed strings end up here too, nothing is copied for them unless
+    // the value is not interned yet
     fn visit_str<E>(self, v: &str) -> Result<Self::Value, E>
         where E: de::Error
     {
         v.parse().map_err(de::Error::custom)
     }
+
+    // the allocation of the string can't be reused (see `from_string`), but
+    // there's no need to borrow it from the deserializer as a `&str` either
+    fn visit_string<E>(self, v: String) -> Result<Self::Value, E>
+        where E: de::Error
+    {
+        Symbol::from_string(v).map_err(de::Error::custom)
+    }
+
+    fn visit_bytes<E>(self, v: &[u8]) -> Result<Self::Value, E>
+        where E: de::Error
+    {
+        let s = str::from_utf8(v).map_err(de::Error::custom)?;
+        self.visit_str(s)
+    }
 }
 
 #[cfg(feature = "serde")]
-impl<'de, V: Validator> Deserialize<'de> for Symbol<V> {
+impl<'de, V: Validator + ?Sized> Deserialize<'de> for Symbol<V> {
     fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
         where D: Deserializer<'de>
     {
-        deserializer.deserialize_str(SymbolVisitor(PhantomData))
+        #[cfg(feature = "std")]
+        {
+            if dedup::active() {
+                return dedup::deserialize(deserializer);
+            }
+        }
+        if V::BINARY_AS_BYTES && !deserializer.is_human_readable() {
+            deserializer.deserialize_bytes(SymbolVisitor(PhantomData))
+        } else {
+            deserializer.deserialize_str(SymbolVisitor(PhantomData))
+        }
     }
 }
 
 #[cfg(feature = "serde")]
-impl<V: Validator> Serialize for Symbol<V> {
+impl<V: Validator + ?Sized> Serialize for Symbol<V> {
     fn serialize<S: Serializer>(&self, serializer: S)
         -> Result<S::Ok, S::Error>
     {
-        serializer.serialize_str(&(self.0).0)
+        #[cfg(feature = "std")]
+        {
+            if dedup::active() {
+                return dedup::serialize(self, serializer);
+            }
+        }
+        if V::BINARY_AS_BYTES && !serializer.is_human_readable() {
+            serializer.serialize_bytes(self.0.as_str().as_bytes())
+        } else {
+            serializer.serialize_str(self.0.as_str())
+        }
     }
 }
 
 impl<V: Validator + ?Sized> Deref for Symbol<V> {
     type Target = str;
     fn deref(&self) -> &str {
-        &(self.0).0
+        self.0.as_str()
     }
 }
 
@@ -225,93 +602,1497 @@ impl<V: Validator + ?Sized> Symbol<V> {
         FromStr::from_str(s)
         .expect("static string used as atom is invalid")
     }
-}
 
-#[cfg(test)]
-mod test {
-    use std::io;
-    use rustc_serialize::json;
-    use {Validator, Symbol};
-    use serde_json;
+    /// Validate and intern the value, same as `from_str`
+    ///
+    /// Reads better at call sites which assert that the string is a valid
+    /// symbol of this type.
+    pub fn checked(s: &str) -> Result<Symbol<V>, V::Err> {
+        FromStr::from_str(s)
+    }
 
-    #[allow(dead_code)]
-    struct AnyString;
-    #[allow(dead_code)]
-    struct AlphaNumString;
-    type Atom = Symbol<AnyString>;
-    type AlphaNum = Symbol<AlphaNumString>;
+    /// Validate and intern an owned string
+    ///
+    /// Useful when the string is built anyway, e.g. by a parser. Note that
+    /// the allocation of the `String` can't be reused, as the pool keeps
+    /// the bytes in the same allocation as the reference counter. So the
+    /// string is copied if the value is not interned yet, and is just
+    /// dropped otherwise.
+    pub fn from_string(s: String) -> Result<Symbol<V>, V::Err> {
+        let value = validate::<V>(&s)?;
+        Ok(Symbol(intern::<V>(value).0, PhantomData))
+    }
 
-    impl Validator for AnyString {
-        // Use an error from standard library to make example shorter
-        type Err = ::std::string::ParseError;
-        fn validate_symbol(_: &str) -> Result<(), Self::Err> {
-            Ok(())
+    /// Intern the value skipping validation in release builds
+    ///
+    /// Only use for trusted input that is known to be valid. With debug
+    /// assertions enabled the value is validated and this panics if it's
+    /// invalid.
+    pub fn unchecked_intern(s: &str) -> Symbol<V> {
+        debug_assert!(validate::<V>(s).is_ok(),
+            "unchecked_intern of invalid value {:?}", s);
+        Symbol(intern::<V>(V::normalize(s)).0, PhantomData)
+    }
+
+    /// Intern the value without validating it
+    ///
+    /// For hot paths where the value is known to be valid, e.g. it comes
+    /// from another symbol or a field validated by the protocol parser.
+    /// With debug assertions enabled the value is validated anyway.
+    ///
+    /// # Safety
+    ///
+    /// `V::validate_symbol` must accept `s`, and its length must not
+    /// exceed `V::MAX_LEN` and `global_max_len()`. Code relying on symbols
+    /// being valid (including unsafe code) may misbehave otherwise.
+    pub unsafe fn from_str_unchecked(s: &str) -> Symbol<V> {
+        debug_assert!(validate::<V>(s).is_ok(),
+            "from_str_unchecked of invalid value {:?}", s);
+        Symbol(intern::<V>(V::normalize(s)).0, PhantomData)
+    }
+
+    /// Intern an owned string without validating it
+    ///
+    /// # Safety
+    ///
+    /// Same as for `from_str_unchecked`.
+    pub unsafe fn from_string_unchecked(s: String) -> Symbol<V> {
+        debug_assert!(validate::<V>(&s).is_ok(),
+            "from_string_unchecked of invalid value {:?}", s);
+        Symbol(intern::<V>(V::normalize(&s)).0, PhantomData)
+    }
+
+    /// Format the arguments and intern the result
+    ///
+    /// Unless the arguments are a plain string literal, the value is
+    /// formatted into a new string, which is then handled like in
+    /// `from_string`. Usually used via the `intern_format!` macro.
+    pub fn intern_fmt(args: fmt::Arguments) -> Result<Symbol<V>, V::Err> {
+        match args.as_str() {
+            Some(s) => FromStr::from_str(s),
+            None => Symbol::from_string(::alloc::fmt::format(args)),
         }
     }
 
-    impl Validator for AlphaNumString {
-        // Use an error from standard library to make example shorter
-        type Err = io::Error;
-        fn validate_symbol(s: &str) -> Result<(), Self::Err> {
-            if s.chars().any(|c| !c.is_alphanumeric()) {
-                return Err(io::Error::new(io::ErrorKind::InvalidData,
-                    "Character is not alphanumeric"));
+    /// Returns the number identifying the value in the pool
+    ///
+    /// Ids are small, so they can be used as indexes of flat arrays: they
+    /// are reused after values are dropped, and are allocated densely
+    /// (with the `sharded` feature ids are dense within each shard). The
+    /// id doesn't change while the value is alive. Each pool (i.e. each
+    /// validator type and each `Interner`) has its own ids.
+    pub fn id(&self) -> u32 {
+        self.0.head.id
+    }
+
+    /// Returns the hash of the value computed when it was interned
+    ///
+    /// The hash is the same for equal symbols, even if they are from
+    /// different pools, but differs between runs of the process. Note that
+    /// `Hash` for the symbol still hashes the string, as lookups by `&str`
+    /// in hash maps require that. Use `Prehashed` for the keys to make use
+    /// of this hash.
+    pub fn precomputed_hash(&self) -> u64 {
+        self.0.head.hash
+    }
+
+    /// Returns `true` if the symbol is from the pool of `V`
+    pub(crate) fn in_shared_pool(&self) -> bool {
+        self.is_from(&pool::<V>().shared_ref())
+    }
+
+    /// Returns `true` if the symbol is from the `pool`
+    pub(crate) fn is_from(&self, pool: &PoolRef) -> bool {
+        self.0.head.pool.same(pool)
+    }
+
+    /// Returns the symbol with the id, if it's alive
+    ///
+    /// Only symbols from the pool of this type can be found, not the ones
+    /// from an `Interner`.
+    pub fn from_id(id: u32) -> Option<Symbol<V>> {
+        pool::<V>().get_by_id(id).map(|a| Symbol(a, PhantomData))
+    }
+
+    /// Returns the symbol if the value is interned already
+    ///
+    /// Never inserts anything into the pool, so it's safe to call with
+    /// untrusted input. The value is normalized but not validated, as only
+    /// valid values can be interned.
+    pub fn get(s: &str) -> Option<Symbol<V>> {
+        let s = V::normalize(s);
+        let key = &V::fold(&s)[..];
+        if let Some(sym) = V::static_atoms().and_then(|a| a.lookup(key)) {
+            return Some(sym.clone());
+        }
+        let pool = pool::<V>();
+        #[cfg(feature = "arc-swap")]
+        {
+            if let Some(a) = pool.snapshot.lookup(key) {
+                return Some(Symbol(a, PhantomData));
             }
-            Ok(())
         }
+        let value = pool.read(key).get(key).and_then(|w| w.upgrade());
+        value.map(|a| Symbol(a, PhantomData))
     }
 
-    #[test]
-    fn eq() {
-        assert_eq!(Atom::from("x"), Atom::from("x"));
+    /// Same as `from_str` but also tells whether value was interned before
+    ///
+    /// The flag is `true` if some symbol with this value was alive (a cache
+    /// hit) and `false` if the value was newly added to the pool.
+    pub fn intern_tracked(s: &str) -> Result<(Symbol<V>, bool), V::Err> {
+        let s = validate::<V>(s)?;
+        let (value, hit) = intern::<V>(s);
+        Ok((Symbol(value, PhantomData), hit))
     }
 
-    #[test]
-    fn ord() {
-        assert!(Atom::from("a") < Atom::from("b"));
+    /// Intern a value and tell whether it was interned before
+    ///
+    /// This is handy to keep side data in sync with the pool:
+    ///
+    /// ```
+    /// # use std::collections::HashMap;
+    /// # use string_intern::{Validator, Symbol, SymbolEntry};
+    /// # struct Tag;
+    /// # impl Validator for Tag {
+    /// #     type Err = ::std::string::ParseError;
+    /// #     fn validate_symbol(_: &str) -> Result<(), Self::Err> { Ok(()) }
+    /// # }
+    /// let mut metadata = HashMap::new();
+    /// for name in &["a", "b", "a"] {
+    ///     if let SymbolEntry::Inserted(sym) = Symbol::<Tag>::entry(name)? {
+    ///         metadata.insert(sym, name.len());
+    ///     }
+    /// }
+    /// assert_eq!(metadata.len(), 2);
+    /// # Ok::<(), ::std::string::ParseError>(())
+    /// ```
+    pub fn entry(s: &str) -> Result<SymbolEntry<V>, V::Err> {
+        let (sym, hit) = Symbol::intern_tracked(s)?;
+        if hit {
+            Ok(SymbolEntry::Existing(sym))
+        } else {
+            Ok(SymbolEntry::Inserted(sym))
+        }
     }
 
-    #[test]
-    fn clone() {
-        assert_eq!(Atom::from("x").clone(), Atom::from("x"));
+    /// Split the string by `sep` and intern each part
+    ///
+    /// All parts are validated first, so either all of them are interned or
+    /// the first validation error is returned. The write lock is acquired
+    /// only once for the whole string. Empty parts are kept.
+    pub fn intern_split(s: &str, sep: char) -> Result<Vec<Symbol<V>>, V::Err> {
+        Symbol::intern_all(s.split(sep))
     }
 
-    #[test]
-    fn hash() {
-        use std::collections::HashMap;
-        let mut h = HashMap::new();
-        h.insert(Atom::from("x"), 123);
-        assert_eq!(h.get("x"), Some(&123));
-        assert_eq!(h.get(&Atom::from("x")), Some(&123));
-        assert_eq!(h.get("y"), None);
-        assert_eq!(h.get(&Atom::from("y")), None);
+    /// Intern all the values from `iter`
+    ///
+    /// All values are validated first, so either all of them are interned
+    /// or the first validation error is returned. The write lock is
+    /// acquired only once for the whole batch, which is much faster than
+    /// interning values one by one when loading large dictionaries.
+    pub fn intern_all<I, S>(iter: I) -> Result<Vec<Symbol<V>>, V::Err>
+        where I: IntoIterator<Item=S>, S: AsRef<str>,
+    {
+        // Input is kept until the lock is released, as dropping it (e.g. if
+        // it's a symbol itself) while the lock is held could deadlock
+        let input = iter.into_iter().collect::<Vec<_>>();
+        let values = input.iter()
+            .map(|item| validate::<V>(item.as_ref()))
+            .collect::<Result<Vec<_>, _>>()?;
+        let pool = pool::<V>();
+        let owner = pool.shared_ref();
+        let mut atoms = pool.write_all();
+        Ok(values.into_iter()
+            .map(|value| {
+                let key = fold::<V>(&value);
+                let k = key.as_deref().unwrap_or(&value);
+                let shard = atoms.get(k);
+                Symbol(intern_locked(shard, value, key.as_deref(), &owner).0,
+                       PhantomData)
+            })
+            .collect())
     }
 
-    #[test]
-    fn encode() {
-        assert_eq!(json::encode(&Atom::from("xyz")).unwrap(),
-                   r#""xyz""#);
+    /// Validate that `buf` is UTF-8 and intern it
+    ///
+    /// The slice itself is used for lookup, so no allocation is made if
+    /// the value is interned already. This allows to tokenize a stream
+    /// into symbols reusing a single read buffer.
+    pub fn intern_from_buf(buf: &[u8]) -> Result<Symbol<V>, FromBufError<V>> {
+        let s = str::from_utf8(buf).map_err(FromBufError::Utf8)?;
+        let s = validate_limited::<V>(s)?;
+        Ok(Symbol(intern::<V>(s).0, PhantomData))
     }
-    #[test]
-    fn decode() {
-        assert_eq!(json::decode::<Atom>(r#""xyz""#).unwrap(),
-                   Atom::from("xyz"));
+
+    /// Same as `from_str` but reports allocation failure as an error
+    ///
+    /// Growing the pool is done with `try_reserve` so failing to allocate
+    /// it returns `TryInternError::Alloc` instead of aborting the process.
+    /// Nothing is allocated if the value is interned already.
+    ///
+    /// This is best effort: stable Rust has no fallible `Arc::new`, so
+    /// allocating the string and the reference counted value still aborts
+    /// on failure.
+    pub fn try_intern(s: &str) -> Result<Symbol<V>, TryInternError<V>> {
+        let s = &validate_limited::<V>(s)?[..];
+        let key = fold::<V>(s);
+        let k = key.as_deref().unwrap_or(s);
+        let pool = pool::<V>();
+        if let Some(entry) = pool.read(k).get(k) {
+            if let Some(a) = entry.upgrade() {
+                pool.touch(entry);
+                scope::pin(&a);
+                return Ok(Symbol(a, PhantomData));
+            }
+        }
+        let mut atoms = pool.write(k);
+        atoms.try_reserve(1).map_err(TryInternError::Alloc)?;
+        Ok(Symbol(intern_locked(&mut atoms, Cow::Borrowed(s), key.as_deref(),
+                                &pool.shared_ref()).0,
+                  PhantomData))
     }
 
-    #[test]
-    fn encode_serde() {
-        assert_eq!(serde_json::to_string(&Atom::from("xyz")).unwrap(),
-                   r#""xyz""#);
+    /// Intern each string from `iter` and add the symbols to `col`
+    ///
+    /// The write lock is acquired once for the whole batch. Interning stops
+    /// at the first value that fails validation, the values before it are
+    /// still added to the collection. The collection is extended after the
+    /// lock is released, so it's fine if it drops some symbols.
+    pub fn extend_interned<C, I, S>(col: &mut C, iter: I)
+        -> Result<(), V::Err>
+        where C: Extend<Symbol<V>>, I: IntoIterator<Item=S>, S: AsRef<str>,
+    {
+        // Input is collected beforehand, as dropping it (e.g. if it's
+        // a symbol itself) while the lock is held could deadlock
+        let input = iter.into_iter().collect::<Vec<_>>();
+        let mut items = Vec::with_capacity(input.len());
+        let mut result = Ok(());
+        {
+            let pool = pool::<V>();
+            let owner = pool.shared_ref();
+            let mut atoms = pool.write_all();
+            for item in &input {
+                let item = match validate::<V>(item.as_ref()) {
+                    Ok(item) => item,
+                    Err(e) => {
+                        result = Err(e);
+                        break;
+                    }
+                };
+                let key = fold::<V>(&item);
+                let shard = atoms.get(key.as_deref().unwrap_or(&item));
+                items.push(Symbol(
+                    intern_locked(shard, item, key.as_deref(), &owner).0,
+                    PhantomData));
+            }
+        }
+        col.extend(items);
+        result
     }
 
-    #[test]
-    fn decode_serde() {
-        assert_eq!(serde_json::from_str::<Atom>(r#""xyz""#).unwrap(),
+    /// Same as `from_str` but the error contains the offending string
+    ///
+    /// This is useful when error is propagated far from the point where
+    /// value is parsed, for example in deserialization of a large
+    /// configuration file.
+    pub fn parse_with_context(s: &str)
+        -> Result<Symbol<V>, SymbolParseError<V>>
+    {
+        s.parse().map_err(|e| SymbolParseError::new(s, e))
+    }
+
+    /// Same as `from_str` but length limits are reported as `TooLong`
+    ///
+    /// Unlike `from_str`, this enforces `global_max_len()` even if the
+    /// validator doesn't set `Validator::TOO_LONG`.
+    pub fn parse_limited(s: &str) -> Result<Symbol<V>, ValidationError<V>> {
+        let s = validate_limited::<V>(s)?;
+        Ok(Symbol(intern::<V>(s).0, PhantomData))
+    }
+
+    /// Join `parts` with `sep` and intern the result
+    ///
+    /// The whole joined string is validated, not individual parts. It's
+    /// built once and then handled like in `from_string`.
+    pub fn concat<I, S>(parts: I, sep: &str) -> Result<Symbol<V>, V::Err>
+        where I: IntoIterator<Item=S>, S: AsRef<str>,
+    {
+        let mut buf = String::new();
+        for (idx, part) in parts.into_iter().enumerate() {
+            if idx > 0 {
+                buf.push_str(sep);
+            }
+            buf.push_str(part.as_ref());
+        }
+        Symbol::from_string(buf)
+    }
+
+    /// Returns the length of the value in bytes
+    ///
+    /// Same as `str::len` but the length is stored next to the reference
+    /// counter, so it doesn't need to reach the string itself.
+    pub fn len(&self) -> usize {
+        self.0.head.len
+    }
+
+    /// Returns `true` if the value is an empty string
+    pub fn is_empty(&self) -> bool {
+        self.0.head.len == 0
+    }
+
+    /// Returns pointer to the interned bytes and their length
+    ///
+    /// Meant for passing the value through FFI without copying. The bytes
+    /// are not moved while any symbol with this value is alive, so the
+    /// pointer stays valid as long as this symbol (or any of its clones) is
+    /// held. The bytes are UTF-8 and are not nul-terminated.
+    pub fn as_ptr_len(&self) -> (*const u8, usize) {
+        (self.0.as_str().as_ptr(), self.len())
+    }
+
+    /// Copy the value into a new boxed string, detached from the pool
+    pub fn to_boxed_str(&self) -> Box<str> {
+        Box::from(&self[..])
+    }
+
+    /// Copy the value into a new `String`, detached from the pool
+    ///
+    /// This is the same as `to_string()` but doesn't go through the
+    /// formatting machinery.
+    pub fn to_owned_string(&self) -> String {
+        String::from(&self[..])
+    }
+
+    /// Returns a reference to the value as a `String`
+    ///
+    /// Useful for APIs that require `&String` rather than `&str`. The value
+    /// is stored in the same allocation as the reference counter rather
+    /// than in a `String`, so it's copied on the first call, and the copy
+    /// is kept until the value is dropped. Same as `Borrow<String>`, unless
+    /// the validator overrides `fold`, then the key is borrowed instead.
+    pub fn as_string(&self) -> &String {
+        let head = &self.0.head;
+        // the copy is kept by the pool until the id is released, which is
+        // when the value is dropped
+        unsafe { &*head.pool.string(head.id, false, &self[..]) }
+    }
+
+    /// Copy the value into a new reference-counted string
+    ///
+    /// The value is stored in the same allocation as the reference counter
+    /// of the symbol, so there is no `Arc<str>` to share, and a new one is
+    /// allocated on every call. It doesn't keep the symbol alive.
+    pub fn as_arc(&self) -> Arc<str> {
+        Arc::from(&self[..])
+    }
+
+    /// Convert symbol into a symbol of another type
+    ///
+    /// The value is validated by the validator `W` and is interned in the
+    /// pool of `W` (each validator type has its own pool), so the string
+    /// is copied unless it's interned as `W` already.
+    pub fn cast<W: Validator + ?Sized>(&self) -> Result<Symbol<W>, W::Err> {
+        let value = validate::<W>(self)?;
+        Ok(Symbol(intern::<W>(value).0, PhantomData))
+    }
+
+    /// Reserve capacity for at least `additional` more interned values
+    ///
+    /// This is useful to avoid rehashing the pool (which is done under the
+    /// write lock) when many values are going to be interned at once.
+    pub fn reserve(additional: usize) {
+        pool::<V>().reserve(additional);
+    }
+
+    /// Returns the number of values the pool can hold without reallocating
+    pub fn capacity() -> usize {
+        pool::<V>().capacity()
+    }
+
+    /// Bound the size of the pool of this type
+    ///
+    /// Useful when symbols are created from untrusted input. See
+    /// `PoolLimits` for how the limits are enforced. Lookups served by the
+    /// `arc-swap` snapshot don't mark values as used.
+    pub fn set_limits(limits: PoolLimits) {
+        pool::<V>().set_limits(&limits);
+    }
+
+    /// Set what to do with the pool entry when the last symbol is dropped
+    ///
+    /// `DropPolicy::Deferred` makes dropping cheap for workloads with lots
+    /// of short-lived symbols, at the cost of keeping dead entries in the
+    /// pool until they are swept. The policy only applies to the pool of
+    /// this type, and is checked at the time of the drop.
+    pub fn set_drop_policy(policy: DropPolicy) {
+        pool::<V>().set_drop_policy(policy);
+    }
+
+    /// Returns the policy set by `set_drop_policy`
+    pub fn drop_policy() -> DropPolicy {
+        pool::<V>().drop_policy()
+    }
+
+    /// Enable or disable the thread-local cache for this type
+    ///
+    /// The cache is enabled by default, and only exists with the
+    /// `thread-cache` feature. See also `InternOptions::thread_cache` to
+    /// disable it temporarily for all types.
+    pub fn set_thread_cache(enabled: bool) {
+        pool::<V>().set_thread_cache(enabled);
+    }
+
+    /// Remove all values from the pool of this type
+    ///
+    /// Meant for resetting the state between tests. Symbols which are
+    /// alive stay valid and still compare equal to the same value interned
+    /// after clearing, but they no longer share the buffer with it. Pinned
+    /// values are detached the same way but are not freed.
+    ///
+    /// Returns the number of removed entries.
+    pub fn clear() -> usize {
+        pool::<V>().clear()
+    }
+
+    /// Returns statistics of the pool of this type
+    ///
+    /// Useful to watch for leaks in long-running processes. Shards of the
+    /// pool are counted one by one, so with the `sharded` feature numbers
+    /// may be slightly inconsistent under concurrent updates.
+    pub fn stats() -> PoolStats {
+        pool::<V>().stats()
+    }
+
+    /// Remove entries of values that are not alive any more
+    ///
+    /// Returns the number of removed entries. Only useful with
+    /// `DropPolicy::Deferred`, as otherwise entries are removed on drop.
+    pub fn prune() -> usize {
+        pool::<V>().write_shards().map(|mut atoms| atoms.sweep()).sum()
+    }
+
+    /// Returns a snapshot of all currently interned symbols of this type
+    ///
+    /// Order is unspecified.
+    ///
+    /// Note: every returned symbol is pinned (can't be freed) as long as
+    /// the returned `Vec` is alive.
+    pub fn iter_interned() -> Vec<Symbol<V>> {
+        let mut result = Vec::new();
+        for atoms in pool::<V>().read_shards() {
+            result.extend(atoms.values()
+                .filter_map(|w| w.upgrade())
+                .map(|a| Symbol(a, PhantomData)));
+        }
+        result
+    }
+
+    /// Calls `f` for each currently interned value
+    ///
+    /// Unlike `iter_interned` this doesn't allocate and doesn't keep values
+    /// alive: the callback is invoked while the read lock on the pool is
+    /// held. Order is unspecified.
+    ///
+    /// The callback must not intern or drop symbols of this type, as that
+    /// requires locking the pool again, which may deadlock. If the
+    /// validator overrides `Validator::fold`, keys are passed rather than
+    /// the values themselves.
+    pub fn for_each_interned<F: FnMut(&str)>(mut f: F) {
+        for atoms in pool::<V>().read_shards() {
+            for (key, weak) in atoms.iter() {
+                if weak.strong_count() > 0 {
+                    f(key);
+                }
+            }
+        }
+    }
+
+    /// Same as `iter_interned` but symbols are sorted by their value
+    ///
+    /// Sorting is done after the lock is released.
+    pub fn iter_interned_sorted() -> Vec<Symbol<V>> {
+        let mut result = Symbol::iter_interned();
+        result.sort_unstable_by(|a, b| a[..].cmp(&b[..]));
+        result
+    }
+
+    /// Returns a human-readable report of the interned values
+    ///
+    /// Each line contains a value, its strong count (number of symbols
+    /// referring to it) and its weak count. Only live values of this type
+    /// are listed. This is purely diagnostic, the format may change at any
+    /// time.
+    pub fn debug_dump() -> String {
+        use core::fmt::Write;
+        // Counts are read without upgrading the references, so this doesn't
+        // influence the numbers and doesn't keep anything alive
+        let mut entries: Vec<(String, usize, usize)> = Vec::new();
+        for atoms in pool::<V>().read_shards() {
+            entries.extend(atoms.iter()
+                .filter(|&(_, w)| w.strong_count() > 0)
+                .map(|(k, w)| (k.to_string(), w.strong_count(),
+                               w.weak_count())));
+        }
+        // keys are unique, so there is no need to compare counts
+        entries.sort_by(|a, b| a.0[..].cmp(&b.0[..]));
+        let mut buf = String::new();
+        for (s, strong, weak) in entries {
+            writeln!(&mut buf, "{:?} strong={} weak={}", &s[..], strong, weak)
+                .expect("write to string");
+        }
+        buf
+    }
+}
+
+/// Format and intern a symbol, like `format!` does for a `String`
+///
+/// Returns `Result<Symbol<V>, V::Err>`, where symbol type is inferred.
+///
+/// ```
+/// # #[macro_use] extern crate string_intern;
+/// # use string_intern::{Validator, Symbol};
+/// # struct UserIdSymbol;
+/// # impl Validator for UserIdSymbol {
+/// #     type Err = ::std::string::ParseError;
+/// #     fn validate_symbol(_: &str) -> Result<(), Self::Err> { Ok(()) }
+/// # }
+/// type UserId = Symbol<UserIdSymbol>;
+/// # fn main() {
+/// let id = 1;
+/// let user: UserId = intern_format!("user{}", id).unwrap();
+/// assert_eq!(user, UserId::from("user1"));
+/// # }
+/// ```
+#[macro_export]
+macro_rules! intern_format {
+    ($($arg:tt)*) => {
+        $crate::Symbol::intern_fmt(format_args!($($arg)*))
+    };
+}
+
+#[cfg(test)]
+mod test {
+    use std::io;
+    use rustc_serialize::json;
+    use {Validator, Symbol};
+    use serde_json;
+
+    #[allow(dead_code)]
+    struct AnyString;
+    #[allow(dead_code)]
+    struct AlphaNumString;
+    type Atom = Symbol<AnyString>;
+    type AlphaNum = Symbol<AlphaNumString>;
+
+    impl Validator for AnyString {
+        // Use an error from standard library to make example shorter
+        type Err = ::std::string::ParseError;
+        fn validate_symbol(_: &str) -> Result<(), Self::Err> {
+            Ok(())
+        }
+    }
+
+    impl Validator for AlphaNumString {
+        // Use an error from standard library to make example shorter
+        type Err = io::Error;
+        fn validate_symbol(s: &str) -> Result<(), Self::Err> {
+            if s.chars().any(|c| !c.is_alphanumeric()) {
+                return Err(io::Error::new(io::ErrorKind::InvalidData,
+                    "Character is not alphanumeric"));
+            }
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn eq() {
+        assert_eq!(Atom::from("x"), Atom::from("x"));
+    }
+
+    #[test]
+    fn eq_matches_str() {
+        let names = ["eq_ptr_a", "eq_ptr_b", "eq_ptr_a", "", "eq_ptr_ab",
+                     "eq_ptr_b"];
+        let atoms = names.iter()
+            .map(|n| n.parse::<Atom>().unwrap())
+            .collect::<Vec<_>>();
+        for (a, x) in names.iter().zip(&atoms) {
+            for (b, y) in names.iter().zip(&atoms) {
+                assert_eq!(x == y, a == b);
+                assert_eq!(x == y, x.as_ptr() == y.as_ptr());
+            }
+        }
+    }
+
+    #[test]
+    fn eq_different_pools() {
+        use Interner;
+        let pool = Interner::<AnyString>::new();
+        let x = Atom::from("eq_pools");
+        let y = pool.intern("eq_pools").unwrap();
+        assert!(x.as_ptr() != y.as_ptr());
+        assert_eq!(x, y);
+        assert_eq!(y, x);
+        assert!(x != pool.intern("eq_pools_other").unwrap());
+    }
+
+    #[test]
+    fn ord() {
+        assert!(Atom::from("a") < Atom::from("b"));
+    }
+
+    #[test]
+    fn eq_str() {
+        let x = Atom::from("eq_str");
+        assert!(x == "eq_str");
+        assert!("eq_str" == x);
+        assert!(x == *"eq_str");
+        assert!(*"eq_str" == x);
+        assert!(x != "eq_st");
+        assert!("eq_str_" != x);
+    }
+
+    #[test]
+    fn ord_str() {
+        let x = Atom::from("m");
+        assert!(x < "n");
+        assert!(x > "l");
+        assert!("a" < x);
+        assert!("z" > x);
+        assert!(x <= "m");
+        assert!(x >= "m");
+        assert!(*"a" < x);
+        assert!(x < *"z");
+        // equal prefix
+        assert!(x < "ma");
+        assert!("ma" > x);
+        assert!(Atom::from("ma") > "m");
+        assert!(Atom::from("") < "m");
+    }
+
+    #[test]
+    fn clone() {
+        assert_eq!(Atom::from("x").clone(), Atom::from("x"));
+    }
+
+    #[test]
+    fn hash() {
+        use std::collections::HashMap;
+        let mut h = HashMap::new();
+        h.insert(Atom::from("x"), 123);
+        assert_eq!(h.get("x"), Some(&123));
+        assert_eq!(h.get(&Atom::from("x")), Some(&123));
+        assert_eq!(h.get("y"), None);
+        assert_eq!(h.get(&Atom::from("y")), None);
+    }
+
+    #[test]
+    fn hash_matches_str() {
+        use std::collections::hash_map::DefaultHasher;
+        use std::hash::{Hash, Hasher};
+        fn hash<T: Hash + ?Sized>(v: &T) -> u64 {
+            let mut h = DefaultHasher::new();
+            v.hash(&mut h);
+            h.finish()
+        }
+        let x = Atom::from("hash_matches");
+        assert_eq!(hash(&x), hash("hash_matches"));
+        assert_eq!(hash(&x), hash(&String::from("hash_matches")));
+        assert_eq!(hash(&x), hash(&Atom::from("hash_matches")));
+    }
+
+    #[test]
+    fn heterogeneous_lookup() {
+        use std::collections::{HashMap, HashSet, BTreeMap};
+        let mut h = HashMap::new();
+        h.insert(Atom::from("lookup_a"), 1);
+        h.insert(Atom::from("lookup_b"), 2);
+        assert_eq!(h.get("lookup_a"), Some(&1));
+        assert_eq!(h.get(&String::from("lookup_a")), Some(&1));
+        assert_eq!(h.get(&Atom::from("lookup_a")), Some(&1));
+        assert_eq!(h.get(&"lookup_a".parse::<Atom>().unwrap()), Some(&1));
+        assert_eq!(h.get("lookup_c"), None);
+
+        let s = vec![Atom::from("lookup_a")].into_iter()
+            .collect::<HashSet<_>>();
+        assert!(s.contains("lookup_a"));
+        assert!(s.contains(&Atom::from("lookup_a")));
+        assert!(!s.contains("lookup_b"));
+
+        let mut b = BTreeMap::new();
+        b.insert(Atom::from("lookup_a"), 1);
+        b.insert(Atom::from("lookup_b"), 2);
+        assert_eq!(b.get("lookup_b"), Some(&2));
+        assert_eq!(b.get(&Atom::from("lookup_b")), Some(&2));
+    }
+
+    #[test]
+    fn encode() {
+        assert_eq!(json::encode(&Atom::from("xyz")).unwrap(),
+                   r#""xyz""#);
+    }
+    #[test]
+    fn decode() {
+        assert_eq!(json::decode::<Atom>(r#""xyz""#).unwrap(),
+                   Atom::from("xyz"));
+    }
+
+    #[test]
+    fn encode_serde() {
+        assert_eq!(serde_json::to_string(&Atom::from("xyz")).unwrap(),
+                   r#""xyz""#);
+    }
+
+    #[test]
+    fn decode_serde() {
+        assert_eq!(serde_json::from_str::<Atom>(r#""xyz""#).unwrap(),
                    Atom::from("xyz"));
     }
 
     #[test]
-    #[should_panic(message="static strings used as atom is invalid")]
+    fn unsized_validator() {
+        use {WeakSymbol, InternSeq, SymbolBytes};
+        // markers may be unsized, all impls must accept them
+        #[allow(dead_code)]
+        struct Unsized(str);
+        impl Validator for Unsized {
+            type Err = ::std::string::ParseError;
+            fn validate_symbol(_: &str) -> Result<(), Self::Err> {
+                Ok(())
+            }
+        }
+        type U = Symbol<Unsized>;
+        let x = U::from("unsized");
+        assert_eq!(json::encode(&x).unwrap(), r#""unsized""#);
+        assert_eq!(json::decode::<U>(r#""unsized""#).unwrap(), x);
+        assert_eq!(serde_json::to_string(&x).unwrap(), r#""unsized""#);
+        assert_eq!(serde_json::from_str::<U>(r#""unsized""#).unwrap(), x);
+        let weak: WeakSymbol<Unsized> = x.downgrade();
+        assert_eq!(serde_json::to_string(&weak).unwrap(), r#""unsized""#);
+        let seq: InternSeq<Unsized> =
+            serde_json::from_str(r#"["unsized", "unsized"]"#).unwrap();
+        assert_eq!(seq.0, vec![x.clone(), x.clone()]);
+        let bytes = ::bincode::serialize(&SymbolBytes(x.clone())).unwrap();
+        let back: SymbolBytes<Unsized> = ::bincode::deserialize(&bytes)
+            .unwrap();
+        assert_eq!(back.0, x);
+    }
+
+    #[test]
+    fn decode_serde_bytes() {
+        use serde::de::{Deserialize, value::{BytesDeserializer, Error}};
+        let de = BytesDeserializer::<Error>::new(b"bytes_serde");
+        assert_eq!(Atom::deserialize(de).unwrap(), Atom::from("bytes_serde"));
+        let de = BytesDeserializer::<Error>::new(b"\xff");
+        assert!(Atom::deserialize(de).is_err());
+        let de = BytesDeserializer::<Error>::new(b"a-b");
+        assert!(AlphaNum::deserialize(de).is_err());
+    }
+
+    #[test]
+    fn binary_as_bytes() {
+        use bincode;
+        struct Raw;
+        impl Validator for Raw {
+            type Err = ::std::string::ParseError;
+            const BINARY_AS_BYTES: bool = true;
+            fn validate_symbol(_: &str) -> Result<(), Self::Err> {
+                Ok(())
+            }
+        }
+        let x = Symbol::<Raw>::from("binary_raw");
+        let data = bincode::serialize(&x).unwrap();
+        assert_eq!(data, bincode::serialize(&b"binary_raw"[..]).unwrap());
+        assert_eq!(bincode::deserialize::<Symbol<Raw>>(&data).unwrap(), x);
+        // human readable formats still get a string
+        assert_eq!(serde_json::to_string(&x).unwrap(), r#""binary_raw""#);
+        assert_eq!(serde_json::from_str::<Symbol<Raw>>(r#""binary_raw""#)
+                   .unwrap(), x);
+    }
+
+    #[test]
+    fn decode_serde_owned() {
+        use serde_json::Value;
+        // `Value` hands strings over with `visit_string`
+        let value = Value::String(String::from("owned_serde"));
+        assert_eq!(serde_json::from_value::<Atom>(value).unwrap(),
+                   Atom::from("owned_serde"));
+        let value = Value::String(String::from("a-b"));
+        assert!(serde_json::from_value::<AlphaNum>(value).is_err());
+        // borrowed strings go through `visit_str`
+        let json = String::from(r#""borrowed_serde""#);
+        assert_eq!(serde_json::from_str::<Atom>(&json).unwrap(),
+                   Atom::from("borrowed_serde"));
+    }
+
+    #[test]
+    fn short_shares_bytes() {
+        let x = Atom::from("sh");
+        let y: Atom = String::from("sh").parse().unwrap();
+        assert_eq!(x.as_ptr(), y.as_ptr());
+        let long = "a_long_string_which_does_not_fit_inline_buffer";
+        assert_eq!(Atom::from(long).as_ptr(), Atom::from(long).as_ptr());
+    }
+
+    #[test]
+    fn empty() {
+        let x = Atom::from("");
+        assert_eq!(&x[..], "");
+        let (y, hit) = Atom::intern_tracked("").unwrap();
+        assert!(hit);
+        assert_eq!(x, y);
+        assert_eq!(x.as_ptr(), y.as_ptr());
+        drop((x, y));
+        // other tests may intern an empty string concurrently, so only check
+        // that it still works after references are dropped
+        assert_eq!(&Atom::from("")[..], "");
+    }
+
+    #[test]
+    fn concat() {
+        let x = Atom::concat(["concat", "a", "b"], ".").unwrap();
+        assert_eq!(&x[..], "concat.a.b");
+        assert_eq!(x, Atom::from("concat.a.b"));
+        assert_eq!(x.as_ptr(), Atom::from("concat.a.b").as_ptr());
+        let y = Atom::concat(vec![String::from("concat"), "ab".into()], "")
+            .unwrap();
+        assert_eq!(&y[..], "concatab");
+        let e = Atom::concat(Vec::<&str>::new(), ".").unwrap();
+        assert_eq!(&e[..], "");
+    }
+
+    #[test]
+    fn concat_validates() {
+        assert!(AlphaNum::concat(["a", "b"], "").is_ok());
+        assert!(AlphaNum::concat(["a", "b"], "-").is_err());
+    }
+
+    #[test]
+    fn as_arc_copies() {
+        let x = Atom::from("as_arc_copies");
+        let (a, b) = (x.as_arc(), x.as_arc());
+        assert_eq!(&a[..], "as_arc_copies");
+        assert_eq!(&b[..], "as_arc_copies");
+        assert!(a.as_ptr() != x.as_ptr());
+        assert!(!::std::sync::Arc::ptr_eq(&a, &b));
+    }
+
+    #[test]
+    fn as_string() {
+        use std::borrow::Borrow;
+        #[allow(clippy::ptr_arg)]
+        fn takes_string(s: &String) -> (usize, *const u8) {
+            (s.len(), s.as_ptr())
+        }
+        let x = Atom::from("as_string");
+        assert_eq!(x.as_string(), "as_string");
+        let (len, ptr) = takes_string(x.as_string());
+        assert_eq!(len, 9);
+        assert_eq!(ptr, Atom::from("as_string").as_string().as_ptr());
+        let b: &String = x.borrow();
+        assert!(::std::ptr::eq(b, x.as_string()));
+    }
+
+    #[test]
+    fn len() {
+        for s in &["", "len", "длина", "長さ", "a\u{1F600}b"] {
+            let x: Atom = s.parse().unwrap();
+            assert_eq!(x.len(), s.len());
+            assert_eq!(x.len(), x.as_ref().len());
+            assert_eq!(x.is_empty(), s.is_empty());
+        }
+    }
+
+    #[test]
+    fn as_ptr_len() {
+        let x = Atom::from("as_ptr_len");
+        let (ptr, len) = x.as_ptr_len();
+        assert_eq!(ptr, x.as_bytes().as_ptr());
+        assert_eq!(len, 10);
+        let keep = x.clone();
+        let other: Atom = "as_ptr_len".parse().unwrap();
+        drop((x, other));
+        assert_eq!(keep.as_ptr_len(), (ptr, len));
+        let bytes = unsafe { ::std::slice::from_raw_parts(ptr, len) };
+        assert_eq!(bytes, b"as_ptr_len");
+    }
+
+    #[test]
+    fn string_roundtrip() {
+        use std::convert::{TryFrom, TryInto};
+        let x = Atom::from("string_roundtrip");
+        let s: String = x.clone().into();
+        assert_eq!(s, "string_roundtrip");
+        assert!(s.as_ptr() != x.as_ptr());
+        let y = Atom::try_from(s).unwrap();
+        assert_eq!(y, x);
+        assert_eq!(y.as_ptr(), x.as_ptr());
+        let z: Result<AlphaNum, _> = String::from("string-x").try_into();
+        assert!(z.is_err());
+    }
+
+    #[test]
+    fn try_from() {
+        use std::borrow::Cow;
+        use std::convert::{TryFrom, TryInto};
+        fn convert<T: TryInto<AlphaNum>>(v: T) -> Result<AlphaNum, T::Error> {
+            v.try_into()
+        }
+        let x = AlphaNum::try_from("tryfrom").unwrap();
+        assert_eq!(&x[..], "tryfrom");
+        assert_eq!(convert(Cow::Borrowed("tryfrom")).unwrap(), x);
+        let owned: Cow<str> = Cow::Owned(String::from("tryfrom"));
+        assert_eq!(convert(owned).unwrap().as_ptr(), x.as_ptr());
+        assert!(convert("try-from").is_err());
+        assert!(convert(Cow::Borrowed("try-from")).is_err());
+    }
+
+    #[test]
+    fn from_str_unchecked() {
+        let x = unsafe { AlphaNum::from_str_unchecked("unchecked") };
+        let y = unsafe {
+            AlphaNum::from_string_unchecked(String::from("unchecked"))
+        };
+        assert_eq!(&x[..], "unchecked");
+        assert_eq!(x.as_ptr(), y.as_ptr());
+        assert_eq!(x.as_ptr(), AlphaNum::from("unchecked").as_ptr());
+    }
+
+    #[test]
+    #[cfg(debug_assertions)]
+    #[should_panic(expected="from_str_unchecked of invalid value")]
+    fn from_str_unchecked_invalid() {
+        unsafe { AlphaNum::from_str_unchecked("un-checked") };
+    }
+
+    #[test]
+    fn get() {
+        use pool::pool;
+        assert_eq!(Atom::get("get_missing"), None);
+        assert!(pool::<AnyString>().read("get_missing")
+                .get("get_missing").is_none());
+        let x = Atom::from("get_existing");
+        let y = Atom::get("get_existing").unwrap();
+        assert_eq!(x.as_ptr(), y.as_ptr());
+        assert_eq!(x.get(..3), Some("get"));
+        drop((x, y));
+        assert_eq!(Atom::get("get_existing"), None);
+    }
+
+    #[test]
+    fn stats() {
+        // own validator type, so other tests don't change the numbers
+        struct Stats;
+        impl Validator for Stats {
+            type Err = ::std::string::ParseError;
+            fn validate_symbol(_: &str) -> Result<(), Self::Err> {
+                Ok(())
+            }
+        }
+        type S = Symbol<Stats>;
+        assert_eq!(S::stats(), Default::default());
+        let a = S::from("stats_a");
+        let b = S::from("stats_bc");
+        let stats = S::stats();
+        assert_eq!((stats.live, stats.dead, stats.bytes), (2, 0, 15));
+        drop(b);
+        let stats = S::stats();
+        assert_eq!((stats.live, stats.dead), (1, 0));
+        S::prune();
+        assert_eq!(S::stats().bytes, 7);
+        drop(a);
+    }
+
+    #[test]
+    fn clear() {
+        struct Clear;
+        impl Validator for Clear {
+            type Err = ::std::string::ParseError;
+            fn validate_symbol(_: &str) -> Result<(), Self::Err> {
+                Ok(())
+            }
+        }
+        type C = Symbol<Clear>;
+        let a = C::from("clear_a");
+        let b = C::from("clear_b");
+        assert_eq!(C::clear(), 2);
+        assert_eq!(C::stats(), Default::default());
+        assert_eq!(C::get("clear_a"), None);
+        let a2 = C::from("clear_a");
+        assert!(a2.as_ptr() != a.as_ptr());
+        assert_eq!(a, a2);
+        assert!(a != b);
+        drop((a, b));
+        assert_eq!(C::get("clear_a").unwrap().as_ptr(), a2.as_ptr());
+        assert_eq!(C::stats().live, 1);
+    }
+
+    #[test]
+    fn limits() {
+        use PoolLimits;
+        struct Limited;
+        impl Validator for Limited {
+            type Err = ::std::string::ParseError;
+            fn validate_symbol(_: &str) -> Result<(), Self::Err> {
+                Ok(())
+            }
+        }
+        type L = Symbol<Limited>;
+        L::set_limits(PoolLimits::new().max_entries(64).max_bytes(1000));
+        let pinned = L::intern_static("limits_pinned").unwrap();
+        let first = L::from("limits_first");
+        let mut keep = Vec::new();
+        for i in 0..1000 {
+            let x = format!("limits_{}", i).parse::<L>().unwrap();
+            if i % 2 == 0 {
+                keep.push(x);
+            }
+            let stats = L::stats();
+            assert!(stats.live + stats.dead <= 64, "{:?}", stats);
+            assert!(stats.bytes <= 1000, "{:?}", stats);
+        }
+        assert_eq!(L::get("limits_pinned"), Some(pinned.clone()));
+        // the value is evicted but still compares equal
+        let again = L::from("limits_first");
+        assert!(again.as_ptr() != first.as_ptr());
+        assert_eq!(again, first);
+        assert_eq!(L::get("limits_first").unwrap().as_ptr(), again.as_ptr());
+    }
+
+    #[test]
+    fn ids() {
+        struct Ids;
+        impl Validator for Ids {
+            type Err = ::std::string::ParseError;
+            fn validate_symbol(_: &str) -> Result<(), Self::Err> {
+                Ok(())
+            }
+        }
+        type I = Symbol<Ids>;
+        let symbols = (0..100).map(|i| format!("ids_{}", i).parse().unwrap())
+            .collect::<Vec<I>>();
+        let mut ids = symbols.iter().map(|s| s.id()).collect::<Vec<_>>();
+        ids.sort();
+        ids.dedup();
+        assert_eq!(ids.len(), 100);
+        if cfg!(not(feature = "sharded")) {
+            assert_eq!(ids, (0..100).collect::<Vec<_>>());
+        }
+        for s in &symbols {
+            assert_eq!(I::from_id(s.id()).unwrap().as_ptr(), s.as_ptr());
+            assert_eq!(I::from("ids_0").id(), symbols[0].id());
+        }
+        assert_eq!(I::from_id(u32::MAX), None);
+        let id = symbols[5].id();
+        drop(symbols);
+        assert_eq!(I::from_id(id), None);
+    }
+
+    #[test]
+    fn from_string() {
+        let x = Atom::from_string(String::from("from_string")).unwrap();
+        assert_eq!(&x[..], "from_string");
+        let y = Atom::from_string(format!("from_{}", "string")).unwrap();
+        assert_eq!(x.as_ptr(), y.as_ptr());
+        assert!(AlphaNum::from_string(String::from("from-string")).is_err());
+    }
+
+    #[test]
+    fn owned_copies() {
+        let x = Atom::from("owned_copies");
+        let boxed = x.to_boxed_str();
+        let string = x.to_owned_string();
+        assert_eq!(&boxed[..], "owned_copies");
+        assert_eq!(string, "owned_copies");
+        assert!(boxed.as_ptr() != x.as_ptr());
+        assert!(string.as_ptr() != x.as_ptr());
+        assert!(string.as_ptr() != boxed.as_ptr());
+    }
+
+    #[test]
+    fn intern_format() {
+        let n = 42;
+        let x: Atom = intern_format!("fmt_{}_{}", "user", n).unwrap();
+        assert_eq!(x, Atom::from("fmt_user_42"));
+        assert_eq!(x.as_ptr(), Atom::from("fmt_user_42").as_ptr());
+        let y: Atom = intern_format!("fmt_plain").unwrap();
+        assert_eq!(y.as_ptr(), Atom::from("fmt_plain").as_ptr());
+        let z: Result<AlphaNum, _> = intern_format!("fmt-{}", n);
+        assert!(z.is_err());
+    }
+
+    #[test]
+    fn checked() {
+        let x = Atom::checked("checked").unwrap();
+        assert_eq!(x, "checked".parse::<Atom>().unwrap());
+        assert_eq!(x.as_ptr(), Atom::from("checked").as_ptr());
+        assert!(AlphaNum::checked("checked1").is_ok());
+        assert_eq!(AlphaNum::checked("checked-2").unwrap_err().kind(),
+                   "checked-2".parse::<AlphaNum>().unwrap_err().kind());
+    }
+
+    #[test]
+    fn unchecked_intern() {
+        let x = AlphaNum::unchecked_intern("unchecked");
+        assert_eq!(x.as_ptr(), AlphaNum::from("unchecked").as_ptr());
+    }
+
+    #[test]
+    #[cfg(not(debug_assertions))]
+    fn unchecked_intern_skips_validation() {
+        let x = AlphaNum::unchecked_intern("unchecked-invalid");
+        assert_eq!(&x[..], "unchecked-invalid");
+        assert_eq!(x.as_ptr(), Atom::from("unchecked-invalid").as_ptr());
+    }
+
+    #[test]
+    #[cfg(debug_assertions)]
+    #[should_panic(expected="unchecked_intern of invalid value")]
+    fn unchecked_intern_validates_in_debug() {
+        AlphaNum::unchecked_intern("unchecked-invalid");
+    }
+
+    #[test]
+    fn intern_tracked() {
+        let (x, hit) = Atom::intern_tracked("intern_tracked").unwrap();
+        assert!(!hit);
+        let (y, hit) = Atom::intern_tracked("intern_tracked").unwrap();
+        assert!(hit);
+        assert_eq!(x.as_ptr(), y.as_ptr());
+        drop((x, y));
+        let (_, hit) = Atom::intern_tracked("intern_tracked").unwrap();
+        assert!(!hit);
+        assert!(AlphaNum::intern_tracked("intern_tracked").is_err());
+    }
+
+    #[test]
+    fn entry() {
+        use std::collections::HashMap;
+        use SymbolEntry;
+        let mut meta = HashMap::new();
+        let mut inserted = 0;
+        let mut keep = Vec::new();
+        for name in &["entry_a", "entry_b", "entry_a", "entry_b", "entry_c"] {
+            let entry = Atom::entry(name).unwrap();
+            if entry.is_inserted() {
+                inserted += 1;
+                meta.insert(entry.symbol().clone(), name.len());
+            }
+            keep.push(entry.into_symbol());
+        }
+        assert_eq!(inserted, 3);
+        assert_eq!(meta.len(), 3);
+        assert_eq!(meta.get("entry_c"), Some(&7));
+        match Atom::entry("entry_a").unwrap() {
+            SymbolEntry::Existing(s) => assert_eq!(s, "entry_a"),
+            e => panic!("unexpected entry {:?}", e),
+        }
+        assert!(AlphaNum::entry("entry-a").is_err());
+    }
+
+    #[test]
+    fn intern_split() {
+        let parts = Atom::intern_split("split_a,split_b,split_a", ',')
+            .unwrap();
+        assert_eq!(parts, vec![Atom::from("split_a"), Atom::from("split_b"),
+                               Atom::from("split_a")]);
+        assert_eq!(parts[0].as_ptr(), parts[2].as_ptr());
+        assert_eq!(parts[1].as_ptr(), Atom::from("split_b").as_ptr());
+    }
+
+    #[test]
+    fn intern_split_empty() {
+        let parts = Atom::intern_split("split_x,,split_y,", ',').unwrap();
+        assert_eq!(parts, vec!["split_x", "", "split_y", ""]);
+        assert_eq!(Atom::intern_split("", ',').unwrap(), vec![""]);
+    }
+
+    #[test]
+    fn intern_split_invalid() {
+        assert!(AlphaNum::intern_split("a/b/c", '/').is_ok());
+        assert!(AlphaNum::intern_split("a/b-c/d", '/').is_err());
+    }
+
+    #[test]
+    fn intern_all() {
+        let words = vec![String::from("all_a"), String::from("all_b"),
+                         String::from("all_a")];
+        let symbols = Atom::intern_all(&words).unwrap();
+        assert_eq!(symbols, vec!["all_a", "all_b", "all_a"]);
+        assert_eq!(symbols[0].as_ptr(), symbols[2].as_ptr());
+        // symbols can be passed as input too
+        let again = Atom::intern_all(symbols.clone()).unwrap();
+        assert_eq!(again[1].as_ptr(), symbols[1].as_ptr());
+        assert!(Atom::intern_all(Vec::<&str>::new()).unwrap().is_empty());
+        assert!(AlphaNum::intern_all(["all1", "all-2"]).is_err());
+        assert_eq!(AlphaNum::get("all1"), None);
+    }
+
+    #[test]
+    fn intern_from_buf() {
+        use std::io::{Cursor, Read};
+        let mut input = Cursor::new(&b"buf_a buf_b buf_a buf_a"[..]);
+        let mut data = Vec::new();
+        input.read_to_end(&mut data).unwrap();
+        let tokens = data.split(|&b| b == b' ')
+            .map(|t| Atom::intern_from_buf(t).unwrap())
+            .collect::<Vec<_>>();
+        assert_eq!(tokens, vec!["buf_a", "buf_b", "buf_a", "buf_a"]);
+        assert_eq!(tokens[0].as_ptr(), tokens[2].as_ptr());
+        assert_eq!(tokens[0].as_ptr(), tokens[3].as_ptr());
+        assert_eq!(tokens[1].as_ptr(), Atom::from("buf_b").as_ptr());
+    }
+
+    #[test]
+    fn intern_from_buf_invalid() {
+        use FromBufError;
+        match Atom::intern_from_buf(b"buf\xff") {
+            Err(FromBufError::Utf8(e)) => assert_eq!(e.valid_up_to(), 3),
+            r => panic!("unexpected result {:?}", r),
+        }
+        match AlphaNum::intern_from_buf(b"buf-x") {
+            Err(FromBufError::Invalid(e)) => {
+                assert_eq!(e.kind(), io::ErrorKind::InvalidData);
+            }
+            r => panic!("unexpected result {:?}", r),
+        }
+        assert!(AlphaNum::intern_from_buf(b"bufx").is_ok());
+    }
+
+    #[test]
+    fn try_intern() {
+        let x = Atom::try_intern("try_intern").unwrap();
+        assert_eq!(x, "try_intern");
+        assert_eq!(x.as_ptr(), Atom::try_intern("try_intern").unwrap()
+            .as_ptr());
+        assert_eq!(x.as_ptr(), Atom::from("try_intern").as_ptr());
+    }
+
+    #[test]
+    fn try_intern_invalid() {
+        use TryInternError;
+        match AlphaNum::try_intern("try-intern") {
+            Err(TryInternError::Invalid(e)) => {
+                assert_eq!(e.kind(), io::ErrorKind::InvalidData);
+            }
+            r => panic!("unexpected result {:?}", r),
+        }
+    }
+
+    #[test]
+    fn extend_interned() {
+        use std::collections::HashSet;
+        let mut vec = vec![Atom::from("extend_a")];
+        Atom::extend_interned(&mut vec, ["extend_b", "extend_a"]).unwrap();
+        assert_eq!(vec, vec!["extend_a", "extend_b", "extend_a"]);
+        assert_eq!(vec[0].as_ptr(), vec[2].as_ptr());
+
+        let mut set = HashSet::new();
+        let names = vec![String::from("extend_a"), "extend_c".into(),
+                         "extend_a".into(), "extend_c".into()];
+        Atom::extend_interned(&mut set, names).unwrap();
+        assert_eq!(set.len(), 2);
+        assert!(set.contains("extend_a"));
+        assert!(set.contains("extend_c"));
+
+        // symbols are dropped after the lock is released
+        let mut vec = Vec::new();
+        Atom::extend_interned(&mut vec, vec![Atom::from("extend_d")])
+            .unwrap();
+        assert_eq!(vec, vec!["extend_d"]);
+    }
+
+    #[test]
+    fn extend_interned_invalid() {
+        let mut vec = Vec::new();
+        assert!(AlphaNum::extend_interned(&mut vec,
+            ["extenda", "extend-b", "extendc"]).is_err());
+        assert_eq!(vec, vec!["extenda"]);
+    }
+
+    #[test]
+    fn cast() {
+        let x = Atom::from("cast1");
+        let y = x.cast::<AlphaNumString>().unwrap();
+        assert_eq!(y, AlphaNum::from("cast1"));
+        // each validator type has its own pool
+        assert!(x.as_ptr() != y.as_ptr());
+        assert_eq!(y.as_ptr(), AlphaNum::from("cast1").as_ptr());
+        assert!(Atom::from("cast-2").cast::<AlphaNumString>().is_err());
+    }
+
+    #[test]
+    fn reserve() {
+        Atom::reserve(1000);
+        assert!(Atom::capacity() >= 1000);
+        let x = Atom::from("reserve_x");
+        let y: Atom = "reserve_x".parse().unwrap();
+        assert_eq!(x.as_ptr(), y.as_ptr());
+    }
+
+    #[test]
+    fn deferred_drop() {
+        use pool::pool;
+        use DropPolicy;
+        // own validator type, as the policy is set for the whole pool
+        struct Deferred;
+        impl Validator for Deferred {
+            type Err = ::std::string::ParseError;
+            fn validate_symbol(_: &str) -> Result<(), Self::Err> {
+                Ok(())
+            }
+        }
+        type D = Symbol<Deferred>;
+        fn entry_count(s: &str) -> Option<usize> {
+            pool::<Deferred>().read(s).get(s).map(|w| w.strong_count())
+        }
+        assert_eq!(D::drop_policy(), DropPolicy::Immediate);
+        D::set_drop_policy(DropPolicy::Deferred);
+        assert_eq!(D::drop_policy(), DropPolicy::Deferred);
+        assert_eq!(Atom::drop_policy(), DropPolicy::Immediate);
+        drop(D::from("deferred_a"));
+        drop(D::from("deferred_b"));
+        assert_eq!(entry_count("deferred_a"), Some(0));
+        let (a, hit) = D::intern_tracked("deferred_a").unwrap();
+        assert!(!hit);
+        assert_eq!(&a[..], "deferred_a");
+        assert_eq!(a.as_ptr(), D::from("deferred_a").as_ptr());
+        assert_eq!(entry_count("deferred_a"), Some(1));
+        assert!(D::prune() >= 1);
+        assert_eq!(entry_count("deferred_b"), None);
+        assert_eq!(entry_count("deferred_a"), Some(1));
+        for i in 0..1000 {
+            drop(format!("deferred_sweep_{}", i).parse::<D>().unwrap());
+        }
+        let left = (0..1000)
+            .filter(|i| entry_count(&format!("deferred_sweep_{}", i)).is_some())
+            .count();
+        assert!(left < 600, "{} dead entries left", left);
+        drop(D::from("deferred_c"));
+        assert!(::purge_dead() >= 1);
+        assert_eq!(entry_count("deferred_c"), None);
+        D::set_drop_policy(DropPolicy::Immediate);
+        drop(a);
+        assert_eq!(entry_count("deferred_a"), None);
+    }
+
+    #[test]
+    fn concurrent_churn() {
+        use std::thread;
+        let threads = (0..8).map(|i| thread::spawn(move || {
+            let mut keep = Vec::new();
+            for n in 0..2000 {
+                let name = format!("churn_{}", (n + i) % 10);
+                let a: Atom = name.parse().unwrap();
+                let b: Atom = name.parse().unwrap();
+                assert_eq!(&a[..], &name[..]);
+                assert_eq!(a.as_ptr(), b.as_ptr());
+                if n % 100 == i {
+                    keep.push(a);
+                }
+            }
+            keep
+        })).collect::<Vec<_>>();
+        let kept = threads.into_iter()
+            .flat_map(|t| t.join().unwrap())
+            .collect::<Vec<_>>();
+        for a in &kept {
+            for b in &kept {
+                assert_eq!(a == b, a.as_ptr() == b.as_ptr());
+            }
+        }
+    }
+
+    #[test]
+    fn iter_interned() {
+        let a = Atom::from("iter_interned_a");
+        let b = Atom::from("iter_interned_b");
+        let c = Atom::from("iter_interned_c");
+        drop(b);
+        let mut names = Atom::iter_interned().into_iter()
+            .filter(|s| s.starts_with("iter_interned_"))
+            .map(|s| s.to_string())
+            .collect::<Vec<_>>();
+        names.sort();
+        assert_eq!(names, vec!["iter_interned_a", "iter_interned_c"]);
+        drop((a, c));
+    }
+
+    #[test]
+    fn for_each_interned() {
+        let a = Atom::from("for_each_a");
+        let b = Atom::from("for_each_b");
+        let c = Atom::from("for_each_c");
+        drop(b);
+        let mut names = Vec::new();
+        Atom::for_each_interned(|s| {
+            if s.starts_with("for_each_") {
+                names.push(s.to_string());
+            }
+        });
+        names.sort();
+        assert_eq!(names, vec!["for_each_a", "for_each_c"]);
+        let mut dashed = false;
+        let _x = Atom::from("for-each-dash");
+        AlphaNum::for_each_interned(|s| dashed |= s.contains('-'));
+        assert!(!dashed);
+        drop((a, c));
+    }
+
+    #[test]
+    fn iter_interned_sorted() {
+        let _c = Atom::from("iter_sorted_c");
+        let _a = Atom::from("iter_sorted_a");
+        let b = Atom::from("iter_sorted_b");
+        let _d = Atom::from("iter_sorted_d");
+        drop(b);
+        let all = Atom::iter_interned_sorted();
+        assert!(all.windows(2).all(|w| w[0][..] <= w[1][..]));
+        let names = all.iter()
+            .filter(|s| s.starts_with("iter_sorted_"))
+            .map(|s| &s[..])
+            .collect::<Vec<_>>();
+        assert_eq!(names, vec!["iter_sorted_a", "iter_sorted_c",
+                               "iter_sorted_d"]);
+    }
+
+    #[test]
+    fn iter_interned_validates() {
+        let _x = Atom::from("iter-interned-dash");
+        assert!(AlphaNum::iter_interned().iter()
+            .all(|s| s.chars().all(|c| c.is_alphanumeric())));
+    }
+
+    #[test]
+    fn debug_dump() {
+        let x = Atom::from("debug_dump_x");
+        let y = Atom::from("debug_dump_y");
+        let _y2 = y.clone();
+        let _y3 = y.clone();
+        let dump = Atom::debug_dump();
+        // weak count depends on whether snapshot contains the value
+        assert!(dump.contains("\"debug_dump_x\" strong=1 weak="));
+        assert!(dump.contains("\"debug_dump_y\" strong=3 weak="));
+        drop(x);
+        assert!(!Atom::debug_dump().contains("debug_dump_x"));
+    }
+
+    #[test]
+    fn separate_pools() {
+        let a = Atom::from("separatepools");
+        let (b, hit) = AlphaNum::intern_tracked("separatepools").unwrap();
+        assert!(!hit);
+        assert!(a.as_ptr() != b.as_ptr());
+        let _x = Atom::from("separatepools2");
+        assert!(!AlphaNum::iter_interned().iter()
+            .any(|s| &s[..] == "separatepools2"));
+        assert!(Atom::iter_interned().iter()
+            .any(|s| &s[..] == "separatepools2"));
+    }
+
+    #[test]
+    #[should_panic(expected="static string used as atom is invalid")]
     fn distinct_validators() {
         let _xa = Atom::from("x");
         let _xn = AlphaNum::from("x");
@@ -0,0 +1,149 @@
+use core::fmt;
+use core::ops::Deref;
+use core::str::from_utf8;
+use core::marker::PhantomData;
+
+use serde::ser::{Serialize, Serializer};
+use serde::de::{self, Deserialize, Deserializer, Visitor};
+use {Validator, Symbol};
+use compat::prelude::*;
+
+
+/// A wrapper that serializes symbol as bytes rather than as a string
+///
+/// This is useful for binary formats which distinguish strings and byte
+/// arrays. On deserialization bytes are checked to be valid UTF-8 and then
+/// validated and interned as usual.
+///
+/// ```ignore
+/// #[derive(Serialize, Deserialize)]
+/// struct Packet {
+///     kind: SymbolBytes<KindValidator>,
+/// }
+/// ```
+pub struct SymbolBytes<V: Validator + ?Sized>(pub Symbol<V>);
+
+impl<V: Validator + ?Sized> SymbolBytes<V> {
+    /// Unwrap the underlying symbol
+    pub fn into_inner(self) -> Symbol<V> {
+        self.0
+    }
+}
+
+impl<V: Validator + ?Sized> Clone for SymbolBytes<V> {
+    fn clone(&self) -> SymbolBytes<V> {
+        SymbolBytes(self.0.clone())
+    }
+}
+
+impl<V: Validator + ?Sized> PartialEq for SymbolBytes<V> {
+    fn eq(&self, other: &SymbolBytes<V>) -> bool {
+        self.0.eq(&other.0)
+    }
+}
+impl<V: Validator + ?Sized> Eq for SymbolBytes<V> {}
+
+impl<V: Validator + ?Sized> fmt::Debug for SymbolBytes<V> {
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+        fmt::Debug::fmt(&self.0, fmt)
+    }
+}
+
+impl<V: Validator + ?Sized> Deref for SymbolBytes<V> {
+    type Target = Symbol<V>;
+    fn deref(&self) -> &Symbol<V> {
+        &self.0
+    }
+}
+
+impl<V: Validator + ?Sized> From<Symbol<V>> for SymbolBytes<V> {
+    fn from(sym: Symbol<V>) -> SymbolBytes<V> {
+        SymbolBytes(sym)
+    }
+}
+
+struct BytesVisitor<V: Validator + ?Sized>(PhantomData<V>);
+
+impl<'de, V: Validator + ?Sized> Visitor<'de> for BytesVisitor<V> {
+    type Value = SymbolBytes<V>;
+
+    fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        formatter.write_str("bytes of a valid symbol")
+    }
+
+    fn visit_bytes<E>(self, v: &[u8]) -> Result<Self::Value, E>
+        where E: de::Error
+    {
+        let s = from_utf8(v).map_err(de::Error::custom)?;
+        s.parse().map(SymbolBytes).map_err(de::Error::custom)
+    }
+
+    fn visit_byte_buf<E>(self, v: Vec<u8>) -> Result<Self::Value, E>
+        where E: de::Error
+    {
+        self.visit_bytes(&v)
+    }
+
+    fn visit_str<E>(self, v: &str) -> Result<Self::Value, E>
+        where E: de::Error
+    {
+        v.parse().map(SymbolBytes).map_err(de::Error::custom)
+    }
+}
+
+impl<'de, V: Validator + ?Sized> Deserialize<'de> for SymbolBytes<V> {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+        where D: Deserializer<'de>
+    {
+        deserializer.deserialize_bytes(BytesVisitor(PhantomData))
+    }
+}
+
+impl<V: Validator + ?Sized> Serialize for SymbolBytes<V> {
+    fn serialize<S: Serializer>(&self, serializer: S)
+        -> Result<S::Ok, S::Error>
+    {
+        serializer.serialize_bytes(self.0.as_bytes())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use bincode;
+    use {Validator, Symbol, SymbolBytes};
+
+    struct AnyString;
+    type Atom = Symbol<AnyString>;
+
+    impl Validator for AnyString {
+        // Use an error from standard library to make example shorter
+        type Err = ::std::string::ParseError;
+        fn validate_symbol(_: &str) -> Result<(), Self::Err> {
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn roundtrip() {
+        let value = SymbolBytes(Atom::from("bytes_xyz"));
+        let data = bincode::serialize(&value).unwrap();
+        let back: SymbolBytes<AnyString> = bincode::deserialize(&data)
+            .unwrap();
+        assert_eq!(back, value);
+        assert_eq!(back.as_ptr(), value.as_ptr());
+    }
+
+    #[test]
+    fn same_as_plain_bytes() {
+        let value = SymbolBytes(Atom::from("bytes_abc"));
+        assert_eq!(bincode::serialize(&value).unwrap(),
+                   bincode::serialize(&b"bytes_abc"[..]).unwrap());
+    }
+
+    #[test]
+    fn invalid_utf8() {
+        let data = bincode::serialize(&vec![0xffu8, 0xfe]).unwrap();
+        assert!(bincode::deserialize::<SymbolBytes<AnyString>>(&data)
+            .is_err());
+    }
+}
@@ -0,0 +1,355 @@
+use core::borrow::Borrow;
+use core::cmp::Ordering;
+use core::convert::TryFrom;
+use core::fmt;
+use core::hash::{Hash, Hasher};
+use core::marker::PhantomData;
+use core::ops::Deref;
+use core::str::{self, Utf8Error};
+use alloc::sync::Arc;
+
+#[cfg(feature = "serde")] use serde::ser::{Serialize, Serializer};
+#[cfg(feature = "serde")]
+use serde::de::{self, Deserialize, Deserializer, SeqAccess, Visitor};
+
+use PoolLimits;
+#[cfg(feature = "serde")] use compat::prelude::*;
+use pool::{item_pool, Item, Lock, Pool, PoolRef};
+
+
+/// Validator of `BytesSymbol`, same as `Validator` but for byte strings
+pub trait BytesValidator: 'static {
+    type Err: fmt::Display + fmt::Debug;
+    fn validate_symbol(value: &[u8]) -> Result<(), Self::Err>;
+    /// Formats the symbol for `fmt::Debug`, as an escaped byte string
+    fn debug(value: &BytesSymbol<Self>, fmt: &mut fmt::Formatter)
+        -> fmt::Result
+    {
+        write!(fmt, "b\"{}\"", value.escape_ascii())
+    }
+}
+
+/// Interned byte string, for values that are not necessarily UTF-8
+///
+/// Works like `Symbol` and is stored in the same kind of pool, but there is
+/// only one pool for each validator type (no `Interner`), and dropping the
+/// last symbol always removes the value.
+///
+/// ```
+/// # use string_intern::{BytesValidator, BytesSymbol};
+/// struct Token;
+///
+/// impl BytesValidator for Token {
+///     type Err = ::std::string::ParseError;
+///     fn validate_symbol(_: &[u8]) -> Result<(), Self::Err> { Ok(()) }
+/// }
+///
+/// let a = BytesSymbol::<Token>::intern(b"\xff\x00")?;
+/// assert_eq!(&a[..], b"\xff\x00");
+/// assert_eq!(a, BytesSymbol::from(b"\xff\x00"));
+/// # Ok::<(), ::std::string::ParseError>(())
+/// ```
+pub struct BytesSymbol<V: BytesValidator + ?Sized>(
+    Arc<BytesValue>, PhantomData<V>);
+
+struct BytesValue {
+    buf: Arc<[u8]>,
+    pool: PoolRef<BytesValue>,
+    // Values from different epochs may be equal, see `Value`
+    epoch: u32,
+    id: u32,
+}
+
+impl Item for BytesValue {
+    type Key = Arc<[u8]>;
+    type Query = [u8];
+    fn entry_key(value: &Arc<BytesValue>) -> Arc<[u8]> {
+        value.buf.clone()
+    }
+    fn size(key: &[u8]) -> usize {
+        key.len()
+    }
+}
+
+fn pool<V: BytesValidator + ?Sized>() -> &'static Pool<BytesValue> {
+    item_pool::<BytesValue, V>()
+}
+
+fn intern(pool: &'static Pool<BytesValue>, s: &[u8]) -> Arc<BytesValue> {
+    pool.get_or_insert(s, |id| Arc::new(BytesValue {
+        buf: Arc::from(s),
+        pool: pool.shared_ref(),
+        epoch: pool.epoch(),
+        id,
+    }))
+}
+
+impl Drop for BytesValue {
+    fn drop(&mut self) {
+        self.pool.release(&self.buf, self.id);
+    }
+}
+
+impl<V: BytesValidator + ?Sized> BytesSymbol<V> {
+    /// Validate and intern the value
+    pub fn intern(s: &[u8]) -> Result<BytesSymbol<V>, V::Err> {
+        V::validate_symbol(s)?;
+        Ok(BytesSymbol(intern(pool::<V>(), s), PhantomData))
+    }
+
+    /// Create a symbol from a static byte string
+    ///
+    /// # Panics
+    ///
+    /// When the value is invalid.
+    pub fn from(s: &'static [u8]) -> BytesSymbol<V> {
+        BytesSymbol::intern(s)
+            .expect("static byte string used as atom is invalid")
+    }
+
+    /// Returns the symbol if the value is interned already
+    pub fn get(s: &[u8]) -> Option<BytesSymbol<V>> {
+        pool::<V>().lookup(s).map(|a| BytesSymbol(a, PhantomData))
+    }
+
+    /// Set limits of the size of the pool of this type
+    ///
+    /// Same as `Symbol::set_limits`.
+    pub fn set_limits(limits: PoolLimits) {
+        pool::<V>().set_limits(&limits);
+    }
+
+    /// Returns the value as a string if it's valid UTF-8
+    pub fn to_str(&self) -> Result<&str, Utf8Error> {
+        str::from_utf8(self)
+    }
+}
+
+/// Same as `BytesSymbol::intern`
+impl<'a, V: BytesValidator + ?Sized> TryFrom<&'a [u8]> for BytesSymbol<V> {
+    type Error = V::Err;
+    fn try_from(s: &'a [u8]) -> Result<BytesSymbol<V>, Self::Error> {
+        BytesSymbol::intern(s)
+    }
+}
+
+impl<V: BytesValidator + ?Sized> Clone for BytesSymbol<V> {
+    fn clone(&self) -> BytesSymbol<V> {
+        BytesSymbol(self.0.clone(), PhantomData)
+    }
+}
+
+// There is a single pool for each type, so comparing pointers is enough,
+// unless values were evicted from the pool
+impl<V: BytesValidator + ?Sized> PartialEq for BytesSymbol<V> {
+    fn eq(&self, other: &BytesSymbol<V>) -> bool {
+        let (a, b) = (&self.0, &other.0);
+        Arc::ptr_eq(a, b) || a.epoch != b.epoch && a.buf == b.buf
+    }
+}
+
+impl<V: BytesValidator + ?Sized> Eq for BytesSymbol<V> {}
+
+impl<V: BytesValidator + ?Sized> PartialEq<[u8]> for BytesSymbol<V> {
+    fn eq(&self, other: &[u8]) -> bool {
+        self[..] == *other
+    }
+}
+
+// Must match the hash of `[u8]` for `Borrow<[u8]>`
+impl<V: BytesValidator + ?Sized> Hash for BytesSymbol<V> {
+    fn hash<H: Hasher>(&self, hasher: &mut H) {
+        self[..].hash(hasher)
+    }
+}
+
+impl<V: BytesValidator + ?Sized> PartialOrd for BytesSymbol<V> {
+    fn partial_cmp(&self, other: &BytesSymbol<V>) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl<V: BytesValidator + ?Sized> Ord for BytesSymbol<V> {
+    fn cmp(&self, other: &BytesSymbol<V>) -> Ordering {
+        self[..].cmp(&other[..])
+    }
+}
+
+impl<V: BytesValidator + ?Sized> Deref for BytesSymbol<V> {
+    type Target = [u8];
+    fn deref(&self) -> &[u8] {
+        &self.0.buf
+    }
+}
+
+impl<V: BytesValidator + ?Sized> AsRef<[u8]> for BytesSymbol<V> {
+    fn as_ref(&self) -> &[u8] {
+        &self.0.buf
+    }
+}
+
+impl<V: BytesValidator + ?Sized> Borrow<[u8]> for BytesSymbol<V> {
+    fn borrow(&self) -> &[u8] {
+        &self.0.buf
+    }
+}
+
+impl<V: BytesValidator + ?Sized> fmt::Debug for BytesSymbol<V> {
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+        V::debug(self, fmt)
+    }
+}
+
+#[cfg(feature = "serde")]
+impl<V: BytesValidator + ?Sized> Serialize for BytesSymbol<V> {
+    fn serialize<S: Serializer>(&self, serializer: S)
+        -> Result<S::Ok, S::Error>
+    {
+        serializer.serialize_bytes(self)
+    }
+}
+
+#[cfg(feature = "serde")]
+struct BytesSymbolVisitor<V: BytesValidator + ?Sized>(PhantomData<V>);
+
+#[cfg(feature = "serde")]
+impl<'de, V: BytesValidator + ?Sized> Visitor<'de> for BytesSymbolVisitor<V> {
+    type Value = BytesSymbol<V>;
+
+    fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        formatter.write_str("bytes of a valid symbol")
+    }
+
+    fn visit_bytes<E>(self, v: &[u8]) -> Result<Self::Value, E>
+        where E: de::Error
+    {
+        BytesSymbol::intern(v).map_err(de::Error::custom)
+    }
+
+    fn visit_str<E>(self, v: &str) -> Result<Self::Value, E>
+        where E: de::Error
+    {
+        self.visit_bytes(v.as_bytes())
+    }
+
+    // Self-describing formats, like JSON, store bytes as a sequence
+    fn visit_seq<A>(self, mut seq: A) -> Result<Self::Value, A::Error>
+        where A: SeqAccess<'de>
+    {
+        let mut buf = Vec::with_capacity(seq.size_hint().unwrap_or(0));
+        while let Some(byte) = seq.next_element()? {
+            buf.push(byte);
+        }
+        self.visit_bytes(&buf)
+    }
+}
+
+#[cfg(feature = "serde")]
+impl<'de, V: BytesValidator + ?Sized> Deserialize<'de> for BytesSymbol<V> {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+        where D: Deserializer<'de>
+    {
+        deserializer.deserialize_bytes(BytesSymbolVisitor(PhantomData))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::collections::HashMap;
+    use std::io;
+    use super::{BytesValidator, BytesSymbol, pool};
+
+    struct AnyBytes;
+    type Token = BytesSymbol<AnyBytes>;
+
+    impl BytesValidator for AnyBytes {
+        type Err = ::std::string::ParseError;
+        fn validate_symbol(_: &[u8]) -> Result<(), Self::Err> {
+            Ok(())
+        }
+    }
+
+    struct NoZero;
+
+    impl BytesValidator for NoZero {
+        type Err = io::Error;
+        fn validate_symbol(s: &[u8]) -> Result<(), Self::Err> {
+            if s.contains(&0) {
+                return Err(io::Error::new(io::ErrorKind::InvalidData,
+                    "zero byte"));
+            }
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn intern() {
+        let a = Token::from(b"bytes_sym\xff");
+        let b = Token::intern(b"bytes_sym\xff").unwrap();
+        assert_eq!(a, b);
+        assert_eq!(a.as_ptr(), b.as_ptr());
+        assert_eq!(&a[..], b"bytes_sym\xff");
+        assert!(a.to_str().is_err());
+        assert_eq!(Token::from(b"bytes_utf").to_str().unwrap(), "bytes_utf");
+        assert_eq!(Token::get(b"bytes_sym\xff"), Some(a.clone()));
+        assert_eq!(format!("{:?}", a), "b\"bytes_sym\\xff\"");
+        let map = vec![(a.clone(), 1)].into_iter()
+            .collect::<HashMap<_, _>>();
+        assert_eq!(map.get(&b"bytes_sym\xff"[..]), Some(&1));
+    }
+
+    #[test]
+    fn drop_removes() {
+        let a = Token::from(b"bytes_drop");
+        drop(a);
+        assert_eq!(Token::get(b"bytes_drop"), None);
+        assert!(pool::<AnyBytes>().read(b"bytes_drop")
+            .get(&b"bytes_drop"[..]).is_none());
+    }
+
+    #[test]
+    fn limits() {
+        use PoolLimits;
+        struct Limited;
+        impl BytesValidator for Limited {
+            type Err = ::std::string::ParseError;
+            fn validate_symbol(_: &[u8]) -> Result<(), Self::Err> {
+                Ok(())
+            }
+        }
+        type L = BytesSymbol<Limited>;
+        L::set_limits(PoolLimits::new().max_entries(16));
+        let keep = (0..64u8).map(|i| L::intern(&[b'l', i]).unwrap())
+            .collect::<Vec<_>>();
+        let entries = pool::<Limited>().read_shards()
+            .map(|atoms| atoms.len())
+            .sum::<usize>();
+        assert!(entries <= 16, "{} entries", entries);
+        for (i, sym) in keep.iter().enumerate() {
+            assert_eq!(*sym, L::intern(&[b'l', i as u8]).unwrap());
+        }
+    }
+
+    #[test]
+    fn validates() {
+        assert!(BytesSymbol::<NoZero>::intern(b"a\x00").is_err());
+        assert!(BytesSymbol::<NoZero>::intern(b"a\x01").is_ok());
+    }
+
+    #[test]
+    #[cfg(feature = "serde")]
+    fn serde() {
+        use bincode;
+        use serde_json;
+        let a = Token::from(b"bytes_serde\x00");
+        let data = bincode::serialize(&a).unwrap();
+        assert_eq!(data, bincode::serialize(&b"bytes_serde\x00"[..]).unwrap());
+        let back: Token = bincode::deserialize(&data).unwrap();
+        assert_eq!(back.as_ptr(), a.as_ptr());
+        let json = serde_json::to_string(&a).unwrap();
+        let back: Token = serde_json::from_str(&json).unwrap();
+        assert_eq!(back, a);
+        let data = bincode::serialize(&b"a\x00"[..]).unwrap();
+        assert!(bincode::deserialize::<BytesSymbol<NoZero>>(&data).is_err());
+    }
+}
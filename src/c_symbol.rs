@@ -0,0 +1,167 @@
+use core::borrow::Borrow;
+use core::cmp::Ordering;
+use core::convert::Infallible;
+use alloc::ffi::{CString, NulError};
+use core::ffi::CStr;
+use core::fmt;
+use core::hash::{Hash, Hasher};
+use core::ops::Deref;
+use core::str::Utf8Error;
+
+use bytes_symbol::{BytesSymbol, BytesValidator};
+use compat::prelude::*;
+
+
+struct CBytes;
+
+impl BytesValidator for CBytes {
+    type Err = Infallible;
+    fn validate_symbol(_: &[u8]) -> Result<(), Self::Err> {
+        Ok(())
+    }
+}
+
+/// Interned C string
+///
+/// The value is stored with the trailing nul, so `as_c_str().as_ptr()` can
+/// be passed to C functions directly, as long as the symbol is alive.
+///
+/// ```
+/// # use std::ffi::CStr;
+/// # use string_intern::CSymbol;
+/// let a = CSymbol::new("eth0")?;
+/// assert_eq!(a.as_c_str(), CStr::from_bytes_with_nul(b"eth0\0").unwrap());
+/// assert!(CSymbol::new("eth\00").is_err());
+/// # Ok::<(), ::std::ffi::NulError>(())
+/// ```
+#[derive(Clone, PartialEq, Eq)]
+pub struct CSymbol(BytesSymbol<CBytes>);
+
+impl CSymbol {
+    /// Intern the value, fails if it contains a nul byte
+    pub fn new<T: Into<Vec<u8>>>(s: T) -> Result<CSymbol, NulError> {
+        Ok(CSymbol::from(&*CString::new(s)?))
+    }
+
+    /// Returns the symbol if the value is interned already
+    pub fn get(s: &CStr) -> Option<CSymbol> {
+        BytesSymbol::get(s.to_bytes_with_nul()).map(CSymbol)
+    }
+
+    /// Returns the value as a `CStr`
+    ///
+    /// The value is always nul-terminated, so the pointer is valid to pass
+    /// to C while the symbol is alive.
+    pub fn as_c_str(&self) -> &CStr {
+        // safe because the bytes were taken from a `CStr`
+        unsafe { CStr::from_bytes_with_nul_unchecked(&self.0) }
+    }
+
+    /// Returns the value as a string if it's valid UTF-8
+    pub fn to_str(&self) -> Result<&str, Utf8Error> {
+        self.as_c_str().to_str()
+    }
+}
+
+impl<'a> From<&'a CStr> for CSymbol {
+    fn from(s: &'a CStr) -> CSymbol {
+        match BytesSymbol::intern(s.to_bytes_with_nul()) {
+            Ok(sym) => CSymbol(sym),
+            Err(e) => match e {},
+        }
+    }
+}
+
+impl From<CString> for CSymbol {
+    fn from(s: CString) -> CSymbol {
+        CSymbol::from(&*s)
+    }
+}
+
+impl From<CSymbol> for CString {
+    fn from(s: CSymbol) -> CString {
+        s.as_c_str().to_owned()
+    }
+}
+
+impl PartialEq<CStr> for CSymbol {
+    fn eq(&self, other: &CStr) -> bool {
+        self.as_c_str() == other
+    }
+}
+
+// Must match the hash of `CStr` for `Borrow<CStr>`
+impl Hash for CSymbol {
+    fn hash<H: Hasher>(&self, hasher: &mut H) {
+        self.as_c_str().hash(hasher)
+    }
+}
+
+impl PartialOrd for CSymbol {
+    fn partial_cmp(&self, other: &CSymbol) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for CSymbol {
+    fn cmp(&self, other: &CSymbol) -> Ordering {
+        self.as_c_str().cmp(other.as_c_str())
+    }
+}
+
+impl Deref for CSymbol {
+    type Target = CStr;
+    fn deref(&self) -> &CStr {
+        self.as_c_str()
+    }
+}
+
+impl AsRef<CStr> for CSymbol {
+    fn as_ref(&self) -> &CStr {
+        self.as_c_str()
+    }
+}
+
+impl Borrow<CStr> for CSymbol {
+    fn borrow(&self) -> &CStr {
+        self.as_c_str()
+    }
+}
+
+impl fmt::Debug for CSymbol {
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+        fmt::Debug::fmt(self.as_c_str(), fmt)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::collections::HashMap;
+    use std::ffi::{CStr, CString};
+    use super::CSymbol;
+
+    #[test]
+    fn intern() {
+        let a = CSymbol::new("c_sym").unwrap();
+        let b = CSymbol::from(CString::new("c_sym").unwrap());
+        assert_eq!(a, b);
+        assert_eq!(a.as_ptr(), b.as_ptr());
+        assert_eq!(a.to_bytes_with_nul(), b"c_sym\0");
+        assert_eq!(a.to_str(), Ok("c_sym"));
+        assert_eq!(format!("{:?}", a), "\"c_sym\"");
+        let key = CStr::from_bytes_with_nul(b"c_sym\0").unwrap();
+        assert_eq!(CSymbol::get(key), Some(a.clone()));
+        let map = vec![(a.clone(), 1)].into_iter()
+            .collect::<HashMap<_, _>>();
+        assert_eq!(map.get(key), Some(&1));
+        drop((a, b, map));
+        assert_eq!(CSymbol::get(key), None);
+    }
+
+    #[test]
+    fn interior_nul() {
+        let err = CSymbol::new(&b"c_sym\0nul"[..]).unwrap_err();
+        assert_eq!(err.nul_position(), 5);
+        assert_eq!(CSymbol::new("").unwrap().to_bytes_with_nul(), b"\0");
+    }
+}
@@ -0,0 +1,118 @@
+//! Collections and locks which differ between `std` and `no_std` builds
+//!
+//! Without the `std` feature, collections come from `hashbrown`, and locks
+//! and lazy cells come from `spin`. With the `parking_lot` feature, locks
+//! come from `parking_lot`. Neither gets poisoned, and poisoning of `std`
+//! locks is ignored, but the wrappers return `Result` anyway, so the code
+//! using them is the same for all builds.
+
+// `HashSet` is only used with `serde`
+#[cfg(feature = "std")]
+#[allow(unused_imports)]
+pub(crate) use std::collections::{HashMap, HashSet, TryReserveError};
+#[cfg(feature = "std")]
+pub(crate) use std::sync::OnceLock;
+#[cfg(all(feature = "std", not(feature = "parking_lot")))]
+pub(crate) use self::std_locks::*;
+#[cfg(feature = "parking_lot")]
+pub(crate) use self::parking_lot_locks::*;
+
+#[cfg(not(feature = "std"))]
+pub(crate) use self::no_std::*;
+
+/// Items of the `std` prelude which are not in the `core` prelude
+pub(crate) mod prelude {
+    pub(crate) use alloc::borrow::ToOwned;
+    pub(crate) use alloc::boxed::Box;
+    pub(crate) use alloc::string::{String, ToString};
+    pub(crate) use alloc::vec::Vec;
+}
+
+/// Wraps `RwLock` and `Mutex` found at `$lib`, `unpoison` must be in scope
+/// to turn the result of locking into a guard
+macro_rules! unpoisoned_locks {
+    ($($lib:ident)::+) => {
+        use core::convert::Infallible;
+
+        pub(crate) use $($lib::)+MutexGuard;
+        pub(crate) use $($lib::)+{RwLockReadGuard, RwLockWriteGuard};
+
+        #[derive(Debug, Default)]
+        pub(crate) struct RwLock<T>($($lib::)+RwLock<T>);
+
+        #[derive(Debug, Default)]
+        pub(crate) struct Mutex<T>($($lib::)+Mutex<T>);
+
+        impl<T> RwLock<T> {
+            pub const fn new(value: T) -> RwLock<T> {
+                RwLock($($lib::)+RwLock::new(value))
+            }
+            pub fn read(&self)
+                -> Result<RwLockReadGuard<'_, T>, Infallible>
+            {
+                Ok(unpoison(self.0.read()))
+            }
+            pub fn write(&self)
+                -> Result<RwLockWriteGuard<'_, T>, Infallible>
+            {
+                Ok(unpoison(self.0.write()))
+            }
+        }
+
+        impl<T> Mutex<T> {
+            pub const fn new(value: T) -> Mutex<T> {
+                Mutex($($lib::)+Mutex::new(value))
+            }
+            pub fn lock(&self) -> Result<MutexGuard<'_, T>, Infallible> {
+                Ok(unpoison(self.0.lock()))
+            }
+        }
+    }
+}
+
+#[cfg(all(feature = "std", not(feature = "parking_lot")))]
+mod std_locks {
+    use std::sync::{LockResult, PoisonError};
+
+    unpoisoned_locks!(std::sync);
+
+    // Tables are never left half-updated by a panic (the `HashMap` is
+    // consistent after each operation), so a panic in one thread shouldn't
+    // make interning panic in every other thread
+    fn unpoison<G>(result: LockResult<G>) -> G {
+        result.unwrap_or_else(PoisonError::into_inner)
+    }
+}
+
+#[cfg(feature = "parking_lot")]
+mod parking_lot_locks {
+    unpoisoned_locks!(parking_lot);
+
+    fn unpoison<G>(guard: G) -> G {
+        guard
+    }
+}
+
+#[cfg(not(feature = "std"))]
+mod no_std {
+    #[allow(unused_imports)] // `HashSet` is only used with `serde`
+    pub(crate) use hashbrown::{HashMap, HashSet, TryReserveError};
+
+    unpoisoned_locks!(spin);
+
+    fn unpoison<G>(guard: G) -> G {
+        guard
+    }
+
+    #[derive(Debug)]
+    pub(crate) struct OnceLock<T>(spin::Once<T>);
+
+    impl<T> OnceLock<T> {
+        pub const fn new() -> OnceLock<T> {
+            OnceLock(spin::Once::new())
+        }
+        pub fn get_or_init<F: FnOnce() -> T>(&self, f: F) -> &T {
+            self.0.call_once(f)
+        }
+    }
+}
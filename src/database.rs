@@ -0,0 +1,114 @@
+use core::str::FromStr;
+
+use sqlx::{Database, Decode, Encode, Type};
+use sqlx::encode::IsNull;
+use sqlx::error::BoxDynError;
+
+use {Validator, Symbol};
+
+
+/// Same type as `str`
+impl<V, DB> Type<DB> for Symbol<V>
+    where V: Validator + ?Sized, DB: Database, str: Type<DB>,
+{
+    fn type_info() -> DB::TypeInfo {
+        <str as Type<DB>>::type_info()
+    }
+    fn compatible(ty: &DB::TypeInfo) -> bool {
+        <str as Type<DB>>::compatible(ty)
+    }
+}
+
+/// Written as a `String`, as SQLite needs to own its arguments
+impl<'q, V, DB> Encode<'q, DB> for Symbol<V>
+    where V: Validator + ?Sized, DB: Database, String: Encode<'q, DB>,
+{
+    fn encode_by_ref(&self, buf: &mut DB::ArgumentBuffer<'q>)
+        -> Result<IsNull, BoxDynError>
+    {
+        self.to_owned_string().encode(buf)
+    }
+}
+
+/// Read as a `&str`, then validated and interned
+impl<'r, V, DB> Decode<'r, DB> for Symbol<V>
+    where V: Validator + ?Sized, DB: Database, &'r str: Decode<'r, DB>,
+{
+    fn decode(value: DB::ValueRef<'r>) -> Result<Self, BoxDynError> {
+        let value = <&str as Decode<'r, DB>>::decode(value)?;
+        Symbol::from_str(value)
+            .map_err(|e: V::Err| e.to_string().into())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::future::Future;
+    use std::pin::pin;
+    use std::sync::Arc;
+    use std::task::{Context, Poll, Wake};
+    use std::thread::{self, Thread};
+
+    use sqlx::{Connection, Database, Decode, Encode, Row, Type};
+    use sqlx::sqlite::SqliteConnection;
+    use Symbol;
+    use validators::Identifier;
+
+    struct Unpark(Thread);
+
+    impl Wake for Unpark {
+        fn wake(self: Arc<Self>) {
+            self.0.unpark()
+        }
+    }
+
+    // SQLite runs queries in its own thread, so no async runtime is needed
+    fn block_on<F: Future>(future: F) -> F::Output {
+        let waker = Arc::new(Unpark(thread::current())).into();
+        let mut cx = Context::from_waker(&waker);
+        let mut future = pin!(future);
+        loop {
+            match future.as_mut().poll(&mut cx) {
+                Poll::Ready(value) => return value,
+                Poll::Pending => thread::park(),
+            }
+        }
+    }
+
+    // Postgres and MySQL aren't compiled in, so this only checks the bounds
+    #[allow(dead_code)]
+    fn database<DB: Database>()
+        where str: Type<DB>,
+              for<'q> String: Encode<'q, DB>,
+              for<'r> &'r str: Decode<'r, DB>,
+    {
+        fn column<T, DB>()
+            where DB: Database,
+                  T: Type<DB>,
+                  for<'q> T: Encode<'q, DB>,
+                  for<'r> T: Decode<'r, DB>,
+        {}
+        column::<Symbol<Identifier>, DB>();
+    }
+
+    #[test]
+    fn sqlite() {
+        let mut conn = block_on(SqliteConnection::connect("sqlite::memory:"))
+            .unwrap();
+        let sym = Symbol::<Identifier>::from("sqlx_value");
+        let row = block_on(sqlx::query("SELECT ? AS a, ? AS b")
+            .bind(&sym)
+            .bind(None::<Symbol<Identifier>>)
+            .fetch_one(&mut conn)).unwrap();
+        let a: Symbol<Identifier> = row.get("a");
+        assert_eq!(a.as_ptr(), sym.as_ptr());
+        let b: Option<Symbol<Identifier>> = row.get("b");
+        assert_eq!(b, None);
+        let invalid = block_on(sqlx::query_scalar::<_, Symbol<Identifier>>(
+            "SELECT 'not valid'").fetch_one(&mut conn));
+        assert!(matches!(invalid, Err(sqlx::Error::ColumnDecode { .. })));
+        let number = block_on(sqlx::query_scalar::<_, Symbol<Identifier>>(
+            "SELECT 1").fetch_one(&mut conn));
+        assert!(matches!(number, Err(sqlx::Error::ColumnDecode { .. })));
+    }
+}
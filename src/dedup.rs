@@ -0,0 +1,348 @@
+use std::any::TypeId;
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::convert::TryFrom;
+use std::fmt;
+use std::marker::PhantomData;
+use std::ops::{Deref, DerefMut};
+use std::sync::Arc;
+
+use serde::ser::{self, Serialize, Serializer};
+use serde::de::{self, Deserialize, Deserializer, DeserializeSeed};
+use serde::de::{EnumAccess, Unexpected, VariantAccess, Visitor};
+
+use {Validator, Symbol};
+use base_type::Value;
+
+
+/// A wrapper that writes each distinct symbol inside the value only once
+///
+/// The first occurrence of a symbol is serialized as `New(value)`, and
+/// the following ones as `Ref(index)`, where `index` is the number of new
+/// symbols written before it. This makes documents that repeat the same
+/// few symbols many times much smaller. The value must be deserialized
+/// with the same wrapper, which validates and interns each symbol once.
+///
+/// Symbols may be at any depth of the value, but they must be deserialized
+/// in the same order as they were serialized, so `#[serde(flatten)]` and
+/// untagged enums containing symbols are not supported.
+///
+/// ```ignore
+/// let data = bincode::serialize(&Dedup(&document))?;
+/// let Dedup(document): Dedup<Document> = bincode::deserialize(&data)?;
+/// ```
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Dedup<T>(pub T);
+
+impl<T> Dedup<T> {
+    /// Unwrap the value
+    pub fn into_inner(self) -> T {
+        self.0
+    }
+}
+
+impl<T> Deref for Dedup<T> {
+    type Target = T;
+    fn deref(&self) -> &T {
+        &self.0
+    }
+}
+
+impl<T> DerefMut for Dedup<T> {
+    fn deref_mut(&mut self) -> &mut T {
+        &mut self.0
+    }
+}
+
+impl<T> From<T> for Dedup<T> {
+    fn from(value: T) -> Dedup<T> {
+        Dedup(value)
+    }
+}
+
+#[derive(Default)]
+struct Session {
+    // indexes of serialized symbols by the address of their value
+    written: HashMap<*const Value, u32>,
+    // keeps serialized values alive, so that addresses are not reused
+    // (e.g. by symbols upgraded from `WeakSymbol` for serialization)
+    values: Vec<Arc<Value>>,
+    // deserialized symbols along with their validator types
+    read: Vec<(TypeId, Arc<Value>)>,
+}
+
+thread_local! {
+    static SESSION: RefCell<Option<Session>> = const { RefCell::new(None) };
+}
+
+/// Restores the outer session (if any) when the inner one is finished
+struct Guard(Option<Session>);
+
+fn start() -> Guard {
+    Guard(SESSION.with(|s| s.replace(Some(Session::default()))))
+}
+
+impl Drop for Guard {
+    fn drop(&mut self) {
+        let outer = self.0.take();
+        SESSION.with(|s| *s.borrow_mut() = outer);
+    }
+}
+
+/// Returns true if symbols are (de)serialized inside `Dedup`
+pub fn active() -> bool {
+    SESSION.with(|s| s.borrow().is_some())
+}
+
+const VARIANTS: &[&str] = &["New", "Ref"];
+
+pub fn serialize<V, S>(sym: &Symbol<V>, serializer: S)
+    -> Result<S::Ok, S::Error>
+    where V: Validator + ?Sized, S: Serializer,
+{
+    let index = SESSION.with(|s| {
+        let mut s = s.borrow_mut();
+        let session = s.as_mut().expect("dedup session is active");
+        let ptr = Arc::as_ptr(&sym.0);
+        if let Some(&index) = session.written.get(&ptr) {
+            return Ok(Some(index));
+        }
+        let index = u32::try_from(session.values.len())
+            .map_err(|_| ser::Error::custom("too many distinct symbols"))?;
+        session.written.insert(ptr, index);
+        session.values.push(sym.0.clone());
+        Ok(None)
+    })?;
+    match index {
+        Some(index) => {
+            serializer.serialize_newtype_variant("Symbol", 1, "Ref", &index)
+        }
+        None => {
+            serializer.serialize_newtype_variant("Symbol", 0, "New", &sym[..])
+        }
+    }
+}
+
+pub fn deserialize<'de, V, D>(deserializer: D) -> Result<Symbol<V>, D::Error>
+    where V: Validator + ?Sized, D: Deserializer<'de>,
+{
+    deserializer.deserialize_enum("Symbol", VARIANTS,
+                                  EntryVisitor(PhantomData))
+}
+
+enum Tag {
+    New,
+    Ref,
+}
+
+impl<'de> Deserialize<'de> for Tag {
+    fn deserialize<D>(deserializer: D) -> Result<Tag, D::Error>
+        where D: Deserializer<'de>
+    {
+        deserializer.deserialize_identifier(TagVisitor)
+    }
+}
+
+struct TagVisitor;
+
+impl<'de> Visitor<'de> for TagVisitor {
+    type Value = Tag;
+
+    fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        formatter.write_str("`New` or `Ref`")
+    }
+
+    fn visit_u64<E>(self, v: u64) -> Result<Tag, E>
+        where E: de::Error
+    {
+        match v {
+            0 => Ok(Tag::New),
+            1 => Ok(Tag::Ref),
+            _ => Err(de::Error::invalid_value(Unexpected::Unsigned(v), &self)),
+        }
+    }
+
+    fn visit_str<E>(self, v: &str) -> Result<Tag, E>
+        where E: de::Error
+    {
+        match v {
+            "New" => Ok(Tag::New),
+            "Ref" => Ok(Tag::Ref),
+            _ => Err(de::Error::unknown_variant(v, VARIANTS)),
+        }
+    }
+}
+
+struct EntryVisitor<V: Validator + ?Sized>(PhantomData<V>);
+
+impl<'de, V: Validator + ?Sized> Visitor<'de> for EntryVisitor<V> {
+    type Value = Symbol<V>;
+
+    fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        formatter.write_str("a new symbol or a reference to one")
+    }
+
+    fn visit_enum<A>(self, data: A) -> Result<Self::Value, A::Error>
+        where A: EnumAccess<'de>
+    {
+        let id = TypeId::of::<V>();
+        let (tag, variant) = data.variant()?;
+        match tag {
+            Tag::New => {
+                let sym = variant.newtype_variant_seed(NewSymbol(self.0))?;
+                SESSION.with(|s| {
+                    s.borrow_mut().as_mut()
+                        .expect("dedup session is active")
+                        .read.push((id, sym.0.clone()));
+                });
+                Ok(sym)
+            }
+            Tag::Ref => {
+                let index: u32 = variant.newtype_variant()?;
+                let value = SESSION.with(|s| {
+                    s.borrow().as_ref()
+                        .expect("dedup session is active")
+                        .read.get(index as usize)
+                        // must be a value of the pool of `V`
+                        .filter(|(t, _)| *t == id)
+                        .map(|(_, value)| value.clone())
+                });
+                value.map(|v| Symbol(v, PhantomData)).ok_or_else(|| {
+                    de::Error::custom(format_args!(
+                        "invalid symbol reference {}", index))
+                })
+            }
+        }
+    }
+}
+
+/// Deserializes the value of the `New` variant as a plain string
+struct NewSymbol<V: Validator + ?Sized>(PhantomData<V>);
+
+impl<'de, V: Validator + ?Sized> DeserializeSeed<'de> for NewSymbol<V> {
+    type Value = Symbol<V>;
+
+    fn deserialize<D>(self, deserializer: D) -> Result<Symbol<V>, D::Error>
+        where D: Deserializer<'de>
+    {
+        deserializer.deserialize_str(self)
+    }
+}
+
+impl<'de, V: Validator + ?Sized> Visitor<'de> for NewSymbol<V> {
+    type Value = Symbol<V>;
+
+    fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        formatter.write_str("a valid symbol")
+    }
+
+    fn visit_str<E>(self, v: &str) -> Result<Self::Value, E>
+        where E: de::Error
+    {
+        v.parse().map_err(de::Error::custom)
+    }
+}
+
+impl<T: Serialize> Serialize for Dedup<T> {
+    fn serialize<S: Serializer>(&self, serializer: S)
+        -> Result<S::Ok, S::Error>
+    {
+        let _session = start();
+        self.0.serialize(serializer)
+    }
+}
+
+impl<'de, T: Deserialize<'de>> Deserialize<'de> for Dedup<T> {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+        where D: Deserializer<'de>
+    {
+        let _session = start();
+        T::deserialize(deserializer).map(Dedup)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::io;
+    use bincode;
+    use serde_json;
+    use {Validator, Symbol, InternSeq, Dedup};
+
+    struct AnyString;
+    type Atom = Symbol<AnyString>;
+
+    impl Validator for AnyString {
+        // Use an error from standard library to make example shorter
+        type Err = ::std::string::ParseError;
+        fn validate_symbol(_: &str) -> Result<(), Self::Err> {
+            Ok(())
+        }
+    }
+
+    struct AlphaNumString;
+
+    impl Validator for AlphaNumString {
+        // Use an error from standard library to make example shorter
+        type Err = io::Error;
+        fn validate_symbol(s: &str) -> Result<(), Self::Err> {
+            if s.chars().any(|c| !c.is_alphanumeric()) {
+                return Err(io::Error::new(io::ErrorKind::InvalidData,
+                    "Character is not alphanumeric"));
+            }
+            Ok(())
+        }
+    }
+
+    fn atoms(names: &[&'static str]) -> Vec<Atom> {
+        names.iter().map(|&n| Atom::from(n)).collect()
+    }
+
+    #[test]
+    fn json() {
+        let value = (atoms(&["dd_a", "dd_b", "dd_a"]),
+                     Some(Atom::from("dd_b")));
+        let json = serde_json::to_string(&Dedup(&value)).unwrap();
+        assert_eq!(json, concat!(r#"[[{"New":"dd_a"},{"New":"dd_b"},"#,
+                                 r#"{"Ref":0}],{"Ref":1}]"#));
+        let back: Dedup<(Vec<Atom>, Option<Atom>)> =
+            serde_json::from_str(&json).unwrap();
+        assert_eq!(back.0, value);
+        // outside of `Dedup` symbols are plain strings again
+        assert_eq!(serde_json::to_string(&value.1).unwrap(), r#""dd_b""#);
+    }
+
+    #[test]
+    fn smaller() {
+        let value = atoms(&["a_rather_long_symbol"; 100]);
+        let plain = bincode::serialize(&value).unwrap();
+        let dedup = bincode::serialize(&Dedup(&value)).unwrap();
+        assert!(dedup.len() * 2 < plain.len());
+        let back: Dedup<Vec<Atom>> = bincode::deserialize(&dedup).unwrap();
+        assert_eq!(back.0, value);
+        assert!(back.iter().all(|s| s.as_ptr() == value[0].as_ptr()));
+    }
+
+    #[test]
+    fn seq() {
+        let value = InternSeq(atoms(&["dd_seq", "dd_seq"]));
+        let json = serde_json::to_string(&Dedup(&value)).unwrap();
+        let back: Dedup<InternSeq<AnyString>> =
+            serde_json::from_str(&json).unwrap();
+        assert_eq!(back.0, value);
+    }
+
+    #[test]
+    fn invalid() {
+        type A = Dedup<Vec<Symbol<AlphaNumString>>>;
+        assert!(serde_json::from_str::<A>(r#"[{"New":"a-b"}]"#).is_err());
+        assert!(serde_json::from_str::<A>(r#"[{"Ref":0}]"#).is_err());
+        assert!(serde_json::from_str::<A>(
+            r#"[{"New":"ab"},{"Ref":1}]"#).is_err());
+        assert!(serde_json::from_str::<A>(
+            r#"[{"New":"ab"},{"Ref":0}]"#).is_ok());
+        // references must point to symbols of the same type
+        type B = Dedup<(Atom, Symbol<AlphaNumString>)>;
+        assert!(serde_json::from_str::<B>(
+            r#"[{"New":"ab"},{"Ref":0}]"#).is_err());
+    }
+}
@@ -0,0 +1,321 @@
+use core::any::type_name;
+use core::fmt;
+use core::error::Error;
+use core::str::Utf8Error;
+
+use {Validator};
+use compat::prelude::*;
+use compat::TryReserveError;
+
+
+/// Error of a value longer than `Validator::MAX_LEN` or `global_max_len()`
+///
+/// Passed to `Validator::TOO_LONG` to make the error of the validator.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TooLong {
+    /// Length of the value in bytes
+    pub len: usize,
+    /// The limit which is exceeded
+    pub max_len: usize,
+}
+
+impl fmt::Display for TooLong {
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+        write!(fmt, "value of {} bytes exceeds the limit of {} bytes",
+            self.len, self.max_len)
+    }
+}
+
+impl Error for TooLong {}
+
+/// Error of a value which can't be interned
+///
+/// Returned by `Symbol::parse_limited`. Unlike the error of the validator,
+/// it can report that the value is too long for any validator.
+pub enum ValidationError<V: Validator + ?Sized> {
+    /// The value is longer than `Validator::MAX_LEN` or `global_max_len()`
+    TooLong(TooLong),
+    /// The value was rejected by the validator
+    Invalid(V::Err),
+}
+
+impl<V: Validator + ?Sized> fmt::Display for ValidationError<V> {
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            ValidationError::TooLong(ref e) => fmt::Display::fmt(e, fmt),
+            ValidationError::Invalid(ref e) => fmt::Display::fmt(e, fmt),
+        }
+    }
+}
+
+impl<V: Validator + ?Sized> fmt::Debug for ValidationError<V> {
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            ValidationError::TooLong(ref e) => {
+                fmt.debug_tuple("TooLong").field(e).finish()
+            }
+            ValidationError::Invalid(ref e) => {
+                fmt.debug_tuple("Invalid").field(e).finish()
+            }
+        }
+    }
+}
+
+impl<V: Validator + ?Sized> PartialEq for ValidationError<V>
+    where V::Err: PartialEq
+{
+    fn eq(&self, other: &ValidationError<V>) -> bool {
+        match (self, other) {
+            (ValidationError::TooLong(a), ValidationError::TooLong(b)) => {
+                a == b
+            }
+            (ValidationError::Invalid(a), ValidationError::Invalid(b)) => {
+                a == b
+            }
+            _ => false,
+        }
+    }
+}
+
+impl<V: Validator + ?Sized> Error for ValidationError<V>
+    where V::Err: Error + 'static
+{
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match *self {
+            ValidationError::TooLong(ref e) => Some(e),
+            ValidationError::Invalid(ref e) => Some(e),
+        }
+    }
+}
+
+/// Error which contains the offending value along with the validator error
+///
+/// This is returned by `Symbol::parse_with_context`.
+pub struct SymbolParseError<V: Validator + ?Sized> {
+    input: String,
+    error: V::Err,
+}
+
+impl<V: Validator + ?Sized> SymbolParseError<V> {
+    pub(crate) fn new(input: &str, error: V::Err) -> SymbolParseError<V> {
+        SymbolParseError {
+            input: input.to_string(),
+            error,
+        }
+    }
+    /// The string which failed validation
+    pub fn input(&self) -> &str {
+        &self.input
+    }
+    /// Name of the validator type, as returned by `type_name`
+    pub fn validator(&self) -> &'static str {
+        type_name::<V>()
+    }
+    /// The error returned by the validator
+    pub fn error(&self) -> &V::Err {
+        &self.error
+    }
+    /// Unwrap the error returned by the validator
+    pub fn into_inner(self) -> V::Err {
+        self.error
+    }
+}
+
+impl<V: Validator + ?Sized> fmt::Display for SymbolParseError<V> {
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+        write!(fmt, "invalid {} {:?}: {}",
+            self.validator(), self.input, self.error)
+    }
+}
+
+impl<V: Validator + ?Sized> fmt::Debug for SymbolParseError<V> {
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+        fmt.debug_struct("SymbolParseError")
+            .field("validator", &self.validator())
+            .field("input", &self.input)
+            .field("error", &self.error)
+            .finish()
+    }
+}
+
+impl<V: Validator + ?Sized> Error for SymbolParseError<V>
+    where V::Err: Error + 'static
+{
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        Some(&self.error)
+    }
+}
+
+/// Error returned by `Symbol::intern_from_buf`
+pub enum FromBufError<V: Validator + ?Sized> {
+    /// The buffer is not valid UTF-8
+    Utf8(Utf8Error),
+    /// The string is longer than allowed
+    TooLong(TooLong),
+    /// The string was rejected by the validator
+    Invalid(V::Err),
+}
+
+impl<V: Validator + ?Sized> From<ValidationError<V>> for FromBufError<V> {
+    fn from(e: ValidationError<V>) -> FromBufError<V> {
+        match e {
+            ValidationError::TooLong(e) => FromBufError::TooLong(e),
+            ValidationError::Invalid(e) => FromBufError::Invalid(e),
+        }
+    }
+}
+
+impl<V: Validator + ?Sized> fmt::Display for FromBufError<V> {
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            FromBufError::Utf8(ref e) => write!(fmt, "invalid utf-8: {}", e),
+            FromBufError::TooLong(ref e) => fmt::Display::fmt(e, fmt),
+            FromBufError::Invalid(ref e) => fmt::Display::fmt(e, fmt),
+        }
+    }
+}
+
+impl<V: Validator + ?Sized> fmt::Debug for FromBufError<V> {
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            FromBufError::Utf8(ref e) => {
+                fmt.debug_tuple("Utf8").field(e).finish()
+            }
+            FromBufError::TooLong(ref e) => {
+                fmt.debug_tuple("TooLong").field(e).finish()
+            }
+            FromBufError::Invalid(ref e) => {
+                fmt.debug_tuple("Invalid").field(e).finish()
+            }
+        }
+    }
+}
+
+impl<V: Validator + ?Sized> Error for FromBufError<V>
+    where V::Err: Error + 'static
+{
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match *self {
+            FromBufError::Utf8(ref e) => Some(e),
+            FromBufError::TooLong(ref e) => Some(e),
+            FromBufError::Invalid(ref e) => Some(e),
+        }
+    }
+}
+
+/// Error returned by `Symbol::try_intern`
+pub enum TryInternError<V: Validator + ?Sized> {
+    /// The string is longer than allowed
+    TooLong(TooLong),
+    /// The string was rejected by the validator
+    Invalid(V::Err),
+    /// Memory for the new value or for the pool couldn't be allocated
+    ///
+    /// The error comes from `hashbrown` without `std` feature.
+    Alloc(TryReserveError),
+}
+
+impl<V: Validator + ?Sized> From<ValidationError<V>> for TryInternError<V> {
+    fn from(e: ValidationError<V>) -> TryInternError<V> {
+        match e {
+            ValidationError::TooLong(e) => TryInternError::TooLong(e),
+            ValidationError::Invalid(e) => TryInternError::Invalid(e),
+        }
+    }
+}
+
+impl<V: Validator + ?Sized> fmt::Display for TryInternError<V> {
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            TryInternError::TooLong(ref e) => fmt::Display::fmt(e, fmt),
+            TryInternError::Invalid(ref e) => fmt::Display::fmt(e, fmt),
+            #[cfg(feature = "std")]
+            TryInternError::Alloc(ref e) => {
+                write!(fmt, "can't intern symbol: {}", e)
+            }
+            // hashbrown's error doesn't implement `Display`
+            #[cfg(not(feature = "std"))]
+            TryInternError::Alloc(ref e) => {
+                write!(fmt, "can't intern symbol: {:?}", e)
+            }
+        }
+    }
+}
+
+impl<V: Validator + ?Sized> fmt::Debug for TryInternError<V> {
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            TryInternError::TooLong(ref e) => {
+                fmt.debug_tuple("TooLong").field(e).finish()
+            }
+            TryInternError::Invalid(ref e) => {
+                fmt.debug_tuple("Invalid").field(e).finish()
+            }
+            TryInternError::Alloc(ref e) => {
+                fmt.debug_tuple("Alloc").field(e).finish()
+            }
+        }
+    }
+}
+
+impl<V: Validator + ?Sized> Error for TryInternError<V>
+    where V::Err: Error + 'static
+{
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match *self {
+            TryInternError::TooLong(ref e) => Some(e),
+            TryInternError::Invalid(ref e) => Some(e),
+            #[cfg(feature = "std")]
+            TryInternError::Alloc(ref e) => Some(e),
+            #[cfg(not(feature = "std"))]
+            TryInternError::Alloc(_) => None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::io;
+    use std::error::Error;
+    use {Validator, Symbol};
+
+    struct AlphaNumString;
+    type AlphaNum = Symbol<AlphaNumString>;
+
+    impl Validator for AlphaNumString {
+        // Use an error from standard library to make example shorter
+        type Err = io::Error;
+        fn validate_symbol(s: &str) -> Result<(), Self::Err> {
+            if s.chars().any(|c| !c.is_alphanumeric()) {
+                return Err(io::Error::new(io::ErrorKind::InvalidData,
+                    "Character is not alphanumeric"));
+            }
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn display() {
+        let err = AlphaNum::parse_with_context("a-b").unwrap_err();
+        let text = err.to_string();
+        assert!(text.contains("\"a-b\""));
+        assert!(text.contains("Character is not alphanumeric"));
+        assert!(text.contains("AlphaNumString"));
+    }
+
+    #[test]
+    fn accessors() {
+        let err = AlphaNum::parse_with_context("a b").unwrap_err();
+        assert_eq!(err.input(), "a b");
+        assert!(err.validator().ends_with("::AlphaNumString"));
+        assert_eq!(err.error().kind(), io::ErrorKind::InvalidData);
+        assert!(err.source().is_some());
+        assert_eq!(err.into_inner().kind(), io::ErrorKind::InvalidData);
+    }
+
+    #[test]
+    fn ok() {
+        assert_eq!(AlphaNum::parse_with_context("ab1").unwrap(),
+                   AlphaNum::from("ab1"));
+    }
+}
@@ -0,0 +1,68 @@
+use arbitrary::{Arbitrary, Error, Result, Unstructured};
+
+use {Validator, Symbol};
+
+
+/// Number of strings tried before the input is rejected
+const ATTEMPTS: usize = 4;
+
+/// Takes strings from the data until a valid one is found
+///
+/// If none of a few strings is valid, or the data is exhausted, the input
+/// is rejected with `Error::IncorrectFormat`, which makes fuzzers skip it.
+impl<'a, V: Validator + ?Sized> Arbitrary<'a> for Symbol<V> {
+    fn arbitrary(u: &mut Unstructured<'a>) -> Result<Self> {
+        for _ in 0..ATTEMPTS {
+            if let Ok(sym) = <&str>::arbitrary(u)?.parse() {
+                return Ok(sym);
+            }
+            if u.is_empty() {
+                break;
+            }
+        }
+        Err(Error::IncorrectFormat)
+    }
+
+    fn arbitrary_take_rest(u: Unstructured<'a>) -> Result<Self> {
+        <&str>::arbitrary_take_rest(u)?.parse()
+            .map_err(|_| Error::IncorrectFormat)
+    }
+
+    fn size_hint(depth: usize) -> (usize, Option<usize>) {
+        <&str>::size_hint(depth)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use arbitrary::{Arbitrary, Error, Unstructured};
+    use Symbol;
+    use validators::{NonEmpty, Uuid};
+
+    #[test]
+    fn valid() {
+        let u = Unstructured::new(b"fuzz_valid");
+        let sym = Symbol::<NonEmpty>::arbitrary_take_rest(u).unwrap();
+        assert_eq!(sym, "fuzz_valid");
+        let mut u = Unstructured::new(b"\x00\x00\x00\x00fuzz_rest\x05");
+        let sym = Symbol::<NonEmpty>::arbitrary(&mut u).unwrap();
+        assert!(!sym.is_empty());
+    }
+
+    #[test]
+    fn rejected() {
+        let u = Unstructured::new(b"not a uuid");
+        assert!(matches!(Symbol::<Uuid>::arbitrary_take_rest(u),
+                         Err(Error::IncorrectFormat)));
+        let mut u = Unstructured::new(b"");
+        assert!(Symbol::<NonEmpty>::arbitrary(&mut u).is_err());
+    }
+
+    #[test]
+    fn nested() {
+        let data = b"fuzz_a\x00fuzz_b\x00\x00\x00\x00\x06\x06";
+        let mut u = Unstructured::new(data);
+        let syms = Vec::<Symbol<NonEmpty>>::arbitrary(&mut u).unwrap();
+        assert!(syms.iter().all(|s| !s.is_empty()));
+    }
+}
@@ -0,0 +1,287 @@
+use core::borrow::Borrow;
+use core::cmp::Ordering;
+use core::convert::Infallible;
+use core::fmt;
+use core::hash::{Hash, Hasher};
+use core::marker::PhantomData;
+use core::mem;
+use core::ops::Deref;
+use alloc::sync::Arc;
+
+#[cfg(feature = "serde")] use serde::ser::{Serialize, Serializer};
+#[cfg(feature = "serde")] use serde::de::{self, Deserialize, Deserializer};
+
+use pool::{item_pool, Item, Pool, PoolRef};
+
+
+/// Values which can be interned by `Intern`
+pub trait Internable: Hash + Eq + Send + Sync + 'static {}
+
+impl<T: Hash + Eq + Send + Sync + 'static> Internable for T {}
+
+/// Validator of `Intern` values
+///
+/// Each validator type has its own pool, so the validator may be a plain
+/// marker that separates unrelated values of the same type.
+pub trait InternValidator<T>: 'static {
+    type Err: fmt::Display + fmt::Debug;
+    fn validate(value: &T) -> Result<(), Self::Err>;
+}
+
+/// Accepts any value
+impl<T> InternValidator<T> for () {
+    type Err = Infallible;
+    fn validate(_: &T) -> Result<(), Self::Err> {
+        Ok(())
+    }
+}
+
+/// Interned value of an arbitrary type
+///
+/// Works like `Symbol`, but for any `T: Hash + Eq`. Equal values share
+/// the same allocation, and the value is removed from the pool when the
+/// last reference to it is dropped.
+///
+/// ```
+/// # use string_intern::Intern;
+/// let a = Intern::from(vec![1u32, 2, 3]);
+/// let b = Intern::from(vec![1u32, 2, 3]);
+/// assert_eq!(a, b);
+/// assert_eq!(a.as_ptr(), b.as_ptr());
+/// assert_eq!(a[1], 2);
+/// ```
+pub struct Intern<T: Internable, V: InternValidator<T> + ?Sized = ()>(
+    Arc<Node<T>>, PhantomData<V>);
+
+struct Node<T: Internable> {
+    value: Arc<T>,
+    pool: PoolRef<Node<T>>,
+    id: u32,
+}
+
+impl<T: Internable> Item for Node<T> {
+    type Key = Arc<T>;
+    type Query = T;
+    fn entry_key(node: &Arc<Node<T>>) -> Arc<T> {
+        node.value.clone()
+    }
+    fn size(value: &T) -> usize {
+        mem::size_of_val(value)
+    }
+}
+
+fn pool<T, V>() -> &'static Pool<Node<T>>
+    where T: Internable, V: InternValidator<T> + ?Sized,
+{
+    item_pool::<Node<T>, V>()
+}
+
+fn intern<T: Internable>(pool: &'static Pool<Node<T>>, value: T)
+    -> Arc<Node<T>>
+{
+    if let Some(node) = pool.lookup(&value) {
+        return node;
+    }
+    let mut atoms = pool.write(&value);
+    if let Some(node) = atoms.find(&value) {
+        return node;
+    }
+    let size = mem::size_of_val(&value);
+    let value = Arc::new(value);
+    atoms.insert_with(size, |id| {
+        Arc::new(Node { value, pool: pool.shared_ref(), id })
+    })
+}
+
+impl<T: Internable> Drop for Node<T> {
+    fn drop(&mut self) {
+        self.pool.release(&self.value, self.id);
+    }
+}
+
+impl<T: Internable, V: InternValidator<T> + ?Sized> Intern<T, V> {
+    /// Validate and intern the value
+    pub fn new(value: T) -> Result<Intern<T, V>, V::Err> {
+        V::validate(&value)?;
+        Ok(Intern(intern(pool::<T, V>(), value), PhantomData))
+    }
+
+    /// Returns the interned value if there is one
+    pub fn get(value: &T) -> Option<Intern<T, V>> {
+        pool::<T, V>().lookup(value).map(|n| Intern(n, PhantomData))
+    }
+
+    /// Pointer to the value, which is the same for all equal values
+    pub fn as_ptr(&self) -> *const T {
+        &*self.0.value
+    }
+}
+
+impl<T: Internable> From<T> for Intern<T> {
+    fn from(value: T) -> Intern<T> {
+        match Intern::new(value) {
+            Ok(value) => value,
+            Err(e) => match e {},
+        }
+    }
+}
+
+impl<T: Internable, V: InternValidator<T> + ?Sized> Clone for Intern<T, V> {
+    fn clone(&self) -> Intern<T, V> {
+        Intern(self.0.clone(), PhantomData)
+    }
+}
+
+// There is a single pool for each type, so comparing pointers is enough
+impl<T: Internable, V: InternValidator<T> + ?Sized> PartialEq
+    for Intern<T, V>
+{
+    fn eq(&self, other: &Intern<T, V>) -> bool {
+        Arc::ptr_eq(&self.0, &other.0)
+    }
+}
+
+impl<T: Internable, V: InternValidator<T> + ?Sized> Eq for Intern<T, V> {}
+
+// Must match the hash of `T` for `Borrow<T>`
+impl<T: Internable, V: InternValidator<T> + ?Sized> Hash for Intern<T, V> {
+    fn hash<H: Hasher>(&self, hasher: &mut H) {
+        (**self).hash(hasher)
+    }
+}
+
+impl<T, V> PartialOrd for Intern<T, V>
+    where T: Internable + Ord, V: InternValidator<T> + ?Sized,
+{
+    fn partial_cmp(&self, other: &Intern<T, V>) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl<T, V> Ord for Intern<T, V>
+    where T: Internable + Ord, V: InternValidator<T> + ?Sized,
+{
+    fn cmp(&self, other: &Intern<T, V>) -> Ordering {
+        (**self).cmp(&**other)
+    }
+}
+
+impl<T: Internable, V: InternValidator<T> + ?Sized> Deref for Intern<T, V> {
+    type Target = T;
+    fn deref(&self) -> &T {
+        &self.0.value
+    }
+}
+
+impl<T: Internable, V: InternValidator<T> + ?Sized> AsRef<T>
+    for Intern<T, V>
+{
+    fn as_ref(&self) -> &T {
+        &self.0.value
+    }
+}
+
+impl<T: Internable, V: InternValidator<T> + ?Sized> Borrow<T>
+    for Intern<T, V>
+{
+    fn borrow(&self) -> &T {
+        &self.0.value
+    }
+}
+
+impl<T, V> fmt::Debug for Intern<T, V>
+    where T: Internable + fmt::Debug, V: InternValidator<T> + ?Sized,
+{
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+        fmt::Debug::fmt(&**self, fmt)
+    }
+}
+
+impl<T, V> fmt::Display for Intern<T, V>
+    where T: Internable + fmt::Display, V: InternValidator<T> + ?Sized,
+{
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+        fmt::Display::fmt(&**self, fmt)
+    }
+}
+
+#[cfg(feature = "serde")]
+impl<T, V> Serialize for Intern<T, V>
+    where T: Internable + Serialize, V: InternValidator<T> + ?Sized,
+{
+    fn serialize<S: Serializer>(&self, serializer: S)
+        -> Result<S::Ok, S::Error>
+    {
+        (**self).serialize(serializer)
+    }
+}
+
+#[cfg(feature = "serde")]
+impl<'de, T, V> Deserialize<'de> for Intern<T, V>
+    where T: Internable + Deserialize<'de>, V: InternValidator<T> + ?Sized,
+{
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+        where D: Deserializer<'de>
+    {
+        let value = T::deserialize(deserializer)?;
+        Intern::new(value).map_err(de::Error::custom)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::io;
+    use super::{Intern, InternValidator, pool};
+
+    struct Short;
+
+    impl InternValidator<Vec<u32>> for Short {
+        type Err = io::Error;
+        fn validate(value: &Vec<u32>) -> Result<(), Self::Err> {
+            if value.len() > 3 {
+                return Err(io::Error::new(io::ErrorKind::InvalidData,
+                    "too long"));
+            }
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn intern() {
+        let a = Intern::from(vec![10u32, 11]);
+        let b = Intern::from(vec![10u32, 11]);
+        assert_eq!(a, b);
+        assert_eq!(a.as_ptr(), b.as_ptr());
+        assert_eq!(&*a, &vec![10, 11]);
+        assert_eq!(format!("{:?}", a), "[10, 11]");
+        assert_eq!(Intern::get(&vec![10u32, 11]), Some(a.clone()));
+        assert!(Intern::from(vec![10u32]) < a);
+        drop((a, b));
+        assert_eq!(Intern::<Vec<u32>>::get(&vec![10, 11]), None);
+        assert!(pool::<Vec<u32>, ()>().read(&vec![10, 11])
+            .get(&vec![10, 11]).is_none());
+    }
+
+    #[test]
+    fn validator() {
+        assert!(Intern::<Vec<u32>, Short>::new(vec![20, 21, 22, 23])
+            .is_err());
+        let a = Intern::<Vec<u32>, Short>::new(vec![20, 21]).unwrap();
+        let b = Intern::from(vec![20u32, 21]);
+        // validators have separate pools
+        assert_ne!(a.as_ptr(), b.as_ptr());
+    }
+
+    #[test]
+    #[cfg(feature = "serde")]
+    fn serde() {
+        use serde_json;
+        let a = Intern::from((30u8, String::from("x")));
+        let json = serde_json::to_string(&a).unwrap();
+        assert_eq!(json, r#"[30,"x"]"#);
+        let b: Intern<(u8, String)> = serde_json::from_str(&json).unwrap();
+        assert_eq!(a, b);
+        assert!(serde_json::from_str::<Intern<Vec<u32>, Short>>("[1,2,3,4]")
+            .is_err());
+    }
+}
@@ -0,0 +1,294 @@
+use alloc::borrow::Cow;
+use core::fmt;
+use core::marker::PhantomData;
+use alloc::sync::Arc;
+
+use {Validator, Symbol};
+use base_type::intern_locked;
+use pool::{Pool, PoolRef, PoolLimits, PoolStats};
+use validator::{validate, fold};
+use compat::prelude::*;
+
+#[cfg(feature = "serde")]
+use serde::de::{self, Deserializer, DeserializeSeed, Visitor};
+
+
+/// A pool of symbols separate from the global one
+///
+/// Symbols created by `Symbol::from_str` and friends live in a single
+/// process-wide pool. This type owns its own table instead, which is
+/// useful to keep values of a connection or a compilation unit isolated.
+///
+/// Symbols of the interner are regular `Symbol`s, they keep the table
+/// alive, so they may outlive the interner itself. The same value
+/// interned in different pools is stored separately, such symbols still
+/// compare equal.
+///
+/// ```
+/// # use string_intern::{Validator, Interner};
+/// # struct Tag;
+/// # impl Validator for Tag {
+/// #     type Err = ::std::string::ParseError;
+/// #     fn validate_symbol(_: &str) -> Result<(), Self::Err> { Ok(()) }
+/// # }
+/// let pool = Interner::<Tag>::new();
+/// let a = pool.intern("a")?;
+/// assert_eq!(pool.len(), 1);
+/// assert_eq!(pool.get("a"), Some(a));
+/// # Ok::<(), ::std::string::ParseError>(())
+/// ```
+///
+/// With `serde`, `&Interner` is a `DeserializeSeed` that deserializes a
+/// symbol into this pool.
+pub struct Interner<V: Validator + ?Sized> {
+    pool: Arc<Pool>,
+    phantom: PhantomData<V>,
+}
+
+impl<V: Validator + ?Sized> Interner<V> {
+    /// Create an empty pool
+    pub fn new() -> Interner<V> {
+        Interner {
+            pool: Arc::new(Pool::new()),
+            phantom: PhantomData,
+        }
+    }
+
+    /// Create an empty pool bounded by `limits`
+    pub fn with_limits(limits: PoolLimits) -> Interner<V> {
+        let interner = Interner::new();
+        interner.pool.set_limits(&limits);
+        interner
+    }
+
+    /// Validate and intern the value in this pool
+    pub fn intern(&self, s: &str) -> Result<Symbol<V>, V::Err> {
+        let s = &validate::<V>(s)?[..];
+        if let Some(sym) = self.get(s) {
+            return Ok(sym);
+        }
+        let key = fold::<V>(s);
+        let owner = PoolRef::Local(self.pool.clone());
+        let mut atoms = self.pool.write(key.as_deref().unwrap_or(s));
+        let (value, _) = intern_locked(&mut atoms, Cow::Borrowed(s),
+                                       key.as_deref(), &owner);
+        Ok(Symbol(value, PhantomData))
+    }
+
+    /// Returns the symbol if the value is interned in this pool
+    ///
+    /// The value is normalized but not validated, as only valid values can
+    /// be interned.
+    pub fn get(&self, s: &str) -> Option<Symbol<V>> {
+        let s = V::normalize(s);
+        let key = &V::fold(&s)[..];
+        let atoms = self.pool.read(key);
+        let entry = atoms.get(key)?;
+        let value = entry.upgrade()?;
+        self.pool.touch(entry);
+        Some(Symbol(value, PhantomData))
+    }
+
+    /// Returns the number of values alive in this pool
+    pub fn len(&self) -> usize {
+        self.pool.read_shards()
+            .map(|atoms| atoms.values().filter(|w| w.strong_count() > 0)
+                              .count())
+            .sum()
+    }
+
+    /// Returns `true` if no values are alive in this pool
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Returns all symbols alive in this pool, in unspecified order
+    ///
+    /// Same as `Symbol::iter_interned` for the shared pool.
+    pub fn iter_interned(&self) -> Vec<Symbol<V>> {
+        let mut result = Vec::new();
+        for atoms in self.pool.read_shards() {
+            result.extend(atoms.values()
+                .filter_map(|w| w.upgrade())
+                .map(|a| Symbol(a, PhantomData)));
+        }
+        result
+    }
+
+    /// Reserve capacity for at least `additional` more values
+    ///
+    /// Same as `Symbol::reserve` for the shared pool.
+    pub fn reserve(&self, additional: usize) {
+        self.pool.reserve(additional);
+    }
+
+    /// Returns the number of values the pool can hold without reallocating
+    pub fn capacity(&self) -> usize {
+        self.pool.capacity()
+    }
+
+    /// Returns statistics of this pool
+    pub fn stats(&self) -> PoolStats {
+        self.pool.stats()
+    }
+}
+
+impl<V: Validator + ?Sized> Default for Interner<V> {
+    fn default() -> Interner<V> {
+        Interner::new()
+    }
+}
+
+impl<V: Validator + ?Sized> fmt::Debug for Interner<V> {
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+        fmt.debug_struct("Interner")
+            .field("len", &self.len())
+            .finish()
+    }
+}
+
+/// Deserializes a symbol and interns it in this pool
+#[cfg(feature = "serde")]
+impl<'de, V: Validator + ?Sized> DeserializeSeed<'de> for &Interner<V> {
+    type Value = Symbol<V>;
+
+    fn deserialize<D>(self, deserializer: D) -> Result<Symbol<V>, D::Error>
+        where D: Deserializer<'de>
+    {
+        deserializer.deserialize_str(self)
+    }
+}
+
+#[cfg(feature = "serde")]
+impl<'de, V: Validator + ?Sized> Visitor<'de> for &Interner<V> {
+    type Value = Symbol<V>;
+
+    fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        formatter.write_str("a valid symbol")
+    }
+
+    fn visit_str<E>(self, v: &str) -> Result<Self::Value, E>
+        where E: de::Error
+    {
+        self.intern(v).map_err(de::Error::custom)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::io;
+    use {Validator, Symbol, Interner, PoolLimits};
+
+    struct AnyString;
+    type Atom = Symbol<AnyString>;
+
+    impl Validator for AnyString {
+        // Use an error from standard library to make example shorter
+        type Err = ::std::string::ParseError;
+        fn validate_symbol(_: &str) -> Result<(), Self::Err> {
+            Ok(())
+        }
+    }
+
+    struct AlphaNumString;
+
+    impl Validator for AlphaNumString {
+        // Use an error from standard library to make example shorter
+        type Err = io::Error;
+        fn validate_symbol(s: &str) -> Result<(), Self::Err> {
+            if s.chars().any(|c| !c.is_alphanumeric()) {
+                return Err(io::Error::new(io::ErrorKind::InvalidData,
+                    "Character is not alphanumeric"));
+            }
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn intern() {
+        let pool = Interner::<AnyString>::new();
+        assert!(pool.is_empty());
+        let a = pool.intern("local_a").unwrap();
+        let b = pool.intern("local_a").unwrap();
+        assert_eq!(a.as_ptr(), b.as_ptr());
+        assert_eq!(pool.get("local_a").unwrap().as_ptr(), a.as_ptr());
+        assert_eq!(pool.get("local_b"), None);
+        assert_eq!(pool.len(), 1);
+        assert_eq!(pool.stats().live, 1);
+        assert_eq!(pool.stats().bytes, 7);
+        assert_eq!(pool.iter_interned(), vec![a.clone()]);
+        drop((a, b));
+        assert_eq!(pool.get("local_a"), None);
+        assert_eq!(pool.len(), 0);
+    }
+
+    #[test]
+    fn reserve() {
+        let pool = Interner::<AnyString>::new();
+        pool.reserve(1000);
+        assert!(pool.capacity() >= 1000);
+        assert!(pool.is_empty());
+    }
+
+    #[test]
+    fn limits() {
+        let pool = Interner::<AnyString>::with_limits(
+            PoolLimits::new().max_entries(32));
+        let symbols = (0..100)
+            .map(|i| pool.intern(&format!("local_limits_{}", i)).unwrap())
+            .collect::<Vec<_>>();
+        assert!(pool.stats().live <= 32);
+        assert_eq!(pool.intern("local_limits_0").unwrap(), symbols[0]);
+    }
+
+    #[test]
+    fn isolated() {
+        let pool1 = Interner::<AnyString>::new();
+        let pool2 = Interner::<AnyString>::new();
+        let global = Atom::from("local_isolated");
+        let a = pool1.intern("local_isolated").unwrap();
+        let b = pool2.intern("local_isolated").unwrap();
+        assert!(a.as_ptr() != b.as_ptr());
+        assert!(a.as_ptr() != global.as_ptr());
+        assert_eq!(a, global);
+        drop(global);
+        assert!(!Atom::intern_tracked("local_isolated").unwrap().1);
+        assert_eq!(pool1.len(), 1);
+        assert_eq!(pool2.len(), 1);
+    }
+
+    #[test]
+    fn outlives_interner() {
+        let pool = Interner::<AnyString>::new();
+        let a = pool.intern("local_outlives").unwrap();
+        let b = a.clone();
+        drop(pool);
+        assert_eq!(&a[..], "local_outlives");
+        drop(a);
+        assert_eq!(&b[..], "local_outlives");
+    }
+
+    #[test]
+    fn validates() {
+        let pool = Interner::<AlphaNumString>::new();
+        let _x = pool.intern("local1").unwrap();
+        assert!(pool.intern("local-2").is_err());
+        assert_eq!(pool.len(), 1);
+    }
+
+    #[test]
+    #[cfg(feature = "serde")]
+    fn deserialize_seed() {
+        use serde::de::DeserializeSeed;
+        use serde_json;
+        let pool = Interner::<AlphaNumString>::new();
+        let mut de = serde_json::Deserializer::from_str(r#""seeded""#);
+        let a = (&pool).deserialize(&mut de).unwrap();
+        assert_eq!(pool.get("seeded").unwrap().as_ptr(), a.as_ptr());
+        assert!(a.as_ptr() != Symbol::<AlphaNumString>::from("seeded")
+                              .as_ptr());
+        let mut de = serde_json::Deserializer::from_str(r#""seed-ed""#);
+        assert!((&pool).deserialize(&mut de).is_err());
+        assert_eq!(pool.len(), 1);
+    }
+}
@@ -0,0 +1,217 @@
+use core::borrow::Borrow;
+use core::cmp::Ordering;
+use core::fmt;
+use core::hash::{Hash, Hasher};
+use core::ops::Deref;
+
+use {Validator, Symbol};
+
+
+/// A symbol hashed by `Symbol::precomputed_hash`
+///
+/// Hashing a symbol hashes the whole string, so that lookups by `&str` in
+/// hash maps work. This wrapper feeds the hash computed when the value was
+/// interned instead, which doesn't depend on the length of the value, but
+/// the map can only be queried with `Prehashed` keys.
+///
+/// ```
+/// # use std::collections::HashMap;
+/// # use string_intern::{Validator, Symbol, Prehashed};
+/// # struct Tag;
+/// # impl Validator for Tag {
+/// #     type Err = ::std::string::ParseError;
+/// #     fn validate_symbol(_: &str) -> Result<(), Self::Err> { Ok(()) }
+/// # }
+/// let mut counts = HashMap::new();
+/// *counts.entry(Prehashed(Symbol::<Tag>::from("a"))).or_insert(0) += 1;
+/// assert_eq!(counts[&Prehashed(Symbol::from("a"))], 1);
+/// ```
+pub struct Prehashed<V: Validator + ?Sized>(pub Symbol<V>);
+
+impl<V: Validator + ?Sized> Hash for Prehashed<V> {
+    fn hash<H: Hasher>(&self, hasher: &mut H) {
+        hasher.write_u64(self.0.precomputed_hash())
+    }
+}
+
+impl<V: Validator + ?Sized> PartialEq for Prehashed<V> {
+    fn eq(&self, other: &Prehashed<V>) -> bool {
+        self.0 == other.0
+    }
+}
+
+impl<V: Validator + ?Sized> Eq for Prehashed<V> {}
+
+impl<V: Validator + ?Sized> Clone for Prehashed<V> {
+    fn clone(&self) -> Prehashed<V> {
+        Prehashed(self.0.clone())
+    }
+}
+
+impl<V: Validator + ?Sized> Deref for Prehashed<V> {
+    type Target = Symbol<V>;
+    fn deref(&self) -> &Symbol<V> {
+        &self.0
+    }
+}
+
+impl<V: Validator + ?Sized> Borrow<Symbol<V>> for Prehashed<V> {
+    fn borrow(&self) -> &Symbol<V> {
+        &self.0
+    }
+}
+
+impl<V: Validator + ?Sized> From<Symbol<V>> for Prehashed<V> {
+    fn from(sym: Symbol<V>) -> Prehashed<V> {
+        Prehashed(sym)
+    }
+}
+
+impl<V: Validator + ?Sized> fmt::Debug for Prehashed<V> {
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+        fmt::Debug::fmt(&self.0, fmt)
+    }
+}
+
+/// A symbol ordered by `Symbol::id` rather than by its value
+///
+/// Comparisons are O(1) regardless of the length of values, which makes
+/// sorting and `BTreeMap` lookups faster, but the order is arbitrary and
+/// changes between runs. Keys are compared by identity, same as in
+/// `SymbolMap`: symbols with the same value from different pools (or kept
+/// across `Symbol::clear`) are different keys.
+///
+/// ```
+/// # use std::collections::BTreeSet;
+/// # use string_intern::{Validator, Symbol};
+/// # struct Tag;
+/// # impl Validator for Tag {
+/// #     type Err = ::std::string::ParseError;
+/// #     fn validate_symbol(_: &str) -> Result<(), Self::Err> { Ok(()) }
+/// # }
+/// let mut set = BTreeSet::new();
+/// set.insert(Symbol::<Tag>::from("b").by_id());
+/// set.insert(Symbol::<Tag>::from("a").by_id());
+/// assert!(set.contains(&Symbol::from("a").by_id()));
+/// ```
+pub struct IdOrdered<V: Validator + ?Sized>(pub Symbol<V>);
+
+impl<V: Validator + ?Sized> Symbol<V> {
+    /// Wrap a clone of the symbol to be ordered by id, see `IdOrdered`
+    pub fn by_id(&self) -> IdOrdered<V> {
+        IdOrdered(self.clone())
+    }
+}
+
+impl<V: Validator + ?Sized> IdOrdered<V> {
+    // ids are only unique within a pool, the address breaks ties
+    fn sort_key(&self) -> (u32, *const u8) {
+        (self.0.id(), self.0.as_ptr())
+    }
+}
+
+impl<V: Validator + ?Sized> PartialEq for IdOrdered<V> {
+    fn eq(&self, other: &IdOrdered<V>) -> bool {
+        self.sort_key() == other.sort_key()
+    }
+}
+
+impl<V: Validator + ?Sized> Eq for IdOrdered<V> {}
+
+impl<V: Validator + ?Sized> PartialOrd for IdOrdered<V> {
+    fn partial_cmp(&self, other: &IdOrdered<V>) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl<V: Validator + ?Sized> Ord for IdOrdered<V> {
+    fn cmp(&self, other: &IdOrdered<V>) -> Ordering {
+        self.sort_key().cmp(&other.sort_key())
+    }
+}
+
+impl<V: Validator + ?Sized> Hash for IdOrdered<V> {
+    fn hash<H: Hasher>(&self, hasher: &mut H) {
+        self.sort_key().hash(hasher)
+    }
+}
+
+impl<V: Validator + ?Sized> Clone for IdOrdered<V> {
+    fn clone(&self) -> IdOrdered<V> {
+        IdOrdered(self.0.clone())
+    }
+}
+
+impl<V: Validator + ?Sized> Deref for IdOrdered<V> {
+    type Target = Symbol<V>;
+    fn deref(&self) -> &Symbol<V> {
+        &self.0
+    }
+}
+
+impl<V: Validator + ?Sized> From<Symbol<V>> for IdOrdered<V> {
+    fn from(sym: Symbol<V>) -> IdOrdered<V> {
+        IdOrdered(sym)
+    }
+}
+
+impl<V: Validator + ?Sized> fmt::Debug for IdOrdered<V> {
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+        fmt::Debug::fmt(&self.0, fmt)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::collections::{BTreeMap, HashSet};
+    use {Validator, Symbol, Interner, Prehashed};
+
+    struct AnyString;
+    type Atom = Symbol<AnyString>;
+
+    impl Validator for AnyString {
+        // Use an error from standard library to make example shorter
+        type Err = ::std::string::ParseError;
+        fn validate_symbol(_: &str) -> Result<(), Self::Err> {
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn precomputed_hash() {
+        let a = Atom::from("prehashed_a");
+        let pool = Interner::<AnyString>::new();
+        let local = pool.intern("prehashed_a").unwrap();
+        assert_eq!(a.precomputed_hash(), local.precomputed_hash());
+        assert!(a.precomputed_hash() !=
+                Atom::from("prehashed_b").precomputed_hash());
+    }
+
+    #[test]
+    fn id_ordered() {
+        let a = Atom::from("id_ordered_a");
+        let b = Atom::from("id_ordered_b");
+        let local = Interner::<AnyString>::new().intern("id_ordered_a")
+            .unwrap();
+        let mut map = BTreeMap::new();
+        map.insert(a.by_id(), 1);
+        map.insert(b.by_id(), 2);
+        map.insert(local.by_id(), 3);
+        assert_eq!(map.len(), 3);
+        assert_eq!(map[&Atom::from("id_ordered_a").by_id()], 1);
+        assert_eq!(map[&local.by_id()], 3);
+        let mut sorted = [b.by_id(), a.by_id()];
+        sorted.sort();
+        assert!(sorted[0].id() <= sorted[1].id());
+    }
+
+    #[test]
+    fn prehashed_keys() {
+        let pool = Interner::<AnyString>::new();
+        let set = vec![Atom::from("prehashed_x"), Atom::from("prehashed_y"),
+                       pool.intern("prehashed_x").unwrap()]
+            .into_iter().map(Prehashed).collect::<HashSet<_>>();
+        assert_eq!(set.len(), 2);
+        assert!(set.contains(&Prehashed(Atom::from("prehashed_y"))));
+    }
+}
@@ -26,16 +26,97 @@
 //! // Both point to the same bytes
 //! assert!(x[..].as_bytes() as *const _ == y[..].as_bytes() as *const _);
 //! ```
+//!
+//! # `no_std`
+//!
+//! Disable the default `std` feature and enable `spin` to use the crate
+//! with `alloc` only. Locks are spinlocks then, `InternScope` and
+//! `OsSymbol` are not available, and the pool is hashed with a fixed-seed
+//! hasher unless the `ahash` feature is enabled. The target must support
+//! atomic operations on pointers.
+#![cfg_attr(not(feature = "std"), no_std)]
+
+#[cfg(not(any(feature = "std", feature = "spin")))]
+compile_error!("either `std` or `spin` feature must be enabled");
+
+#[cfg(feature = "std")] extern crate core;
+extern crate alloc;
 #[macro_use] extern crate lazy_static;
 #[cfg(feature = "rustc-serialize")] extern crate rustc_serialize;
 #[cfg(feature = "serde")] extern crate serde;
+#[cfg(feature = "schemars")] #[macro_use] extern crate schemars;
+#[cfg(feature = "arc-swap")] extern crate arc_swap;
+#[cfg(feature = "unicode-normalization")] extern crate unicode_normalization;
+#[cfg(feature = "regex")] extern crate regex;
+#[cfg(feature = "derive")] extern crate string_intern_derive;
+#[cfg(feature = "rayon")] extern crate rayon;
+#[cfg(feature = "proptest")] extern crate proptest;
+#[cfg(feature = "arbitrary")] extern crate arbitrary;
+#[cfg(feature = "rkyv")] extern crate rkyv;
+#[cfg(feature = "borsh")] extern crate borsh;
+#[cfg(feature = "diesel")] extern crate diesel;
+#[cfg(feature = "sqlx")] extern crate sqlx;
+#[cfg(feature = "ahash")] extern crate ahash;
+#[cfg(feature = "parking_lot")] extern crate parking_lot;
+#[cfg(not(feature = "std"))] extern crate spin;
+#[cfg(not(feature = "std"))] extern crate hashbrown;
 #[cfg(test)] extern crate serde_json;
+#[cfg(test)] extern crate bincode;
 
-mod base_type;
+mod compat;
+#[macro_use] mod base_type;
+mod error;
 mod validator;
+mod pool;
+#[macro_use] mod static_symbol;
+pub mod validators;
+mod weak;
+mod scope;
+mod interner;
+mod map;
+mod keys;
+mod bytes_symbol;
+#[cfg(feature = "std")] mod os_symbol;
+#[cfg(feature = "std")] mod local;
+mod c_symbol;
+mod intern;
+#[cfg(feature = "serde")] mod bytes;
+#[cfg(feature = "serde")] mod seq;
+#[cfg(all(feature = "serde", feature = "std"))] mod dedup;
+#[cfg(feature = "schemars")] mod schema;
+#[cfg(feature = "arc-swap")] mod snapshot;
+#[cfg(feature = "rayon")] mod parallel;
+#[cfg(feature = "proptest")] mod strategy;
+#[cfg(feature = "arbitrary")] mod fuzz;
+#[cfg(feature = "rkyv")] mod archive;
+#[cfg(feature = "borsh")] mod wire;
+#[cfg(feature = "diesel")] mod sql;
+#[cfg(feature = "sqlx")] mod database;
+#[cfg(feature = "thread-cache")] mod thread_cache;
+
+pub use base_type::{Symbol, SymbolEntry};
+pub use error::{SymbolParseError, FromBufError, TryInternError};
+pub use error::{ValidationError, TooLong};
+pub use validator::{Validator, set_global_max_len, global_max_len};
+pub use static_symbol::{StaticSymbol, StaticAtoms};
+pub use weak::WeakSymbol;
+#[cfg(feature = "std")] pub use scope::{InternScope, InternOptions};
+pub use interner::Interner;
+pub use map::{SymbolMap, SymbolSet};
+pub use keys::{Prehashed, IdOrdered};
+pub use bytes_symbol::{BytesSymbol, BytesValidator};
+#[cfg(feature = "std")] pub use os_symbol::OsSymbol;
+#[cfg(feature = "std")] pub use local::LocalSymbol;
+pub use c_symbol::CSymbol;
+pub use intern::{Intern, InternValidator, Internable};
+pub use pool::{purge_dead, PoolLimits, PoolStats, DropPolicy};
+#[cfg(feature = "serde")] pub use bytes::SymbolBytes;
+#[cfg(feature = "serde")] pub use seq::InternSeq;
+#[cfg(all(feature = "serde", feature = "std"))] pub use dedup::Dedup;
+#[cfg(feature = "derive")] pub use string_intern_derive::SymbolValidator;
 
-pub use base_type::Symbol;
-pub use validator::Validator;
+// Used by macros, `::core` isn't available to 2015 edition crates
+#[doc(hidden)] pub use core::fmt as __fmt;
 
 #[cfg(test)]
 mod test {
@@ -0,0 +1,334 @@
+use std::any::TypeId;
+use std::borrow::Borrow;
+use std::cell::RefCell;
+use std::cmp::Ordering;
+use std::collections::HashMap;
+use std::convert::TryFrom;
+use std::fmt;
+use std::hash::{Hash, Hasher};
+use std::marker::PhantomData;
+use std::ops::Deref;
+use std::rc::{Rc, Weak};
+use std::str::FromStr;
+
+#[cfg(feature = "serde")] use serde::ser::{Serialize, Serializer};
+#[cfg(feature = "serde")]
+use serde::de::{self, Deserialize, Deserializer, Visitor};
+
+use {Validator, Symbol};
+use base_type::intern_dynamic;
+use validator::{validate, fold};
+
+
+/// Symbol which is interned in a table of the current thread
+///
+/// Same as `Symbol`, but reference counting is not atomic and the table
+/// is not locked, which is faster for single-threaded programs (like
+/// interpreters). The symbol can't be sent to another thread, use
+/// `to_symbol` for that. Each thread has its own table for each validator
+/// type, so the same value interned in two threads gives two different
+/// allocations.
+///
+/// `Validator::debug` and `Validator::display` are not used, as they take
+/// a `Symbol`, both format the bare value (quoted for `Debug`).
+pub struct LocalSymbol<V: Validator + ?Sized>(Rc<LocalValue>, PhantomData<V>);
+
+type LocalAtoms = HashMap<Rc<str>, Weak<LocalValue>>;
+
+struct LocalValue {
+    buf: Rc<str>,
+    // same as `Value::key`
+    key: Option<Rc<str>>,
+    // keeps the table alive after thread-local storage is destroyed
+    table: Rc<RefCell<LocalAtoms>>,
+}
+
+thread_local! {
+    static TABLES: RefCell<HashMap<TypeId, Rc<RefCell<LocalAtoms>>>> =
+        RefCell::new(HashMap::new());
+}
+
+fn table<V: Validator + ?Sized>() -> Rc<RefCell<LocalAtoms>> {
+    let new_table = || Rc::new(RefCell::new(LocalAtoms::new()));
+    TABLES.try_with(|tables| {
+        tables.borrow_mut().entry(TypeId::of::<V>())
+            .or_insert_with(new_table)
+            .clone()
+    // a symbol interned while the thread is exiting gets its own table
+    }).unwrap_or_else(|_| new_table())
+}
+
+impl LocalValue {
+    fn key(&self) -> &str {
+        self.key.as_ref().unwrap_or(&self.buf)
+    }
+}
+
+fn intern(table: Rc<RefCell<LocalAtoms>>, s: &str, key: Option<String>)
+    -> Rc<LocalValue>
+{
+    let mut atoms = table.borrow_mut();
+    if let Some(value) = atoms.get(key.as_deref().unwrap_or(s))
+        .and_then(|w| w.upgrade())
+    {
+        return value;
+    }
+    let buf = Rc::<str>::from(s);
+    let key = key.map(Rc::<str>::from);
+    let map_key = key.clone().unwrap_or_else(|| buf.clone());
+    let value = Rc::new(LocalValue { buf, key, table: table.clone() });
+    atoms.insert(map_key, Rc::downgrade(&value));
+    value
+}
+
+impl Drop for LocalValue {
+    fn drop(&mut self) {
+        // nothing can intern the same value while it's being dropped, so
+        // the entry is always the entry of this value
+        self.table.borrow_mut().remove(self.key());
+    }
+}
+
+impl<V: Validator + ?Sized> LocalSymbol<V> {
+    /// Create a symbol from a static string
+    ///
+    /// # Panics
+    ///
+    /// When the value is invalid, same as `Symbol::from`.
+    pub fn from(s: &'static str) -> LocalSymbol<V> {
+        FromStr::from_str(s)
+            .expect("static string used as atom is invalid")
+    }
+
+    /// Returns the symbol if the value is interned in this thread already
+    pub fn get(s: &str) -> Option<LocalSymbol<V>> {
+        let value = V::normalize(s);
+        let key = V::fold(&value);
+        let table = table::<V>();
+        let found = RefCell::borrow(&table).get(&key[..])
+            .and_then(|w| w.upgrade());
+        found.map(|v| LocalSymbol(v, PhantomData))
+    }
+
+    /// Intern the value into the shared pool, e.g. to send it to another
+    /// thread
+    pub fn to_symbol(&self) -> Symbol<V> {
+        let key = self.0.key.as_ref().map(|k| k.to_string());
+        Symbol(intern_dynamic::<V>((&self.0.buf[..]).into(), key).0,
+               PhantomData)
+    }
+}
+
+impl<V: Validator + ?Sized> FromStr for LocalSymbol<V> {
+    type Err = V::Err;
+    fn from_str(s: &str) -> Result<LocalSymbol<V>, Self::Err> {
+        let value = validate::<V>(s)?;
+        let key = fold::<V>(&value);
+        Ok(LocalSymbol(intern(table::<V>(), &value, key), PhantomData))
+    }
+}
+
+/// Same as `from_str`
+impl<'a, V: Validator + ?Sized> TryFrom<&'a str> for LocalSymbol<V> {
+    type Error = V::Err;
+    fn try_from(s: &'a str) -> Result<LocalSymbol<V>, Self::Error> {
+        FromStr::from_str(s)
+    }
+}
+
+impl<V: Validator + ?Sized> Clone for LocalSymbol<V> {
+    fn clone(&self) -> LocalSymbol<V> {
+        LocalSymbol(self.0.clone(), PhantomData)
+    }
+}
+
+// Values are never evicted from the table, so comparing pointers is enough
+impl<V: Validator + ?Sized> PartialEq for LocalSymbol<V> {
+    fn eq(&self, other: &LocalSymbol<V>) -> bool {
+        Rc::ptr_eq(&self.0, &other.0)
+    }
+}
+
+impl<V: Validator + ?Sized> Eq for LocalSymbol<V> {}
+
+// Same as the hash of `Symbol`, see comment there
+impl<V: Validator + ?Sized> Hash for LocalSymbol<V> {
+    fn hash<H: Hasher>(&self, hasher: &mut H) {
+        self.0.key().hash(hasher)
+    }
+}
+
+impl<V: Validator + ?Sized> PartialOrd for LocalSymbol<V> {
+    fn partial_cmp(&self, other: &LocalSymbol<V>) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl<V: Validator + ?Sized> Ord for LocalSymbol<V> {
+    fn cmp(&self, other: &LocalSymbol<V>) -> Ordering {
+        V::compare(self, other)
+    }
+}
+
+impl<V: Validator + ?Sized> PartialEq<str> for LocalSymbol<V> {
+    fn eq(&self, other: &str) -> bool {
+        self.0.key() == V::fold(other)
+    }
+}
+
+impl<'a, V: Validator + ?Sized> PartialEq<&'a str> for LocalSymbol<V> {
+    fn eq(&self, other: &&'a str) -> bool {
+        self.eq(*other)
+    }
+}
+
+impl<V: Validator + ?Sized> Deref for LocalSymbol<V> {
+    type Target = str;
+    fn deref(&self) -> &str {
+        &self.0.buf
+    }
+}
+
+impl<V: Validator + ?Sized> AsRef<str> for LocalSymbol<V> {
+    fn as_ref(&self) -> &str {
+        &self.0.buf
+    }
+}
+
+impl<V: Validator + ?Sized> Borrow<str> for LocalSymbol<V> {
+    fn borrow(&self) -> &str {
+        &self.0.buf
+    }
+}
+
+impl<V: Validator + ?Sized> fmt::Debug for LocalSymbol<V> {
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+        write!(fmt, "i{:?}", &self.0.buf[..])
+    }
+}
+
+impl<V: Validator + ?Sized> fmt::Display for LocalSymbol<V> {
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+        fmt::Display::fmt(&self.0.buf[..], fmt)
+    }
+}
+
+#[cfg(feature = "serde")]
+struct LocalSymbolVisitor<V: Validator + ?Sized>(PhantomData<V>);
+
+#[cfg(feature = "serde")]
+impl<'de, V: Validator + ?Sized> Visitor<'de> for LocalSymbolVisitor<V> {
+    type Value = LocalSymbol<V>;
+
+    fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        formatter.write_str("a valid symbol")
+    }
+
+    fn visit_str<E>(self, v: &str) -> Result<Self::Value, E>
+        where E: de::Error
+    {
+        v.parse().map_err(de::Error::custom)
+    }
+}
+
+#[cfg(feature = "serde")]
+impl<'de, V: Validator + ?Sized> Deserialize<'de> for LocalSymbol<V> {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+        where D: Deserializer<'de>
+    {
+        deserializer.deserialize_str(LocalSymbolVisitor(PhantomData))
+    }
+}
+
+#[cfg(feature = "serde")]
+impl<V: Validator + ?Sized> Serialize for LocalSymbol<V> {
+    fn serialize<S: Serializer>(&self, serializer: S)
+        -> Result<S::Ok, S::Error>
+    {
+        serializer.serialize_str(&self.0.buf)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::io;
+    use std::thread;
+    use {Validator, Symbol, LocalSymbol};
+    use validators::CaseInsensitive;
+
+    struct AnyString;
+    type Local = LocalSymbol<AnyString>;
+
+    impl Validator for AnyString {
+        // Use an error from standard library to make example shorter
+        type Err = ::std::string::ParseError;
+        fn validate_symbol(_: &str) -> Result<(), Self::Err> {
+            Ok(())
+        }
+    }
+
+    struct AlphaNumString;
+
+    impl Validator for AlphaNumString {
+        type Err = io::Error;
+        fn validate_symbol(s: &str) -> Result<(), Self::Err> {
+            if s.chars().any(|c| !c.is_alphanumeric()) {
+                return Err(io::Error::new(io::ErrorKind::InvalidData,
+                    "Character is not alphanumeric"));
+            }
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn intern() {
+        let a = Local::from("local_a");
+        let b: Local = "local_a".parse().unwrap();
+        assert_eq!(a, b);
+        assert_eq!(a.as_ptr(), b.as_ptr());
+        assert_eq!(a, "local_a");
+        assert_eq!(format!("{:?} {}", a, a), "i\"local_a\" local_a");
+        assert_eq!(Local::get("local_a"), Some(a.clone()));
+        assert!(Local::from("local_0") < a);
+        drop((a, b));
+        assert_eq!(Local::get("local_a"), None);
+        assert!("local-1".parse::<LocalSymbol<AlphaNumString>>().is_err());
+    }
+
+    #[test]
+    fn per_thread() {
+        let a = Local::from("local_thread");
+        let other = thread::spawn(|| {
+            let b = Local::from("local_thread");
+            (b.as_ptr() as usize, b.to_symbol())
+        }).join().unwrap();
+        assert_ne!(a.as_ptr() as usize, other.0);
+        assert_eq!(a.to_symbol(), other.1);
+        assert_eq!(other.1, Symbol::<AnyString>::from("local_thread"));
+    }
+
+    #[test]
+    fn folded() {
+        type Ci = LocalSymbol<CaseInsensitive<AnyString>>;
+        let a = Ci::from("Local_Ci");
+        let b = Ci::from("LOCAL_CI");
+        assert_eq!(a, b);
+        assert_eq!(&b[..], "Local_Ci");
+        assert_eq!(a, "local_ci");
+        assert_eq!(Ci::get("local_CI"), Some(a.clone()));
+        assert_eq!(&a.to_symbol()[..], "Local_Ci");
+    }
+
+    #[test]
+    #[cfg(feature = "serde")]
+    fn serde() {
+        use serde_json;
+        let a = Local::from("local_serde");
+        let json = serde_json::to_string(&a).unwrap();
+        assert_eq!(json, r#""local_serde""#);
+        let b: Local = serde_json::from_str(&json).unwrap();
+        assert_eq!(a, b);
+        assert!(serde_json::from_str::<LocalSymbol<AlphaNumString>>(
+            r#""a-b""#).is_err());
+    }
+}
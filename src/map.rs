@@ -0,0 +1,342 @@
+use core::fmt;
+use core::iter::FromIterator;
+use alloc::sync::Arc;
+
+use {Validator, Symbol};
+use compat::prelude::*;
+
+
+/// A map keyed by symbols, stored as a vector indexed by `Symbol::id`
+///
+/// Much faster than `HashMap<Symbol<V>, T>` as no hashing is involved, but
+/// the memory used is proportional to the largest id rather than to the
+/// number of entries. The map holds its keys, so their ids can't be reused
+/// by other values.
+///
+/// Keys are compared by identity: only symbols of the pool of `V` can be
+/// used (not the ones from an `Interner`), and a symbol evicted from the
+/// pool or kept across `Symbol::clear` is a different key than the same
+/// value interned again.
+///
+/// ```
+/// # use string_intern::{Validator, Symbol, SymbolMap};
+/// # struct Tag;
+/// # impl Validator for Tag {
+/// #     type Err = ::std::string::ParseError;
+/// #     fn validate_symbol(_: &str) -> Result<(), Self::Err> { Ok(()) }
+/// # }
+/// let a = Symbol::<Tag>::from("a");
+/// let mut map = SymbolMap::new();
+/// map.insert(a.clone(), 1);
+/// assert_eq!(map.get(&a), Some(&1));
+/// assert_eq!(map.get(&Symbol::from("b")), None);
+/// ```
+pub struct SymbolMap<V: Validator + ?Sized, T> {
+    slots: Vec<Option<(Symbol<V>, T)>>,
+    len: usize,
+}
+
+/// A set of symbols, stored as a vector indexed by `Symbol::id`
+///
+/// Same as `SymbolMap` with no values: only symbols of the pool of `V` can
+/// be added, and the set holds its symbols, so their ids can't be reused by
+/// other values while they are in the set.
+pub struct SymbolSet<V: Validator + ?Sized> {
+    map: SymbolMap<V, ()>,
+}
+
+impl<V: Validator + ?Sized, T> SymbolMap<V, T> {
+    /// Create an empty map
+    pub fn new() -> SymbolMap<V, T> {
+        SymbolMap { slots: Vec::new(), len: 0 }
+    }
+
+    /// Insert a value, returns the previous value for the key
+    ///
+    /// # Panics
+    ///
+    /// When the symbol comes from an `Interner`.
+    pub fn insert(&mut self, key: Symbol<V>, value: T) -> Option<T> {
+        assert!(key.in_shared_pool(),
+            "symbols from an Interner can't be used in a SymbolMap");
+        let idx = key.id() as usize;
+        if idx >= self.slots.len() {
+            self.slots.resize_with(idx + 1, || None);
+        }
+        // the slot can't belong to another value, as the key holds the id
+        match self.slots[idx].replace((key, value)) {
+            Some((_, old)) => Some(old),
+            None => {
+                self.len += 1;
+                None
+            }
+        }
+    }
+
+    fn slot(&self, key: &Symbol<V>) -> Option<&(Symbol<V>, T)> {
+        self.slots.get(key.id() as usize)?.as_ref()
+            .filter(|&(k, _)| Arc::ptr_eq(&k.0, &key.0))
+    }
+
+    /// Returns the value for the key
+    pub fn get(&self, key: &Symbol<V>) -> Option<&T> {
+        self.slot(key).map(|(_, v)| v)
+    }
+
+    /// Returns a mutable reference to the value for the key
+    pub fn get_mut(&mut self, key: &Symbol<V>) -> Option<&mut T> {
+        self.slots.get_mut(key.id() as usize)?.as_mut()
+            .filter(|(k, _)| Arc::ptr_eq(&k.0, &key.0))
+            .map(|(_, v)| v)
+    }
+
+    /// Returns `true` if the map contains the key
+    pub fn contains_key(&self, key: &Symbol<V>) -> bool {
+        self.slot(key).is_some()
+    }
+
+    /// Removes the key, returns its value
+    pub fn remove(&mut self, key: &Symbol<V>) -> Option<T> {
+        self.slot(key)?;
+        let (_, value) = self.slots[key.id() as usize].take()?;
+        self.len -= 1;
+        Some(value)
+    }
+
+    /// Returns the number of entries
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    /// Returns `true` if the map has no entries
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// Removes all entries
+    pub fn clear(&mut self) {
+        self.slots.clear();
+        self.len = 0;
+    }
+
+    /// Iterates over the entries in the order of ids
+    pub fn iter(&self) -> impl Iterator<Item=(&Symbol<V>, &T)> {
+        self.slots.iter().filter_map(|s| s.as_ref()).map(|(k, v)| (k, v))
+    }
+}
+
+impl<V: Validator + ?Sized> SymbolSet<V> {
+    /// Create an empty set
+    pub fn new() -> SymbolSet<V> {
+        SymbolSet { map: SymbolMap::new() }
+    }
+
+    /// Adds a symbol, returns `true` if it wasn't in the set
+    ///
+    /// # Panics
+    ///
+    /// When the symbol comes from an `Interner`.
+    pub fn insert(&mut self, sym: Symbol<V>) -> bool {
+        assert!(sym.in_shared_pool(),
+            "symbols from an Interner can't be used in a SymbolSet");
+        self.map.insert(sym, ()).is_none()
+    }
+
+    /// Returns `true` if the symbol is in the set
+    pub fn contains(&self, sym: &Symbol<V>) -> bool {
+        self.map.contains_key(sym)
+    }
+
+    /// Removes a symbol, returns `true` if it was in the set
+    pub fn remove(&mut self, sym: &Symbol<V>) -> bool {
+        self.map.remove(sym).is_some()
+    }
+
+    /// Returns the number of symbols in the set
+    pub fn len(&self) -> usize {
+        self.map.len()
+    }
+
+    /// Returns `true` if the set is empty
+    pub fn is_empty(&self) -> bool {
+        self.map.is_empty()
+    }
+
+    /// Removes all symbols
+    pub fn clear(&mut self) {
+        self.map.clear();
+    }
+
+    /// Iterates over the symbols in the order of ids
+    pub fn iter(&self) -> impl Iterator<Item=&Symbol<V>> {
+        self.map.iter().map(|(k, _)| k)
+    }
+}
+
+impl<V: Validator + ?Sized, T> Default for SymbolMap<V, T> {
+    fn default() -> SymbolMap<V, T> {
+        SymbolMap::new()
+    }
+}
+
+impl<V: Validator + ?Sized> Default for SymbolSet<V> {
+    fn default() -> SymbolSet<V> {
+        SymbolSet::new()
+    }
+}
+
+impl<V: Validator + ?Sized, T: Clone> Clone for SymbolMap<V, T> {
+    fn clone(&self) -> SymbolMap<V, T> {
+        SymbolMap { slots: self.slots.clone(), len: self.len }
+    }
+}
+
+impl<V: Validator + ?Sized> Clone for SymbolSet<V> {
+    fn clone(&self) -> SymbolSet<V> {
+        SymbolSet { map: self.map.clone() }
+    }
+}
+
+impl<V: Validator + ?Sized, T: fmt::Debug> fmt::Debug for SymbolMap<V, T> {
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+        fmt.debug_map().entries(self.iter()).finish()
+    }
+}
+
+impl<V: Validator + ?Sized> fmt::Debug for SymbolSet<V> {
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+        fmt.debug_set().entries(self.iter()).finish()
+    }
+}
+
+impl<V: Validator + ?Sized, T> FromIterator<(Symbol<V>, T)>
+    for SymbolMap<V, T>
+{
+    fn from_iter<I>(iter: I) -> SymbolMap<V, T>
+        where I: IntoIterator<Item=(Symbol<V>, T)>
+    {
+        let mut map = SymbolMap::new();
+        for (k, v) in iter {
+            map.insert(k, v);
+        }
+        map
+    }
+}
+
+impl<V: Validator + ?Sized> FromIterator<Symbol<V>> for SymbolSet<V> {
+    fn from_iter<I>(iter: I) -> SymbolSet<V>
+        where I: IntoIterator<Item=Symbol<V>>
+    {
+        let mut set = SymbolSet::new();
+        for sym in iter {
+            set.insert(sym);
+        }
+        set
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use {Validator, Symbol, Interner, SymbolMap, SymbolSet};
+
+    struct AnyString;
+    type Atom = Symbol<AnyString>;
+
+    impl Validator for AnyString {
+        // Use an error from standard library to make example shorter
+        type Err = ::std::string::ParseError;
+        fn validate_symbol(_: &str) -> Result<(), Self::Err> {
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn map() {
+        let a = Atom::from("map_a");
+        let b = Atom::from("map_b");
+        let mut map = SymbolMap::new();
+        assert_eq!(map.insert(a.clone(), 1), None);
+        assert_eq!(map.insert(a.clone(), 2), Some(1));
+        assert_eq!(map.get(&a), Some(&2));
+        assert_eq!(map.get(&b), None);
+        *map.get_mut(&a).unwrap() += 1;
+        map.insert(b.clone(), 10);
+        assert_eq!(map.len(), 2);
+        assert_eq!(map.remove(&a), Some(3));
+        assert_eq!(map.remove(&a), None);
+        assert_eq!(map.iter().collect::<Vec<_>>(), vec![(&b, &10)]);
+        assert!(!map.is_empty());
+    }
+
+    #[test]
+    fn map_holds_keys() {
+        let map = vec![(Atom::from("map_holds"), 1)].into_iter()
+            .collect::<SymbolMap<_, _>>();
+        let key = Atom::get("map_holds").unwrap();
+        assert_eq!(map.get(&key), Some(&1));
+    }
+
+    #[test]
+    #[should_panic(expected="symbols from an Interner")]
+    fn map_rejects_interner() {
+        let pool = Interner::<AnyString>::new();
+        SymbolMap::new().insert(pool.intern("map_interner").unwrap(), 1);
+    }
+
+    #[test]
+    fn map_other_pool() {
+        let pool = Interner::<AnyString>::new();
+        let a = Atom::from("map_other");
+        let b = pool.intern("map_other").unwrap();
+        let map = vec![(a, 1)].into_iter().collect::<SymbolMap<_, _>>();
+        assert!(!map.contains_key(&b));
+    }
+
+    #[test]
+    fn set() {
+        let a = Atom::from("set_a");
+        let b = Atom::from("set_b");
+        let mut set = SymbolSet::new();
+        assert!(set.insert(a.clone()));
+        assert!(!set.insert(a.clone()));
+        assert!(set.contains(&a));
+        assert!(!set.contains(&b));
+        assert_eq!(set.len(), 1);
+        assert!(!set.remove(&b));
+        assert!(set.remove(&a));
+        assert!(!set.contains(&a));
+        assert!(set.is_empty());
+        let set = vec![a.clone(), b.clone()].into_iter()
+            .collect::<SymbolSet<_>>();
+        let mut items = set.iter().cloned().collect::<Vec<_>>();
+        items.sort();
+        assert_eq!(items, vec![a, b]);
+    }
+
+    #[test]
+    fn set_holds_symbols() {
+        let mut set = SymbolSet::new();
+        set.insert(Atom::from("set_held"));
+        let other = Atom::from("set_not_held");
+        assert!(!set.contains(&other));
+        assert!(set.contains(&Atom::from("set_held")));
+        assert_eq!(set.iter().collect::<Vec<_>>(), vec!["set_held"]);
+    }
+
+    #[test]
+    #[should_panic(expected="symbols from an Interner")]
+    fn set_rejects_interner() {
+        let pool = Interner::<AnyString>::new();
+        SymbolSet::new().insert(pool.intern("set_interner").unwrap());
+    }
+
+    #[test]
+    fn set_other_pool() {
+        let pool = Interner::<AnyString>::new();
+        let a = Atom::from("set_other");
+        let b = pool.intern("set_other").unwrap();
+        let set = vec![a.clone()].into_iter().collect::<SymbolSet<_>>();
+        assert!(set.contains(&a));
+        assert!(!set.contains(&b));
+    }
+}
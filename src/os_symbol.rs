@@ -0,0 +1,209 @@
+use std::borrow::Borrow;
+use std::cmp::Ordering;
+use std::convert::Infallible;
+use std::ffi::{OsStr, OsString};
+use std::fmt;
+use std::hash::{Hash, Hasher};
+use std::ops::Deref;
+use std::path::{Path, PathBuf};
+
+use bytes_symbol::{BytesSymbol, BytesValidator};
+
+
+struct OsBytes;
+
+impl BytesValidator for OsBytes {
+    type Err = Infallible;
+    fn validate_symbol(_: &[u8]) -> Result<(), Self::Err> {
+        Ok(())
+    }
+}
+
+/// Interned `OsStr`, useful for file paths
+///
+/// Values are stored as is, without conversion to UTF-8, so paths which
+/// `to_string_lossy` would corrupt are kept intact.
+///
+/// ```
+/// # use std::path::Path;
+/// # use string_intern::OsSymbol;
+/// let a = OsSymbol::new("/etc/hosts");
+/// let b = OsSymbol::from(Path::new("/etc").join("hosts"));
+/// assert_eq!(a, b);
+/// assert_eq!(a.as_path(), Path::new("/etc/hosts"));
+/// ```
+#[derive(Clone, PartialEq, Eq)]
+pub struct OsSymbol(BytesSymbol<OsBytes>);
+
+impl OsSymbol {
+    /// Intern the value
+    pub fn new<S: AsRef<OsStr> + ?Sized>(s: &S) -> OsSymbol {
+        let bytes = s.as_ref().as_encoded_bytes();
+        match BytesSymbol::intern(bytes) {
+            Ok(sym) => OsSymbol(sym),
+            Err(e) => match e {},
+        }
+    }
+
+    /// Returns the symbol if the value is interned already
+    pub fn get<S: AsRef<OsStr> + ?Sized>(s: &S) -> Option<OsSymbol> {
+        BytesSymbol::get(s.as_ref().as_encoded_bytes()).map(OsSymbol)
+    }
+
+    /// Returns the value as an `OsStr`
+    pub fn as_os_str(&self) -> &OsStr {
+        // safe because the bytes were taken from an `OsStr`
+        unsafe { OsStr::from_encoded_bytes_unchecked(&self.0) }
+    }
+
+    /// Returns the value as a `Path`
+    pub fn as_path(&self) -> &Path {
+        Path::new(self.as_os_str())
+    }
+
+    /// Returns the value as a string if it's valid unicode
+    pub fn to_str(&self) -> Option<&str> {
+        self.as_os_str().to_str()
+    }
+}
+
+impl<'a> From<&'a OsStr> for OsSymbol {
+    fn from(s: &'a OsStr) -> OsSymbol {
+        OsSymbol::new(s)
+    }
+}
+
+impl<'a> From<&'a Path> for OsSymbol {
+    fn from(s: &'a Path) -> OsSymbol {
+        OsSymbol::new(s)
+    }
+}
+
+impl<'a> From<&'a str> for OsSymbol {
+    fn from(s: &'a str) -> OsSymbol {
+        OsSymbol::new(s)
+    }
+}
+
+impl From<OsString> for OsSymbol {
+    fn from(s: OsString) -> OsSymbol {
+        OsSymbol::new(&s)
+    }
+}
+
+impl From<PathBuf> for OsSymbol {
+    fn from(s: PathBuf) -> OsSymbol {
+        OsSymbol::new(&s)
+    }
+}
+
+impl From<OsSymbol> for PathBuf {
+    fn from(s: OsSymbol) -> PathBuf {
+        s.as_path().to_path_buf()
+    }
+}
+
+impl From<OsSymbol> for OsString {
+    fn from(s: OsSymbol) -> OsString {
+        s.as_os_str().to_os_string()
+    }
+}
+
+impl PartialEq<OsStr> for OsSymbol {
+    fn eq(&self, other: &OsStr) -> bool {
+        self.as_os_str() == other
+    }
+}
+
+impl PartialEq<Path> for OsSymbol {
+    fn eq(&self, other: &Path) -> bool {
+        self.as_path() == other
+    }
+}
+
+// Must match the hash of `OsStr` for `Borrow<OsStr>`
+impl Hash for OsSymbol {
+    fn hash<H: Hasher>(&self, hasher: &mut H) {
+        self.as_os_str().hash(hasher)
+    }
+}
+
+impl PartialOrd for OsSymbol {
+    fn partial_cmp(&self, other: &OsSymbol) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for OsSymbol {
+    fn cmp(&self, other: &OsSymbol) -> Ordering {
+        self.as_os_str().cmp(other.as_os_str())
+    }
+}
+
+impl Deref for OsSymbol {
+    type Target = OsStr;
+    fn deref(&self) -> &OsStr {
+        self.as_os_str()
+    }
+}
+
+impl AsRef<OsStr> for OsSymbol {
+    fn as_ref(&self) -> &OsStr {
+        self.as_os_str()
+    }
+}
+
+impl AsRef<Path> for OsSymbol {
+    fn as_ref(&self) -> &Path {
+        self.as_path()
+    }
+}
+
+impl Borrow<OsStr> for OsSymbol {
+    fn borrow(&self) -> &OsStr {
+        self.as_os_str()
+    }
+}
+
+impl fmt::Debug for OsSymbol {
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+        fmt::Debug::fmt(self.as_os_str(), fmt)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::collections::HashMap;
+    use std::ffi::OsStr;
+    use std::path::{Path, PathBuf};
+    use super::OsSymbol;
+
+    #[test]
+    fn intern() {
+        let a = OsSymbol::new("os_sym/a.txt");
+        let b = OsSymbol::from(PathBuf::from("os_sym").join("a.txt"));
+        assert_eq!(a, b);
+        assert_eq!(a.as_encoded_bytes().as_ptr(),
+                   b.as_encoded_bytes().as_ptr());
+        assert_eq!(a.as_path(), Path::new("os_sym/a.txt"));
+        assert_eq!(a.to_str(), Some("os_sym/a.txt"));
+        assert_eq!(format!("{:?}", a), "\"os_sym/a.txt\"");
+        assert_eq!(OsSymbol::get("os_sym/a.txt"), Some(a.clone()));
+        let map = vec![(a.clone(), 1)].into_iter()
+            .collect::<HashMap<_, _>>();
+        assert_eq!(map.get(OsStr::new("os_sym/a.txt")), Some(&1));
+        drop((a, b, map));
+        assert_eq!(OsSymbol::get("os_sym/a.txt"), None);
+    }
+
+    #[test]
+    #[cfg(unix)]
+    fn non_unicode() {
+        use std::os::unix::ffi::OsStrExt;
+        let raw = OsStr::from_bytes(b"os_sym/\xff.txt");
+        let a = OsSymbol::new(raw);
+        assert_eq!(a.to_str(), None);
+        assert_eq!(a.as_bytes(), b"os_sym/\xff.txt");
+        assert_eq!(&*a, raw);
+    }
+}
@@ -0,0 +1,116 @@
+use std::marker::PhantomData;
+use std::sync::Arc;
+
+use rayon::prelude::*;
+
+use {Validator, Symbol};
+use base_type::{intern_locked, Value};
+use pool::pool;
+use validator::{validate, fold};
+use scope;
+
+
+impl<V: Validator + ?Sized> Symbol<V> {
+    /// Intern all the values using the rayon thread pool
+    ///
+    /// Same as `intern_all`, but values are validated and hashed in
+    /// parallel, then each shard of the pool is filled by its own thread
+    /// under its own lock. Without the `sharded` feature there is a single
+    /// shard, so only validation and hashing run in parallel.
+    ///
+    /// Symbols are returned in the order of the input.
+    pub fn par_intern_all<S>(input: &[S]) -> Result<Vec<Symbol<V>>, V::Err>
+        where S: AsRef<str> + Sync, V::Err: Send,
+    {
+        let pool = pool::<V>();
+        let values = input.par_iter()
+            .map(|item| {
+                let value = validate::<V>(item.as_ref())?;
+                let key = fold::<V>(&value);
+                let shard = pool.index(key.as_deref().unwrap_or(&value));
+                Ok((shard, value, key))
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+        let mut shards = (0..pool.shard_count())
+            .map(|_| Vec::new())
+            .collect::<Vec<_>>();
+        for (idx, (shard, value, key)) in values.into_iter().enumerate() {
+            shards[shard].push((idx, value, key));
+        }
+        let owner = pool.shared_ref();
+        let interned = shards.into_par_iter()
+            .enumerate()
+            .filter(|(_, items)| !items.is_empty())
+            .map(|(shard, items)| {
+                let mut atoms = pool.write_shard(shard);
+                items.into_iter()
+                    .map(|(idx, value, key)| {
+                        let key = key.as_deref();
+                        (idx, intern_locked(&mut atoms, value, key, &owner).0)
+                    })
+                    .collect::<Vec<_>>()
+            })
+            .collect::<Vec<_>>();
+        let mut result: Vec<Option<Arc<Value>>> = vec![None; input.len()];
+        for (idx, value) in interned.into_iter().flatten() {
+            // worker threads don't have the scope of the caller
+            scope::pin(&value);
+            result[idx] = Some(value);
+        }
+        Ok(result.into_iter()
+            .map(|value| Symbol(value.expect("all values interned"),
+                                PhantomData))
+            .collect())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::io;
+    use {Validator, Symbol};
+
+    struct AnyString;
+    type Atom = Symbol<AnyString>;
+
+    impl Validator for AnyString {
+        // Use an error from standard library to make example shorter
+        type Err = ::std::string::ParseError;
+        fn validate_symbol(_: &str) -> Result<(), Self::Err> {
+            Ok(())
+        }
+    }
+
+    struct AlphaNumString;
+
+    impl Validator for AlphaNumString {
+        type Err = io::Error;
+        fn validate_symbol(s: &str) -> Result<(), Self::Err> {
+            if s.chars().any(|c| !c.is_alphanumeric()) {
+                return Err(io::Error::new(io::ErrorKind::InvalidData,
+                    "Character is not alphanumeric"));
+            }
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn par_intern_all() {
+        let words = (0..1000).map(|i| format!("par_{}", i % 300))
+            .collect::<Vec<_>>();
+        let symbols = Atom::par_intern_all(&words).unwrap();
+        assert_eq!(symbols.len(), 1000);
+        for (word, sym) in words.iter().zip(&symbols) {
+            assert_eq!(&sym[..], &word[..]);
+        }
+        assert_eq!(symbols[1].as_ptr(), symbols[301].as_ptr());
+        assert_eq!(symbols[1].as_ptr(), Atom::from("par_1").as_ptr());
+        assert!(Atom::par_intern_all::<&str>(&[]).unwrap().is_empty());
+    }
+
+    #[test]
+    fn par_intern_all_invalid() {
+        type AlphaNum = Symbol<AlphaNumString>;
+        assert!(AlphaNum::par_intern_all(&["par1", "par-2"]).is_err());
+        assert_eq!(AlphaNum::get("par1"), None);
+    }
+}
@@ -0,0 +1,839 @@
+//! Storage of interned values
+//!
+//! Each validator type has its own pool, so values of unrelated types don't
+//! contend on the same lock. Pools are created on first use and are never
+//! freed. `Interner` owns a pool which is freed along with the last symbol.
+//!
+//! With the `sharded` feature each pool is split into a number of shards by
+//! hash of the value, each under its own lock, so interning and dropping of
+//! different values scales with the number of threads.
+//!
+//! Pools of `Intern` and `BytesSymbol` values use the same implementation,
+//! they only differ in the type of the stored values, see `Item`.
+//!
+//! A pool may be bounded by `PoolLimits`. Each entry records when it was
+//! last used, and when a shard exceeds its share of the limits, dead
+//! entries are swept, and then least recently used entries are evicted. An
+//! evicted entry may belong to a live value, so the epoch of the pool is
+//! incremented, which makes symbols compare by value rather than by pointer
+//! (see `Value::epoch`).
+use core::any::{Any, TypeId};
+use core::borrow::Borrow;
+#[cfg(feature = "std")] use core::cell::RefCell;
+use core::convert::TryFrom;
+#[cfg(all(feature = "std", not(feature = "ahash")))]
+use std::collections::hash_map::RandomState;
+use core::hash::{BuildHasher, Hash};
+use core::ops::Deref;
+use core::ptr;
+use alloc::sync::{Arc, Weak};
+use core::sync::atomic::{AtomicBool, AtomicU32, AtomicUsize, Ordering};
+
+use Validator;
+use compat::prelude::*;
+use compat::{HashMap, Mutex, TryReserveError};
+use compat::{RwLock, RwLockReadGuard, RwLockWriteGuard};
+use base_type::Value;
+use scope;
+#[cfg(feature = "arc-swap")] use snapshot::Snapshot;
+
+// SipHash by default, which is resistant to collision attacks
+#[cfg(all(feature = "std", not(feature = "ahash")))]
+pub(crate) type BuildHash = RandomState;
+// There is no source of random seeds without `std`
+#[cfg(all(not(feature = "std"), not(feature = "ahash")))]
+pub(crate) type BuildHash = ::hashbrown::DefaultHashBuilder;
+#[cfg(feature = "ahash")]
+pub(crate) type BuildHash = ::ahash::RandomState;
+
+pub(crate) type Atoms<N = Value> =
+    HashMap<<N as Item>::Key, Entry<N>, BuildHash>;
+
+/// Type of values stored in a pool
+///
+/// May be unsized, like `Value` which holds the string itself.
+pub(crate) trait Item: Send + Sync + 'static {
+    /// Key of the entry, it outlives the value if the entry is left dead
+    type Key: Borrow<Self::Query> + Hash + Eq + Clone + Send + Sync;
+    /// Type the entries are looked up by
+    type Query: Hash + Eq + ?Sized;
+    /// Returns the key of the entry of the value
+    fn entry_key(value: &Arc<Self>) -> Self::Key;
+    /// Size of the key counted towards `PoolLimits::max_bytes`
+    fn size(key: &Self::Query) -> usize;
+}
+
+/// A value in the pool, derefs to the weak reference to it
+pub(crate) struct Entry<N: Item + ?Sized = Value> {
+    weak: Weak<N>,
+    // Tick of `CLOCK` when the entry was last used, only updated in pools
+    // with limits
+    used: AtomicUsize,
+}
+
+// Ticks on every value inserted into a pool with limits
+static CLOCK: AtomicUsize = AtomicUsize::new(0);
+
+#[cfg(not(feature = "sharded"))]
+const SHARDS: usize = 1;
+#[cfg(feature = "sharded")]
+const SHARDS: usize = 16;
+
+pub(crate) struct Pool<N: Item + ?Sized = Value> {
+    shards: Vec<Shard<N>>,
+    // Picks a shard, the same hasher can't be used for the tables
+    // themselves, as it would put all keys of a shard in few buckets
+    hasher: BuildHash,
+    // Number of times the pool was cleared or live entries were evicted
+    epoch: AtomicU32,
+    // Limits for each shard, `usize::MAX` if not limited
+    max_entries: AtomicUsize,
+    max_bytes: AtomicUsize,
+    // Whether dropping the last symbol leaves its entry in the pool
+    deferred_drop: AtomicBool,
+    // Whether `thread_cache` is used for this pool
+    thread_cache: AtomicBool,
+    #[cfg(feature = "arc-swap")]
+    pub snapshot: Snapshot<N>,
+}
+
+struct Shard<N: Item + ?Sized> {
+    atoms: RwLock<Atoms<N>>,
+    // Number of values dropped without removing their entries, see
+    // `DropPolicy::Deferred`
+    dead: AtomicUsize,
+    // Total length of keys, only changed under the write lock
+    bytes: AtomicUsize,
+    index: usize,
+    // Locked after `atoms` if both are needed
+    ids: Mutex<Ids<N>>,
+}
+
+/// Values of a shard indexed by id, see `Symbol::id`
+///
+/// Id is `index * SHARDS + shard`, so ids are dense within the whole pool
+/// if values are distributed evenly between shards.
+struct Ids<N: Item + ?Sized> {
+    // `None` for free ids, and for the id of a value being created
+    slots: Vec<Option<Weak<N>>>,
+    // Copies made by `Symbol::as_string` (and of the keys which differ from
+    // the values), by index, dropped with the id. Boxed, as references to
+    // them must outlive growing of the table
+    #[allow(clippy::box_collection)]
+    strings: HashMap<(usize, bool), Box<String>>,
+    free: Vec<usize>,
+}
+
+/// A shard locked for writing
+///
+/// Mutating methods keep track of the size of the shard, so there is no
+/// mutable access to the table itself.
+pub(crate) struct ShardGuard<'a, N: Item + ?Sized = Value> {
+    atoms: RwLockWriteGuard<'a, Atoms<N>>,
+    shard: &'a Shard<N>,
+    pool: &'a Pool<N>,
+}
+
+/// Limits of the size of a pool
+///
+/// When a new value is interned into a full pool, entries of dropped values
+/// are removed, and if that's not enough, least recently used values are
+/// evicted from the pool. Evicted values stay valid and compare equal to
+/// the same value interned again, but they no longer share the buffer with
+/// it. Pinned values (see `Symbol::pin`) are never evicted.
+///
+/// Limits are split evenly between shards (see `sharded` feature), so the
+/// pool may be evicted a little before it reaches the limit.
+#[derive(Debug, Clone, Default)]
+pub struct PoolLimits {
+    max_entries: Option<usize>,
+    max_bytes: Option<usize>,
+}
+
+/// What to do with the entry in the pool when the last symbol is dropped
+///
+/// Set for each pool by `Symbol::set_drop_policy`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DropPolicy {
+    /// Remove the entry right away, this is the default
+    Immediate,
+    /// Keep the entry until it's swept or the same value is interned again
+    ///
+    /// Dropping doesn't take the lock, but the string buffer stays in
+    /// memory until the entry is removed. Dead entries are swept when
+    /// enough of them accumulate in the pool, or explicitly by
+    /// `Symbol::prune` and `purge_dead`.
+    Deferred,
+}
+
+/// Locks the pool for removing a dropped value
+///
+/// `Value` refers to its pool by trait object rather than by the lock
+/// itself, so it doesn't look like a type with interior mutability, and
+/// clippy doesn't complain about symbols used as keys of a `HashMap`.
+pub(crate) trait Lock<N: Item + ?Sized = Value>: Send + Sync {
+    fn lock(&self, key: &N::Query) -> ShardGuard<'_, N>;
+    /// Records that `key` is dropped, but its entry is left in place
+    fn dropped(&self, key: &N::Query);
+    fn epoch(&self) -> u32;
+    /// Makes the id of a dropped value available for new values
+    fn release_id(&self, id: u32);
+    /// Returns the copy of the value with the id, made on the first call
+    ///
+    /// If `key` is true, it's the key of the value, which is copied
+    /// separately.
+    fn string(&self, id: u32, key: bool, value: &str) -> *const String;
+    /// Returns `true` if the pool uses `DropPolicy::Deferred`
+    fn deferred_drop(&self) -> bool;
+}
+
+/// All shards of the pool locked for writing
+pub(crate) struct WriteAll<'a, N: Item + ?Sized = Value> {
+    pool: &'a Pool<N>,
+    guards: Vec<ShardGuard<'a, N>>,
+}
+
+/// Statistics of a pool, as returned by `Symbol::stats`
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct PoolStats {
+    /// Number of values referred to by some symbol
+    pub live: usize,
+    /// Number of entries of dropped values which are not removed yet
+    ///
+    /// Only non-zero for a short time, unless `DropPolicy::Deferred` is
+    /// used.
+    pub dead: usize,
+    /// Total length of strings held by the pool, including dead entries
+    pub bytes: usize,
+}
+
+/// The pool of the value
+pub(crate) enum PoolRef<N: Item + ?Sized = Value> {
+    /// Pool of the validator type
+    Shared(&'static dyn Lock<N>),
+    /// Pool of an `Interner`
+    Local(Arc<dyn Lock<N>>),
+}
+
+type AnyPool = &'static (dyn Any + Send + Sync);
+
+lazy_static! {
+    static ref POOLS: RwLock<HashMap<TypeId, &'static Pool>> =
+        RwLock::new(HashMap::new());
+    // Pools of other items, see `item_pool`
+    static ref ITEM_POOLS: RwLock<HashMap<TypeId, AnyPool>> =
+        RwLock::new(HashMap::new());
+}
+
+#[cfg(feature = "std")]
+thread_local! {
+    // Avoids locking `POOLS` on every lookup
+    static CACHE: RefCell<HashMap<TypeId, &'static Pool>> =
+        RefCell::new(HashMap::new());
+}
+
+impl<N: Item + ?Sized> Pool<N> {
+    pub fn new() -> Pool<N> {
+        Pool {
+            shards: (0..SHARDS).map(Shard::new).collect(),
+            hasher: BuildHash::default(),
+            epoch: AtomicU32::new(0),
+            max_entries: AtomicUsize::new(usize::MAX),
+            max_bytes: AtomicUsize::new(usize::MAX),
+            deferred_drop: AtomicBool::new(false),
+            thread_cache: AtomicBool::new(true),
+            #[cfg(feature = "arc-swap")]
+            snapshot: Snapshot::new(),
+        }
+    }
+    /// Returns the index of the shard containing `key`
+    pub fn index(&self, key: &N::Query) -> usize {
+        if SHARDS == 1 {
+            return 0;
+        }
+        (BuildHasher::hash_one(&self.hasher, key) % SHARDS as u64) as usize
+    }
+    /// Locks the shard containing `key` for reading
+    pub fn read(&self, key: &N::Query) -> RwLockReadGuard<'_, Atoms<N>> {
+        self.shards[self.index(key)].atoms.read().expect("atoms locked")
+    }
+    /// Locks the shard containing `key` for writing
+    pub fn write(&self, key: &N::Query) -> ShardGuard<'_, N> {
+        self.shards[self.index(key)].write(self)
+    }
+    /// Returns the live value of `key`, marks it as recently used
+    pub fn lookup(&self, key: &N::Query) -> Option<Arc<N>> {
+        let atoms = self.read(key);
+        let entry = atoms.get(key)?;
+        // We may get a race condition where the value has no strong
+        // references any more, but the entry is still not removed because
+        // destructor is waiting for a lock in another thread.
+        //
+        // That's fine, the caller gets a write lock and rechecks it later.
+        let value = entry.upgrade()?;
+        self.touch(entry);
+        Some(value)
+    }
+    /// Returns the live value of `key`, or inserts the one made by `make`
+    ///
+    /// `make` gets the id of the new value.
+    pub fn get_or_insert<F>(&self, key: &N::Query, make: F) -> Arc<N>
+        where F: FnOnce(u32) -> Arc<N>,
+    {
+        if let Some(value) = self.lookup(key) {
+            return value;
+        }
+        let mut atoms = self.write(key);
+        match atoms.find(key) {
+            Some(value) => value,
+            None => atoms.insert_with(N::size(key), make),
+        }
+    }
+    /// Locks the shard with the index returned by `index` for writing
+    #[cfg(feature = "rayon")]
+    pub fn write_shard(&self, index: usize) -> ShardGuard<'_, N> {
+        self.shards[index].write(self)
+    }
+    /// Returns the number of shards
+    #[cfg(feature = "rayon")]
+    pub fn shard_count(&self) -> usize {
+        self.shards.len()
+    }
+    /// Locks every shard for writing, to avoid locking for each value
+    pub fn write_all(&self) -> WriteAll<'_, N> {
+        WriteAll {
+            pool: self,
+            // shards are always locked in the same order, so this can't
+            // deadlock with another `write_all`
+            guards: self.shards.iter().map(|s| s.write(self)).collect(),
+        }
+    }
+    /// Locks shards for reading one by one
+    pub fn read_shards(&self)
+        -> impl Iterator<Item=RwLockReadGuard<'_, Atoms<N>>>
+    {
+        self.shards.iter().map(|s| s.atoms.read().expect("atoms locked"))
+    }
+    /// Locks shards for writing one by one
+    pub fn write_shards(&self) -> impl Iterator<Item=ShardGuard<'_, N>> {
+        self.shards.iter().map(move |s| s.write(self))
+    }
+    /// Reserves capacity for `additional` values in the whole pool
+    pub fn reserve(&self, additional: usize) {
+        // values are distributed evenly between shards
+        let per_shard = additional.div_ceil(self.shards.len());
+        for mut atoms in self.write_shards() {
+            atoms.reserve(per_shard);
+        }
+    }
+    /// Returns the number of values the pool can hold without reallocating
+    pub fn capacity(&self) -> usize {
+        self.read_shards().map(|atoms| atoms.capacity()).sum()
+    }
+    /// Removes all entries, returns the number removed
+    pub fn clear(&self) -> usize {
+        let mut all = self.write_all();
+        // all shards are locked, so no value can be inserted with the old
+        // epoch after this point
+        self.epoch.fetch_add(1, Ordering::Relaxed);
+        #[cfg(feature = "arc-swap")]
+        self.snapshot.clear();
+        all.guards.iter_mut().map(|atoms| atoms.clear()).sum()
+    }
+    /// Sets limits, they are enforced when values are interned
+    pub fn set_limits(&self, limits: &PoolLimits) {
+        let per_shard = |n: Option<usize>| {
+            n.map(|n| n.div_ceil(SHARDS)).unwrap_or(usize::MAX)
+        };
+        self.max_entries.store(per_shard(limits.max_entries),
+            Ordering::Relaxed);
+        self.max_bytes.store(per_shard(limits.max_bytes), Ordering::Relaxed);
+    }
+    pub fn set_drop_policy(&self, policy: DropPolicy) {
+        self.deferred_drop.store(policy == DropPolicy::Deferred,
+            Ordering::Relaxed);
+    }
+    pub fn drop_policy(&self) -> DropPolicy {
+        if self.deferred_drop() {
+            DropPolicy::Deferred
+        } else {
+            DropPolicy::Immediate
+        }
+    }
+    pub fn set_thread_cache(&self, enabled: bool) {
+        self.thread_cache.store(enabled, Ordering::Relaxed);
+    }
+    #[cfg(feature = "thread-cache")]
+    pub fn thread_cache(&self) -> bool {
+        self.thread_cache.load(Ordering::Relaxed)
+    }
+    fn limited(&self) -> bool {
+        self.max_entries.load(Ordering::Relaxed) != usize::MAX ||
+            self.max_bytes.load(Ordering::Relaxed) != usize::MAX
+    }
+    /// Returns the value with the id if it's alive
+    pub fn get_by_id(&self, id: u32) -> Option<Arc<N>> {
+        let (shard, index) = split_id(id);
+        let ids = self.shards.get(shard)?.ids.lock().expect("ids locked");
+        ids.slots.get(index)?.as_ref()?.upgrade()
+    }
+    /// Marks the entry as recently used, called on lookups
+    pub fn touch(&self, entry: &Entry<N>) {
+        if self.limited() {
+            entry.used.store(CLOCK.load(Ordering::Relaxed), Ordering::Relaxed);
+        }
+    }
+    /// Collects statistics, locking one shard at a time
+    pub fn stats(&self) -> PoolStats {
+        let mut stats = PoolStats::default();
+        for atoms in self.read_shards() {
+            for (key, weak) in atoms.iter() {
+                if weak.strong_count() > 0 {
+                    stats.live += 1;
+                } else {
+                    stats.dead += 1;
+                }
+                stats.bytes += N::size(key.borrow());
+            }
+        }
+        stats
+    }
+    pub fn shared_ref(&'static self) -> PoolRef<N> {
+        PoolRef::Shared(self)
+    }
+}
+
+impl<N: Item + ?Sized> Lock<N> for Pool<N> {
+    fn lock(&self, key: &N::Query) -> ShardGuard<'_, N> {
+        self.write(key)
+    }
+    fn dropped(&self, key: &N::Query) {
+        self.shards[self.index(key)].dead.fetch_add(1, Ordering::Relaxed);
+    }
+    fn epoch(&self) -> u32 {
+        self.epoch.load(Ordering::Relaxed)
+    }
+    fn release_id(&self, id: u32) {
+        let (shard, index) = split_id(id);
+        let mut ids = self.shards[shard].ids.lock().expect("ids locked");
+        ids.slots[index] = None;
+        ids.strings.remove(&(index, false));
+        ids.strings.remove(&(index, true));
+        ids.free.push(index);
+    }
+    fn string(&self, id: u32, key: bool, value: &str) -> *const String {
+        let (shard, index) = split_id(id);
+        let mut ids = self.shards[shard].ids.lock().expect("ids locked");
+        &**ids.strings.entry((index, key))
+            .or_insert_with(|| Box::new(String::from(value)))
+    }
+    fn deferred_drop(&self) -> bool {
+        self.deferred_drop.load(Ordering::Relaxed)
+    }
+}
+
+impl<N: Item + ?Sized> Shard<N> {
+    fn new(index: usize) -> Shard<N> {
+        Shard {
+            atoms: RwLock::new(Atoms::default()),
+            dead: AtomicUsize::new(0),
+            bytes: AtomicUsize::new(0),
+            index,
+            ids: Mutex::new(Ids {
+                slots: Vec::new(),
+                strings: HashMap::default(),
+                free: Vec::new(),
+            }),
+        }
+    }
+    fn write<'a>(&'a self, pool: &'a Pool<N>) -> ShardGuard<'a, N> {
+        ShardGuard {
+            atoms: self.atoms.write().expect("atoms locked"),
+            shard: self,
+            pool,
+        }
+    }
+}
+
+impl<N: Item + ?Sized> Clone for Entry<N> {
+    fn clone(&self) -> Entry<N> {
+        Entry {
+            weak: self.weak.clone(),
+            used: AtomicUsize::new(self.used.load(Ordering::Relaxed)),
+        }
+    }
+}
+
+impl<N: Item + ?Sized> Deref for Entry<N> {
+    type Target = Weak<N>;
+    fn deref(&self) -> &Weak<N> {
+        &self.weak
+    }
+}
+
+impl<'a, N: Item + ?Sized> ShardGuard<'a, N> {
+    fn add_bytes(&self, key: &N::Key) {
+        let size = N::size(key.borrow());
+        self.shard.bytes.fetch_add(size, Ordering::Relaxed);
+    }
+    fn sub_bytes(shard: &Shard<N>, key: &N::Key) {
+        shard.bytes.fetch_sub(N::size(key.borrow()), Ordering::Relaxed);
+    }
+    /// Returns the live value of `key`, removes the entry if it's dead
+    ///
+    /// Another thread might intern the value while we were waiting for the
+    /// write lock, so this is checked before allocating anything.
+    pub fn find(&mut self, key: &N::Query) -> Option<Arc<N>> {
+        let dead = match self.atoms.get(key) {
+            Some(entry) => match entry.upgrade() {
+                Some(value) => {
+                    self.touch(entry);
+                    return Some(value);
+                }
+                None => true,
+            },
+            None => false,
+        };
+        if dead {
+            // Remove the key too, so it doesn't hold the old buffer
+            self.remove(key);
+        }
+        None
+    }
+    /// Inserts the value made by `make`, the entry must be vacant
+    ///
+    /// Makes room for the key of `size` first, `make` gets the id of the
+    /// new value.
+    pub fn insert_with<F>(&mut self, size: usize, make: F) -> Arc<N>
+        where F: FnOnce(u32) -> Arc<N>,
+    {
+        self.maybe_sweep();
+        self.make_room(size);
+        let id = self.alloc_id();
+        let value = make(id);
+        let (_, index) = split_id(id);
+        self.shard.ids.lock().expect("ids locked").slots[index] =
+            Some(Arc::downgrade(&value));
+        self.insert(N::entry_key(&value), &value);
+        value
+    }
+    /// Removes the entry of a dropped value
+    ///
+    /// While we were waiting for the lock, the same value might be
+    /// interned again, so the entry is only removed if it's dead.
+    pub fn remove_dead(&mut self, key: &N::Query) {
+        let dead = self.atoms.get(key)
+            .map(|w| w.strong_count() == 0)
+            .unwrap_or(false);
+        if dead {
+            self.remove(key);
+        }
+    }
+    /// Assigns an id to the value which is being created
+    ///
+    /// The value isn't found by the id until it's stored in the slot.
+    fn alloc_id(&self) -> u32 {
+        let mut ids = self.shard.ids.lock().expect("ids locked");
+        let index = match ids.free.pop() {
+            Some(index) => index,
+            None => {
+                ids.slots.push(None);
+                ids.slots.len() - 1
+            }
+        };
+        let id = index * SHARDS + self.shard.index;
+        u32::try_from(id).expect("too many values in the pool")
+    }
+    /// Inserts a new value, the entry must be vacant
+    pub fn insert(&mut self, key: N::Key, value: &Arc<N>) {
+        let used = if self.pool.limited() {
+            CLOCK.fetch_add(1, Ordering::Relaxed)
+        } else {
+            0
+        };
+        self.add_bytes(&key);
+        let old = self.atoms.insert(key, Entry {
+            weak: Arc::downgrade(value),
+            used: AtomicUsize::new(used),
+        });
+        debug_assert!(old.is_none());
+    }
+    /// Removes the entry of `key`
+    pub fn remove(&mut self, key: &N::Query) {
+        if let Some((key, _)) = self.atoms.remove_entry(key) {
+            ShardGuard::sub_bytes(self.shard, &key);
+        }
+    }
+    pub fn reserve(&mut self, additional: usize) {
+        self.atoms.reserve(additional)
+    }
+    pub fn try_reserve(&mut self, additional: usize)
+        -> Result<(), TryReserveError>
+    {
+        self.atoms.try_reserve(additional)
+    }
+    /// Marks the entry as recently used
+    pub fn touch(&self, entry: &Entry<N>) {
+        self.pool.touch(entry)
+    }
+    /// Removes all entries, returns the number removed
+    pub fn clear(&mut self) -> usize {
+        let removed = self.atoms.len();
+        self.atoms.clear();
+        self.shard.dead.store(0, Ordering::Relaxed);
+        self.shard.bytes.store(0, Ordering::Relaxed);
+        removed
+    }
+    /// Removes entries of dropped values, returns the number removed
+    pub fn sweep(&mut self) -> usize {
+        let before = self.atoms.len();
+        let shard = self.shard;
+        self.atoms.retain(|key, w| {
+            let alive = w.strong_count() > 0;
+            if !alive {
+                ShardGuard::sub_bytes(shard, key);
+            }
+            alive
+        });
+        self.shard.dead.store(0, Ordering::Relaxed);
+        before - self.atoms.len()
+    }
+    /// Sweeps if many values were dropped since the last sweep
+    ///
+    /// The threshold is proportional to the size of the table, so the cost
+    /// of sweeping is amortized over the drops.
+    pub fn maybe_sweep(&mut self) {
+        let dead = self.shard.dead.load(Ordering::Relaxed);
+        if dead > self.atoms.len() / 2 + 16 {
+            self.sweep();
+        }
+    }
+    /// Makes room for a new value of `len` bytes, if the pool is limited
+    pub fn make_room(&mut self, len: usize) {
+        let max_entries = self.pool.max_entries.load(Ordering::Relaxed);
+        let max_bytes = self.pool.max_bytes.load(Ordering::Relaxed);
+        let fits = |atoms: &ShardGuard<N>, entries: usize, bytes: usize| {
+            atoms.atoms.len() < entries &&
+                atoms.shard.bytes.load(Ordering::Relaxed)
+                    .saturating_add(len) <= bytes
+        };
+        if fits(self, max_entries, max_bytes) {
+            return;
+        }
+        self.sweep();
+        if fits(self, max_entries, max_bytes) {
+            return;
+        }
+        // Evict down to 3/4 of the limits, so that the cost of sorting is
+        // amortized over the following insertions
+        let (entries, bytes) = (max_entries - max_entries / 4,
+                                max_bytes - max_bytes / 4);
+        let mut lru = {
+            let pinned = scope::pinned();
+            self.atoms.iter()
+                .filter(|&(_, e)| {
+                    let ptr = e.weak.as_ptr() as *const ();
+                    !pinned.contains_key(&(ptr as usize))
+                })
+                .map(|(k, e)| (e.used.load(Ordering::Relaxed), k.clone()))
+                .collect::<Vec<_>>()
+        };
+        lru.sort_unstable_by_key(|&(used, _)| used);
+        for (_, key) in lru {
+            if fits(self, entries, bytes) {
+                break;
+            }
+            self.remove(key.borrow());
+        }
+        // Some evicted values may be alive, values interned from now on
+        // must not be assumed to be different from them
+        self.pool.epoch.fetch_add(1, Ordering::Relaxed);
+    }
+}
+
+impl<'a, N: Item + ?Sized> Deref for ShardGuard<'a, N> {
+    type Target = Atoms<N>;
+    fn deref(&self) -> &Atoms<N> {
+        &self.atoms
+    }
+}
+
+impl PoolLimits {
+    /// No limits, this is the default
+    pub fn new() -> PoolLimits {
+        PoolLimits::default()
+    }
+    /// Maximum number of entries in the pool
+    pub fn max_entries(mut self, value: usize) -> PoolLimits {
+        self.max_entries = Some(value);
+        self
+    }
+    /// Maximum total length of strings in the pool
+    pub fn max_bytes(mut self, value: usize) -> PoolLimits {
+        self.max_bytes = Some(value);
+        self
+    }
+}
+
+impl<'a, N: Item + ?Sized> WriteAll<'a, N> {
+    /// Returns the locked shard containing `key`
+    pub fn get(&mut self, key: &N::Query) -> &mut ShardGuard<'a, N> {
+        &mut self.guards[self.pool.index(key)]
+    }
+}
+
+impl<N: Item + ?Sized> Clone for PoolRef<N> {
+    fn clone(&self) -> PoolRef<N> {
+        match *self {
+            PoolRef::Shared(pool) => PoolRef::Shared(pool),
+            PoolRef::Local(ref pool) => PoolRef::Local(pool.clone()),
+        }
+    }
+}
+
+impl<N: Item + ?Sized> PoolRef<N> {
+    pub fn lock(&self, key: &N::Query) -> ShardGuard<'_, N> {
+        self.get().lock(key)
+    }
+    pub fn dropped(&self, key: &N::Query) {
+        self.get().dropped(key)
+    }
+    pub fn epoch(&self) -> u32 {
+        self.get().epoch()
+    }
+    pub fn release_id(&self, id: u32) {
+        self.get().release_id(id)
+    }
+    pub fn string(&self, id: u32, key: bool, value: &str) -> *const String {
+        self.get().string(id, key, value)
+    }
+    pub fn deferred_drop(&self) -> bool {
+        self.get().deferred_drop()
+    }
+    /// Called when the last reference to the value is dropped
+    pub fn release(&self, key: &N::Query, id: u32) {
+        self.release_id(id);
+        if self.deferred_drop() {
+            // dead entry is replaced by `ShardGuard::find` or swept later
+            self.dropped(key);
+            return;
+        }
+        self.lock(key).remove_dead(key);
+    }
+    /// Returns `true` if both refer to the same pool
+    pub fn same(&self, other: &PoolRef<N>) -> bool {
+        ptr::addr_eq(self.get(), other.get())
+    }
+    fn get(&self) -> &dyn Lock<N> {
+        match *self {
+            PoolRef::Shared(pool) => pool,
+            PoolRef::Local(ref pool) => &**pool,
+        }
+    }
+}
+
+/// Returns the shard and index in it
+// `SHARDS` is 1 without the `sharded` feature
+#[allow(clippy::modulo_one)]
+fn split_id(id: u32) -> (usize, usize) {
+    let id = id as usize;
+    (id % SHARDS, id / SHARDS)
+}
+
+/// Returns the pool of the validator type
+#[cfg(feature = "std")]
+pub(crate) fn pool<V: Validator + ?Sized>() -> &'static Pool {
+    let id = TypeId::of::<V>();
+    CACHE.try_with(|cache| {
+        if let Some(pool) = cache.borrow().get(&id) {
+            return *pool;
+        }
+        let pool = shared_pool(id);
+        cache.borrow_mut().insert(id, pool);
+        pool
+    // thread-local storage is not available while thread is exiting
+    }).unwrap_or_else(|_| shared_pool(id))
+}
+
+#[cfg(not(feature = "std"))]
+pub(crate) fn pool<V: Validator + ?Sized>() -> &'static Pool {
+    shared_pool(TypeId::of::<V>())
+}
+
+/// Remove entries of dropped values from pools of all validator types
+///
+/// Returns the number of removed entries. This is only needed with
+/// `DropPolicy::Deferred`: dropping a symbol leaves its entry in the pool
+/// then. Dead entries are also swept automatically when new values are
+/// interned, once their number is large enough. Pools of `Interner`s are
+/// not affected.
+pub fn purge_dead() -> usize {
+    let pools = POOLS.read().expect("pools locked").values().cloned()
+        .collect::<Vec<_>>();
+    pools.iter()
+        .flat_map(|pool| pool.write_shards())
+        .map(|mut atoms| atoms.sweep())
+        .sum()
+}
+
+/// Returns the pool of items `N` for the type `T`, e.g. a validator type
+pub(crate) fn item_pool<N: Item, T: ?Sized + 'static>() -> &'static Pool<N> {
+    let id = TypeId::of::<(N, T)>();
+    let found = ITEM_POOLS.read().expect("pools locked").get(&id).cloned();
+    let pool = found.unwrap_or_else(|| {
+        *ITEM_POOLS.write().expect("pools locked").entry(id)
+            .or_insert_with(|| Box::leak(Box::new(Pool::<N>::new())))
+    });
+    pool.downcast_ref().expect("pool of the right type")
+}
+
+fn shared_pool(id: TypeId) -> &'static Pool {
+    if let Some(pool) = POOLS.read().expect("pools locked").get(&id) {
+        return pool;
+    }
+    POOLS.write().expect("pools locked").entry(id)
+        .or_insert_with(|| Box::leak(Box::new(Pool::new())))
+}
+
+#[cfg(test)]
+mod test {
+    use std::collections::HashSet;
+    use super::{Pool, SHARDS};
+
+    #[test]
+    fn index() {
+        let pool: Pool = Pool::new();
+        let indexes = (0..100).map(|i| format!("shard_{}", i))
+            .map(|s| {
+                assert_eq!(pool.index(&s), pool.index(&s));
+                pool.index(&s)
+            })
+            .collect::<HashSet<_>>();
+        assert!(indexes.iter().all(|&i| i < SHARDS));
+        if SHARDS > 1 {
+            assert!(indexes.len() > 1);
+        }
+    }
+
+    #[test]
+    fn shards() {
+        let pool: Pool = Pool::new();
+        assert_eq!(pool.read_shards().count(), SHARDS);
+        assert_eq!(pool.write_shards().count(), SHARDS);
+    }
+
+    #[test]
+    fn not_poisoned() {
+        use std::sync::Arc;
+        use std::thread;
+        let pool: Arc<Pool> = Arc::new(Pool::new());
+        let locked = pool.clone();
+        let result = thread::spawn(move || {
+            let _guard = locked.write("poisoned");
+            panic!("panic while the shard is locked");
+        }).join();
+        assert!(result.is_err());
+        assert!(pool.read("poisoned").is_empty());
+        drop(pool.write("poisoned"));
+    }
+}
@@ -0,0 +1,96 @@
+use std::borrow::Cow;
+
+use schemars::{JsonSchema, Schema, SchemaGenerator};
+use {Validator, Symbol};
+
+
+/// Symbols are described as strings
+///
+/// When validator declares `MAX_LEN` it's emitted as `maxLength`, and
+/// `PATTERN` as `pattern`. Note that `MAX_LEN` is in bytes while
+/// `maxLength` is in characters, so the schema is slightly more permissive
+/// for non-ASCII values.
+impl<V: Validator + ?Sized> JsonSchema for Symbol<V> {
+    fn inline_schema() -> bool {
+        true
+    }
+
+    fn schema_name() -> Cow<'static, str> {
+        "Symbol".into()
+    }
+
+    fn json_schema(_: &mut SchemaGenerator) -> Schema {
+        let mut schema = json_schema!({
+            "type": "string",
+        });
+        if let Some(max_len) = V::MAX_LEN {
+            schema.insert("maxLength".into(), max_len.into());
+        }
+        if let Some(pattern) = V::PATTERN {
+            schema.insert("pattern".into(), pattern.into());
+        }
+        schema
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::io;
+    use serde_json;
+    use {Validator, Symbol, TooLong};
+
+    struct AnyString;
+    struct ShortString;
+
+    impl Validator for AnyString {
+        // Use an error from standard library to make example shorter
+        type Err = ::std::string::ParseError;
+        fn validate_symbol(_: &str) -> Result<(), Self::Err> {
+            Ok(())
+        }
+    }
+
+    impl Validator for ShortString {
+        type Err = io::Error;
+        const MAX_LEN: Option<usize> = Some(16);
+        const TOO_LONG: Option<fn(TooLong) -> io::Error> = Some(|_| {
+            io::Error::new(io::ErrorKind::InvalidData, "too long")
+        });
+        fn validate_symbol(_: &str) -> Result<(), Self::Err> {
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn string() {
+        let schema = schema_for!(Symbol<AnyString>);
+        let value = serde_json::to_value(&schema).unwrap();
+        assert_eq!(value["type"], "string");
+        assert!(value.get("maxLength").is_none());
+    }
+
+    #[test]
+    fn max_length() {
+        let schema = schema_for!(Symbol<ShortString>);
+        let value = serde_json::to_value(&schema).unwrap();
+        assert_eq!(value["type"], "string");
+        assert_eq!(value["maxLength"], 16);
+        assert!(value.get("pattern").is_none());
+    }
+
+    #[test]
+    fn pattern() {
+        struct Slug;
+        impl Validator for Slug {
+            type Err = ::std::string::ParseError;
+            const PATTERN: Option<&'static str> = Some("^[a-z]+$");
+            fn validate_symbol(_: &str) -> Result<(), Self::Err> {
+                Ok(())
+            }
+        }
+        let schema = schema_for!(Symbol<Slug>);
+        let value = serde_json::to_value(&schema).unwrap();
+        assert_eq!(value["type"], "string");
+        assert_eq!(value["pattern"], "^[a-z]+$");
+    }
+}
@@ -0,0 +1,212 @@
+#[cfg(feature = "std")] use core::cell::Cell;
+#[cfg(feature = "std")] use core::marker::PhantomData;
+use alloc::sync::Arc;
+
+use {Validator, Symbol};
+use base_type::Value;
+use compat::{HashMap, Mutex, MutexGuard};
+
+
+#[cfg(feature = "std")]
+thread_local! {
+    static PERMANENT: Cell<bool> = const { Cell::new(false) };
+    static NO_CACHE: Cell<bool> = const { Cell::new(false) };
+}
+
+lazy_static! {
+    // Keyed by address, which is unique as pinned values are never freed
+    static ref PINNED: Mutex<HashMap<usize, Arc<Value>>> =
+        Mutex::new(HashMap::new());
+}
+
+/// Options applied by `Symbol::scope`
+#[cfg(feature = "std")]
+#[derive(Debug, Clone, Default)]
+pub struct InternOptions {
+    permanent: bool,
+    no_cache: bool,
+}
+
+/// Guard returned by `Symbol::scope`
+///
+/// Restores previous options when dropped. Options are per thread, so the
+/// guard can't be sent to another thread. Nested guards must be dropped in
+/// reverse order (which is natural for scoped variables).
+#[cfg(feature = "std")]
+#[must_use = "options are reverted when the guard is dropped"]
+pub struct InternScope {
+    previous: InternOptions,
+    // options are thread-local
+    _not_send: PhantomData<*const ()>,
+}
+
+#[cfg(feature = "std")]
+impl InternOptions {
+    /// Default options, i.e. the behavior outside of any scope
+    pub fn new() -> InternOptions {
+        InternOptions::default()
+    }
+    /// Never free values interned while the option is enabled
+    ///
+    /// Values are pinned the first time they are interned in the scope
+    /// (either created or found in the pool) and are kept until the end of
+    /// the process, even after the scope is left.
+    pub fn permanent(mut self, value: bool) -> InternOptions {
+        self.permanent = value;
+        self
+    }
+    /// Use the thread-local cache of recently interned values
+    ///
+    /// Enabled by default, has no effect without the `thread-cache`
+    /// feature. See also `Symbol::set_thread_cache`.
+    pub fn thread_cache(mut self, value: bool) -> InternOptions {
+        self.no_cache = !value;
+        self
+    }
+}
+
+#[cfg(feature = "std")]
+impl<V: Validator + ?Sized> Symbol<V> {
+    /// Apply `options` to the current thread until the guard is dropped
+    ///
+    /// Options apply to symbols of all types, not only `V`.
+    pub fn scope(options: InternOptions) -> InternScope {
+        InternScope {
+            previous: apply(options),
+            _not_send: PhantomData,
+        }
+    }
+}
+
+impl<V: Validator + ?Sized> Symbol<V> {
+    /// Validate and intern the value, and never free it
+    ///
+    /// For values used for the whole life of the program, like keywords or
+    /// configuration keys. As the value is never freed, cloning and
+    /// dropping its symbols never touches the pool.
+    pub fn intern_static(s: &str) -> Result<Symbol<V>, V::Err> {
+        let sym: Symbol<V> = s.parse()?;
+        sym.pin();
+        Ok(sym)
+    }
+
+    /// Never free the value of this symbol
+    ///
+    /// Same as `intern_static` for a symbol which is interned already. If
+    /// the symbol comes from an `Interner` the pool is kept too.
+    pub fn pin(&self) {
+        pin_forever(&self.0);
+    }
+}
+
+#[cfg(feature = "std")]
+impl Drop for InternScope {
+    fn drop(&mut self) {
+        apply(self.previous.clone());
+    }
+}
+
+#[cfg(feature = "std")]
+fn apply(options: InternOptions) -> InternOptions {
+    InternOptions {
+        permanent: PERMANENT.with(|p| p.replace(options.permanent)),
+        no_cache: NO_CACHE.with(|c| c.replace(options.no_cache)),
+    }
+}
+
+/// Returns `false` if current scope disables the thread-local cache
+#[cfg(feature = "thread-cache")]
+pub fn thread_cache() -> bool {
+    // thread-local storage is not available while thread is exiting
+    !NO_CACHE.try_with(|c| c.get()).unwrap_or(false)
+}
+
+/// Called for every interned value, pins it if current scope asks so
+#[cfg(feature = "std")]
+pub fn pin(value: &Arc<Value>) {
+    if PERMANENT.with(|p| p.get()) {
+        pin_forever(value);
+    }
+}
+
+/// There are no scopes without `std`
+#[cfg(not(feature = "std"))]
+pub fn pin(_value: &Arc<Value>) {}
+
+/// Returns pinned values keyed by address
+pub fn pinned() -> MutexGuard<'static, HashMap<usize, Arc<Value>>> {
+    PINNED.lock().expect("pinned values locked")
+}
+
+/// Keeps the value alive until the end of the process
+pub fn pin_forever(value: &Arc<Value>) {
+    PINNED.lock().expect("pinned values locked")
+        .entry(Arc::as_ptr(value) as *const () as usize)
+        .or_insert_with(|| value.clone());
+}
+
+#[cfg(test)]
+mod test {
+    use {Validator, Symbol, InternOptions};
+
+    struct AnyString;
+    type Atom = Symbol<AnyString>;
+
+    impl Validator for AnyString {
+        // Use an error from standard library to make example shorter
+        type Err = ::std::string::ParseError;
+        fn validate_symbol(_: &str) -> Result<(), Self::Err> {
+            Ok(())
+        }
+    }
+
+    fn is_alive(s: &str) -> bool {
+        Atom::intern_tracked(s).unwrap().1
+    }
+
+    #[test]
+    fn permanent() {
+        {
+            let _scope = Atom::scope(InternOptions::new().permanent(true));
+            drop(Atom::from("scope_permanent_a"));
+            assert!(is_alive("scope_permanent_a"));
+        }
+        assert!(is_alive("scope_permanent_a"));
+        drop(Atom::from("scope_permanent_b"));
+        assert!(!is_alive("scope_permanent_b"));
+    }
+
+    #[test]
+    fn intern_static() {
+        drop(Atom::intern_static("scope_static").unwrap());
+        assert!(is_alive("scope_static"));
+        let x = Atom::from("scope_pin");
+        x.pin();
+        x.pin();
+        drop(x);
+        assert!(is_alive("scope_pin"));
+    }
+
+    #[test]
+    fn nested() {
+        let outer = Atom::scope(InternOptions::new().permanent(true));
+        {
+            let _inner = Atom::scope(InternOptions::new());
+            drop(Atom::from("scope_nested_a"));
+            assert!(!is_alive("scope_nested_a"));
+            {
+                let _inner2 = Atom::scope(
+                    InternOptions::new().permanent(true));
+                drop(Atom::from("scope_nested_b"));
+            }
+            drop(Atom::from("scope_nested_c"));
+            assert!(!is_alive("scope_nested_c"));
+        }
+        drop(Atom::from("scope_nested_d"));
+        drop(outer);
+        drop(Atom::from("scope_nested_e"));
+        assert!(is_alive("scope_nested_b"));
+        assert!(is_alive("scope_nested_d"));
+        assert!(!is_alive("scope_nested_e"));
+    }
+}
@@ -0,0 +1,195 @@
+use core::fmt;
+use core::ops::Deref;
+use core::marker::PhantomData;
+
+use serde::ser::{Serialize, Serializer};
+use serde::de::{self, Deserialize, Deserializer, DeserializeSeed};
+use serde::de::{SeqAccess, Visitor};
+use {Validator, Symbol};
+#[cfg(feature = "std")] use dedup;
+use compat::HashSet;
+use compat::prelude::*;
+
+
+/// A sequence of symbols optimized for deserializing repeated values
+///
+/// Values already seen in the sequence are taken from a local cache,
+/// without validating them again and without touching the global pool.
+/// This is useful for long arrays of few distinct values, like a column
+/// of categorical data. Serialized as a plain sequence of strings.
+///
+/// ```ignore
+/// #[derive(Deserialize)]
+/// struct Column {
+///     values: InternSeq<CategoryValidator>,
+/// }
+/// ```
+pub struct InternSeq<V: Validator + ?Sized>(pub Vec<Symbol<V>>);
+
+impl<V: Validator + ?Sized> InternSeq<V> {
+    /// Unwrap the underlying vector
+    pub fn into_inner(self) -> Vec<Symbol<V>> {
+        self.0
+    }
+}
+
+impl<V: Validator + ?Sized> Clone for InternSeq<V> {
+    fn clone(&self) -> InternSeq<V> {
+        InternSeq(self.0.clone())
+    }
+}
+
+impl<V: Validator + ?Sized> PartialEq for InternSeq<V> {
+    fn eq(&self, other: &InternSeq<V>) -> bool {
+        self.0.eq(&other.0)
+    }
+}
+impl<V: Validator + ?Sized> Eq for InternSeq<V> {}
+
+impl<V: Validator + ?Sized> fmt::Debug for InternSeq<V> {
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+        fmt::Debug::fmt(&self.0, fmt)
+    }
+}
+
+impl<V: Validator + ?Sized> Deref for InternSeq<V> {
+    type Target = Vec<Symbol<V>>;
+    fn deref(&self) -> &Vec<Symbol<V>> {
+        &self.0
+    }
+}
+
+impl<V: Validator + ?Sized> From<Vec<Symbol<V>>> for InternSeq<V> {
+    fn from(vec: Vec<Symbol<V>>) -> InternSeq<V> {
+        InternSeq(vec)
+    }
+}
+
+struct Cached<'a, V: Validator + ?Sized + 'a>(&'a mut HashSet<Symbol<V>>);
+
+impl<'de, 'a, V: Validator + ?Sized> DeserializeSeed<'de> for Cached<'a, V> {
+    type Value = Symbol<V>;
+
+    fn deserialize<D>(self, deserializer: D) -> Result<Symbol<V>, D::Error>
+        where D: Deserializer<'de>
+    {
+        #[cfg(feature = "std")]
+        {
+            // values are not repeated inside `Dedup` anyway
+            if dedup::active() {
+                return dedup::deserialize(deserializer);
+            }
+        }
+        deserializer.deserialize_str(self)
+    }
+}
+
+impl<'de, 'a, V: Validator + ?Sized> Visitor<'de> for Cached<'a, V> {
+    type Value = Symbol<V>;
+
+    fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        formatter.write_str("a valid symbol")
+    }
+
+    fn visit_str<E>(self, v: &str) -> Result<Self::Value, E>
+        where E: de::Error
+    {
+        if let Some(sym) = self.0.get(v) {
+            return Ok(sym.clone());
+        }
+        let sym: Symbol<V> = v.parse().map_err(de::Error::custom)?;
+        self.0.insert(sym.clone());
+        Ok(sym)
+    }
+}
+
+struct SeqVisitor<V: Validator + ?Sized>(PhantomData<V>);
+
+impl<'de, V: Validator + ?Sized> Visitor<'de> for SeqVisitor<V> {
+    type Value = InternSeq<V>;
+
+    fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        formatter.write_str("a sequence of valid symbols")
+    }
+
+    fn visit_seq<A>(self, mut seq: A) -> Result<Self::Value, A::Error>
+        where A: SeqAccess<'de>
+    {
+        let mut cache = HashSet::new();
+        let mut result = Vec::with_capacity(seq.size_hint().unwrap_or(0));
+        while let Some(sym) = seq.next_element_seed(Cached(&mut cache))? {
+            result.push(sym);
+        }
+        Ok(InternSeq(result))
+    }
+}
+
+impl<'de, V: Validator + ?Sized> Deserialize<'de> for InternSeq<V> {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+        where D: Deserializer<'de>
+    {
+        deserializer.deserialize_seq(SeqVisitor(PhantomData))
+    }
+}
+
+impl<V: Validator + ?Sized> Serialize for InternSeq<V> {
+    fn serialize<S: Serializer>(&self, serializer: S)
+        -> Result<S::Ok, S::Error>
+    {
+        self.0.serialize(serializer)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::io;
+    use serde_json;
+    use {Validator, Symbol, InternSeq};
+
+    struct AnyString;
+    type Atom = Symbol<AnyString>;
+
+    impl Validator for AnyString {
+        // Use an error from standard library to make example shorter
+        type Err = ::std::string::ParseError;
+        fn validate_symbol(_: &str) -> Result<(), Self::Err> {
+            Ok(())
+        }
+    }
+
+    struct AlphaNumString;
+
+    impl Validator for AlphaNumString {
+        // Use an error from standard library to make example shorter
+        type Err = io::Error;
+        fn validate_symbol(s: &str) -> Result<(), Self::Err> {
+            if s.chars().any(|c| !c.is_alphanumeric()) {
+                return Err(io::Error::new(io::ErrorKind::InvalidData,
+                    "Character is not alphanumeric"));
+            }
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn roundtrip() {
+        let data = r#"["seq_a","seq_b","seq_a","seq_a","seq_c","seq_b"]"#;
+        let seq: InternSeq<AnyString> = serde_json::from_str(data).unwrap();
+        assert_eq!(seq.len(), 6);
+        assert_eq!(*seq, vec!["seq_a", "seq_b", "seq_a", "seq_a", "seq_c",
+                              "seq_b"]);
+        assert_eq!(seq[0].as_ptr(), seq[2].as_ptr());
+        assert_eq!(seq[0].as_ptr(), seq[3].as_ptr());
+        assert_eq!(seq[1].as_ptr(), seq[5].as_ptr());
+        assert_eq!(seq[4].as_ptr(), Atom::from("seq_c").as_ptr());
+        assert_eq!(serde_json::to_string(&seq).unwrap(), data);
+    }
+
+    #[test]
+    fn invalid() {
+        assert!(serde_json::from_str::<InternSeq<AlphaNumString>>(
+            r#"["seqa","seqa","seq-b"]"#).is_err());
+        assert!(serde_json::from_str::<InternSeq<AlphaNumString>>(
+            r#"["seqa","seqb"]"#).is_ok());
+    }
+}
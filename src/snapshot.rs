@@ -0,0 +1,98 @@
+//! Lock-free read path, enabled by `arc-swap` feature
+//!
+//! A copy of the pool is published via `ArcSwap` and looked up before
+//! taking any locks. The copy is refreshed after the number of lookups
+//! which missed it exceeds a half of its size, so most lookups in a
+//! mostly stable pool don't touch the lock, while the cost of copying is
+//! amortized.
+//!
+//! The copy may contain stale entries, those are never upgraded and fall
+//! back to the locked path, but the keys keep string buffers alive until
+//! the next refresh.
+use std::sync::Arc;
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+use arc_swap::ArcSwap;
+use base_type::Value;
+use pool::{Atoms, Item, Pool};
+
+pub(crate) struct Snapshot<N: Item + ?Sized = Value> {
+    map: ArcSwap<Atoms<N>>,
+    // Number of lookups that missed the snapshot since it was published
+    misses: AtomicUsize,
+}
+
+impl<N: Item + ?Sized> Snapshot<N> {
+    pub fn new() -> Snapshot<N> {
+        Snapshot {
+            map: ArcSwap::from_pointee(Atoms::default()),
+            misses: AtomicUsize::new(0),
+        }
+    }
+
+    pub fn clear(&self) {
+        self.map.store(Arc::new(Atoms::default()));
+        self.misses.store(0, Ordering::Relaxed);
+    }
+    pub fn lookup(&self, key: &N::Query) -> Option<Arc<N>> {
+        self.map.load().get(key).and_then(|w| w.upgrade())
+    }
+}
+
+/// Called after a lookup which missed the snapshot
+///
+/// Must not be called while the pool is locked.
+pub fn missed<N: Item + ?Sized>(pool: &Pool<N>) {
+    let snapshot = &pool.snapshot;
+    let misses = snapshot.misses.fetch_add(1, Ordering::Relaxed) + 1;
+    if misses > snapshot.map.load().len() / 2 + 16 &&
+        snapshot.misses.compare_exchange(misses, 0,
+            Ordering::Relaxed, Ordering::Relaxed).is_ok()
+    {
+        let mut copy = Atoms::default();
+        for shard in pool.read_shards() {
+            copy.extend(shard.iter().map(|(k, v)| (k.clone(), v.clone())));
+        }
+        snapshot.map.store(Arc::new(copy));
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::sync::mpsc::channel;
+    use std::thread;
+    use std::time::Duration;
+    use {Validator, Symbol};
+    use pool::pool;
+
+    // Own validator type, so the pool isn't touched by other tests
+    struct Hot;
+    type HotAtom = Symbol<Hot>;
+
+    impl Validator for Hot {
+        type Err = ::std::string::ParseError;
+        fn validate_symbol(_: &str) -> Result<(), Self::Err> {
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn hit_without_lock() {
+        let hot = HotAtom::from("snapshot_hot");
+        // misses refresh the snapshot
+        let keep = (0..32).map(|i| format!("snapshot_{}", i).parse())
+            .collect::<Result<Vec<HotAtom>, _>>().unwrap();
+        assert!(pool::<Hot>().snapshot.lookup("snapshot_hot").is_some());
+        let guard = pool::<Hot>().write_all();
+        let (tx, rx) = channel();
+        thread::spawn(move || {
+            let sym: HotAtom = "snapshot_hot".parse().unwrap();
+            tx.send(sym.as_ptr() as usize).unwrap();
+        });
+        let ptr = rx.recv_timeout(Duration::from_secs(10))
+            .expect("lookup is blocked by the lock");
+        assert_eq!(ptr, hot.as_ptr() as usize);
+        drop(guard);
+        drop(keep);
+    }
+}
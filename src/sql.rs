@@ -0,0 +1,65 @@
+use diesel::backend::Backend;
+use diesel::deserialize::{self, FromSql};
+use diesel::serialize::{self, Output, ToSql};
+use diesel::sql_types::Text;
+
+use {Validator, Symbol};
+
+
+/// Written the same way as `str`
+impl<V, DB> ToSql<Text, DB> for Symbol<V>
+    where V: Validator + ?Sized, DB: Backend, str: ToSql<Text, DB>,
+{
+    fn to_sql<'b>(&'b self, out: &mut Output<'b, '_, DB>)
+        -> serialize::Result
+    {
+        <str as ToSql<Text, DB>>::to_sql(&self[..], out)
+    }
+}
+
+/// Read as a `String`, then validated and interned
+impl<V, DB> FromSql<Text, DB> for Symbol<V>
+    where V: Validator + ?Sized, DB: Backend, String: FromSql<Text, DB>,
+{
+    fn from_sql(bytes: DB::RawValue<'_>) -> deserialize::Result<Self> {
+        let value = <String as FromSql<Text, DB>>::from_sql(bytes)?;
+        Symbol::from_string(value)
+            .map_err(|e: V::Err| e.to_string().into())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use diesel::backend::Backend;
+    use diesel::deserialize::{FromSql, FromSqlRow};
+    use diesel::expression::AsExpression;
+    use diesel::serialize::ToSql;
+    use diesel::sql_types::{Nullable, Text};
+    use Symbol;
+    use validators::Identifier;
+
+    fn expression<T: AsExpression<Text>>(_: T) {}
+    fn nullable<T: AsExpression<Nullable<Text>>>(_: T) {}
+
+    // No backend is compiled in, so this only checks the bounds
+    #[allow(dead_code)]
+    fn backend<DB: Backend>()
+        where str: ToSql<Text, DB>, String: FromSql<Text, DB>,
+    {
+        fn column<T, DB>()
+            where DB: Backend,
+                  T: ToSql<Text, DB> + FromSql<Text, DB>,
+                  T: FromSqlRow<Text, DB>,
+                  Option<T>: FromSqlRow<Nullable<Text>, DB>,
+        {}
+        column::<Symbol<Identifier>, DB>();
+    }
+
+    #[test]
+    fn as_expression() {
+        let sym = Symbol::<Identifier>::from("sql_value");
+        expression(&sym);
+        nullable(&sym);
+        expression(sym);
+    }
+}
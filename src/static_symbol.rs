@@ -0,0 +1,464 @@
+use core::fmt;
+use core::marker::PhantomData;
+use core::ops::Deref;
+
+use {Validator, Symbol};
+use base_type::intern_dynamic;
+use compat::prelude::*;
+use compat::{HashMap, OnceLock};
+use validator::{validate, fold};
+
+
+/// A symbol which is interned on first access
+///
+/// This is the type of constants declared by the `symbols!` macro. It
+/// dereferences to the `Symbol` itself, so use `.clone()` to get an owned
+/// copy.
+///
+/// # Panics
+///
+/// On first access, if the value is not valid (same as `Symbol::from`).
+pub struct StaticSymbol<V: Validator + ?Sized> {
+    value: &'static str,
+    cell: OnceLock<Symbol<V>>,
+}
+
+impl<V: Validator + ?Sized> StaticSymbol<V> {
+    /// Create a symbol that is interned lazily
+    ///
+    /// Usually you should use `symbols!` macro instead.
+    pub const fn new(value: &'static str) -> StaticSymbol<V> {
+        StaticSymbol {
+            value,
+            cell: OnceLock::new(),
+        }
+    }
+
+    /// Returns interned symbol, interning it on the first call
+    pub fn get(&self) -> &Symbol<V> {
+        self.cell.get_or_init(|| Symbol::from(self.value))
+    }
+}
+
+impl<V: Validator + ?Sized> Deref for StaticSymbol<V> {
+    type Target = Symbol<V>;
+    fn deref(&self) -> &Symbol<V> {
+        self.get()
+    }
+}
+
+impl<V: Validator + ?Sized> fmt::Debug for StaticSymbol<V> {
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+        fmt::Debug::fmt(self.get(), fmt)
+    }
+}
+
+/// A closed set of well-known values, interned on first access
+///
+/// Usually declared by the `static_atoms!` macro and returned by
+/// `Validator::static_atoms`. Values of the set are resolved by a lookup in
+/// a read-only table, without locking or hashing the pool, and unknown
+/// values fall back to the pool. Values of the set are never freed.
+/// `Interner`s don't use the set.
+///
+/// # Panics
+///
+/// On first access, if any of the values is not valid.
+pub struct StaticAtoms<V: Validator + ?Sized> {
+    values: &'static [&'static str],
+    table: OnceLock<HashMap<Box<str>, Symbol<V>>>,
+    phantom: PhantomData<V>,
+}
+
+impl<V: Validator + ?Sized> StaticAtoms<V> {
+    /// Create a set that is interned lazily
+    ///
+    /// Usually you should use `static_atoms!` macro instead.
+    pub const fn new(values: &'static [&'static str]) -> StaticAtoms<V> {
+        StaticAtoms {
+            values,
+            table: OnceLock::new(),
+            phantom: PhantomData,
+        }
+    }
+
+    /// Returns the values of the set as declared
+    pub fn values(&self) -> &'static [&'static str] {
+        self.values
+    }
+
+    /// Returns the symbol if the value is in the set
+    ///
+    /// The value is normalized, same as for `Symbol::get`.
+    pub fn get(&self, s: &str) -> Option<Symbol<V>> {
+        let s = V::normalize(s);
+        self.lookup(&V::fold(&s)).cloned()
+    }
+
+    /// Looks up a key, as returned by `Validator::fold`
+    pub(crate) fn lookup(&self, key: &str) -> Option<&Symbol<V>> {
+        self.table.get_or_init(|| {
+            self.values.iter().map(|value| {
+                let value = validate::<V>(value).unwrap_or_else(|_| {
+                    panic!("invalid value in static atoms {:?}", value)
+                });
+                let key = fold::<V>(&value);
+                let table_key = Box::from(key.as_deref().unwrap_or(&value));
+                let sym = Symbol(intern_dynamic::<V>(value, key).0,
+                                 PhantomData);
+                sym.pin();
+                (table_key, sym)
+            }).collect()
+        }).get(key)
+    }
+}
+
+impl<V: Validator + ?Sized> fmt::Debug for StaticAtoms<V> {
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+        fmt.debug_set().entries(self.values).finish()
+    }
+}
+
+/// Declare a closed set of well-known values of a symbol type
+///
+/// Declares a static `StaticAtoms`. Return it from `Validator::static_atoms`
+/// to make parsing, interning and looking up these values skip the pool.
+///
+/// # Example
+///
+/// ```
+/// # #[macro_use] extern crate string_intern;
+/// # use string_intern::{Validator, Symbol, StaticAtoms};
+/// struct Method;
+///
+/// static_atoms!(Method; pub METHODS = ["GET", "POST", "PUT"]);
+///
+/// impl Validator for Method {
+///     type Err = ::std::string::ParseError;
+///     fn validate_symbol(_: &str) -> Result<(), Self::Err> { Ok(()) }
+///     fn static_atoms() -> Option<&'static StaticAtoms<Self>> {
+///         Some(&METHODS)
+///     }
+/// }
+///
+/// # fn main() {
+/// let get: Symbol<Method> = "GET".parse().unwrap();
+/// assert_eq!(get.as_ptr(), METHODS.get("GET").unwrap().as_ptr());
+/// // unknown values are interned as usual
+/// let patch: Symbol<Method> = "PATCH".parse().unwrap();
+/// assert_eq!(METHODS.get("PATCH"), None);
+/// # }
+/// ```
+#[macro_export]
+macro_rules! static_atoms {
+    ($validator:ty; $(#[$meta:meta])* $vis:vis $name:ident =
+        [$($value:expr),* $(,)*])
+    => {
+        $(#[$meta])*
+        $vis static $name: $crate::StaticAtoms<$validator> =
+            $crate::StaticAtoms::new(&[$($value),*]);
+    };
+}
+
+/// Declare a group of lazily interned symbol constants
+///
+/// Each constant is a `StaticSymbol` that is validated and interned once,
+/// on first access. Dereference it or call `.clone()` to get a `Symbol`.
+///
+/// # Example
+///
+/// ```
+/// # #[macro_use] extern crate string_intern;
+/// # use string_intern::{Validator, Symbol};
+/// # struct Method;
+/// # impl Validator for Method {
+/// #     type Err = ::std::string::ParseError;
+/// #     fn validate_symbol(_: &str) -> Result<(), Self::Err> { Ok(()) }
+/// # }
+/// symbols!(Method;
+///     pub GET = "get",
+///     pub POST = "post",
+/// );
+///
+/// # fn main() {
+/// let get: Symbol<Method> = GET.clone();
+/// assert_eq!(&get[..], "get");
+/// # }
+/// ```
+#[macro_export]
+macro_rules! symbols {
+    ($validator:ty; $($(#[$meta:meta])* $vis:vis $name:ident = $value:expr),*
+        $(,)*)
+    => {
+        $(
+            $(#[$meta])*
+            $vis static $name: $crate::StaticSymbol<$validator> =
+                $crate::StaticSymbol::new($value);
+        )*
+    };
+}
+
+/// Declare a lazily interned symbol constant, validated at compile time
+///
+/// Works like `symbols!` for a single constant, but the validator type must
+/// have an inherent `const fn validate_const(&str) -> bool`, which is
+/// evaluated at compile time. So an invalid literal is a compile error
+/// rather than a panic on first access. The value is still validated by
+/// `validate_symbol` and interned on first access.
+///
+/// Use `symbol!` for a checked literal in an expression. The check can't
+/// live in the `derive` crate, because a proc-macro can't call a validator
+/// defined in the crate it expands in.
+///
+/// # Example
+///
+/// ```
+/// # #[macro_use] extern crate string_intern;
+/// # use string_intern::{Validator, Symbol};
+/// struct Lower;
+/// impl Lower {
+///     const fn validate_const(s: &str) -> bool {
+///         let bytes = s.as_bytes();
+///         let mut i = 0;
+///         while i < bytes.len() {
+///             if !bytes[i].is_ascii_lowercase() {
+///                 return false;
+///             }
+///             i += 1;
+///         }
+///         true
+///     }
+/// }
+/// # impl Validator for Lower {
+/// #     type Err = ::std::string::ParseError;
+/// #     fn validate_symbol(_: &str) -> Result<(), Self::Err> { Ok(()) }
+/// # }
+/// const_symbol!(Lower; pub GET = "get");
+///
+/// # fn main() {
+/// let get: Symbol<Lower> = GET.clone();
+/// assert_eq!(&get[..], "get");
+/// # }
+/// ```
+///
+/// Invalid literal fails to compile:
+///
+/// ```compile_fail
+/// # #[macro_use] extern crate string_intern;
+/// # use string_intern::Validator;
+/// # struct Lower;
+/// # impl Lower {
+/// #     const fn validate_const(s: &str) -> bool { s.is_empty() }
+/// # }
+/// # impl Validator for Lower {
+/// #     type Err = ::std::string::ParseError;
+/// #     fn validate_symbol(_: &str) -> Result<(), Self::Err> { Ok(()) }
+/// # }
+/// const_symbol!(Lower; GET = "GET");
+/// # fn main() {}
+/// ```
+#[macro_export]
+macro_rules! const_symbol {
+    ($validator:ty; $(#[$meta:meta])* $vis:vis $name:ident = $value:expr)
+    => {
+        const _: () = assert!(<$validator>::validate_const($value),
+            concat!("invalid value of symbol `", stringify!($name),
+                    "`: ", stringify!($value)));
+        $(#[$meta])*
+        $vis static $name: $crate::StaticSymbol<$validator> =
+            $crate::StaticSymbol::new($value);
+    };
+}
+
+/// Intern a value once and return a clone of the symbol on each call
+///
+/// Returns a `Symbol<V>`. The symbol is kept in a hidden `StaticSymbol`,
+/// so after the first call this is an atomic load and a reference count
+/// increment instead of a lookup in the pool. Useful for keywords used in
+/// hot loops. Panics on first call if the value is invalid.
+///
+/// # Example
+///
+/// ```
+/// # #[macro_use] extern crate string_intern;
+/// # use string_intern::{Validator, Symbol};
+/// # struct Keyword;
+/// # impl Validator for Keyword {
+/// #     type Err = ::std::string::ParseError;
+/// #     fn validate_symbol(_: &str) -> Result<(), Self::Err> { Ok(()) }
+/// # }
+/// # fn main() {
+/// for _ in 0..3 {
+///     let kw: Symbol<Keyword> = static_symbol!(Keyword, "if");
+///     assert_eq!(&kw[..], "if");
+/// }
+/// # }
+/// ```
+#[macro_export]
+macro_rules! static_symbol {
+    ($validator:ty, $value:expr $(,)*) => {{
+        static SYMBOL: $crate::StaticSymbol<$validator> =
+            $crate::StaticSymbol::new($value);
+        SYMBOL.get().clone()
+    }};
+}
+
+/// Intern a string literal, validated at compile time
+///
+/// Returns a `Symbol<V>`. Same as `static_symbol!`, but the literal is
+/// first checked by an inherent `const fn validate_const(&str) -> bool` of
+/// the validator type (see `const_symbol!`), so an invalid literal is a
+/// build error rather than a panic at runtime.
+///
+/// # Example
+///
+/// ```
+/// # #[macro_use] extern crate string_intern;
+/// # use string_intern::{Validator, Symbol};
+/// # struct Lower;
+/// # impl Lower {
+/// #     const fn validate_const(s: &str) -> bool { !s.is_empty() }
+/// # }
+/// # impl Validator for Lower {
+/// #     type Err = ::std::string::ParseError;
+/// #     fn validate_symbol(_: &str) -> Result<(), Self::Err> { Ok(()) }
+/// # }
+/// # fn main() {
+/// let get: Symbol<Lower> = symbol!(Lower, "get");
+/// assert_eq!(&get[..], "get");
+/// # }
+/// ```
+///
+/// Invalid literal fails to compile:
+///
+/// ```compile_fail
+/// # #[macro_use] extern crate string_intern;
+/// # use string_intern::Validator;
+/// # struct Lower;
+/// # impl Lower {
+/// #     const fn validate_const(s: &str) -> bool { !s.is_empty() }
+/// # }
+/// # impl Validator for Lower {
+/// #     type Err = ::std::string::ParseError;
+/// #     fn validate_symbol(_: &str) -> Result<(), Self::Err> { Ok(()) }
+/// # }
+/// # fn main() {
+/// let empty = symbol!(Lower, "");
+/// # }
+/// ```
+#[macro_export]
+macro_rules! symbol {
+    ($validator:ty, $value:expr $(,)*) => {{
+        const _: () = assert!(<$validator>::validate_const($value),
+            concat!("invalid symbol literal: ", stringify!($value)));
+        static SYMBOL: $crate::StaticSymbol<$validator> =
+            $crate::StaticSymbol::new($value);
+        SYMBOL.get().clone()
+    }};
+}
+
+#[cfg(test)]
+mod test {
+    use {Validator, Symbol, StaticAtoms};
+    use pool::pool;
+
+    struct AnyString;
+
+    impl Validator for AnyString {
+        // Use an error from standard library to make example shorter
+        type Err = ::std::string::ParseError;
+        fn validate_symbol(_: &str) -> Result<(), Self::Err> {
+            Ok(())
+        }
+    }
+
+    impl AnyString {
+        const fn validate_const(s: &str) -> bool {
+            !s.is_empty()
+        }
+    }
+
+    const_symbol!(AnyString; PUT = "static_put");
+
+    struct Keyword;
+
+    static_atoms!(Keyword; KEYWORDS = ["if", "else", "Loop"]);
+
+    impl Validator for Keyword {
+        type Err = ::std::string::ParseError;
+        fn validate_symbol(_: &str) -> Result<(), Self::Err> {
+            Ok(())
+        }
+        fn fold(value: &str) -> ::std::borrow::Cow<'_, str> {
+            value.to_lowercase().into()
+        }
+        fn static_atoms() -> Option<&'static StaticAtoms<Self>> {
+            Some(&KEYWORDS)
+        }
+    }
+
+    symbols!(AnyString;
+        GET = "static_get",
+        /// Documented constant
+        POST = "static_post",
+    );
+
+    #[test]
+    fn values() {
+        assert_eq!(&GET[..], "static_get");
+        assert_eq!(&POST[..], "static_post");
+        assert_eq!(&PUT[..], "static_put");
+        assert_eq!(PUT.as_ptr(), Symbol::<AnyString>::from("static_put")
+            .as_ptr());
+    }
+
+    #[test]
+    fn static_symbol_macro() {
+        fn keyword() -> Symbol<AnyString> {
+            static_symbol!(AnyString, "static_keyword")
+        }
+        let x = keyword();
+        assert_eq!(&x[..], "static_keyword");
+        assert_eq!(x.as_ptr(), keyword().as_ptr());
+        assert_eq!(x.as_ptr(), Symbol::<AnyString>::from("static_keyword")
+            .as_ptr());
+    }
+
+    #[test]
+    fn symbol_macro() {
+        fn literal() -> Symbol<AnyString> {
+            symbol!(AnyString, "static_literal")
+        }
+        let x = literal();
+        assert_eq!(&x[..], "static_literal");
+        assert_eq!(x.as_ptr(), literal().as_ptr());
+        assert_eq!(x.as_ptr(), Symbol::<AnyString>::from("static_literal")
+            .as_ptr());
+    }
+
+    #[test]
+    fn static_atoms() {
+        let known = KEYWORDS.get("if").unwrap();
+        assert_eq!(KEYWORDS.get("while"), None);
+        assert_eq!(KEYWORDS.values(), &["if", "else", "Loop"]);
+        // known values don't touch the pool
+        let guard = pool::<Keyword>().write_all();
+        let x: Symbol<Keyword> = "if".parse().unwrap();
+        assert_eq!(x.as_ptr(), known.as_ptr());
+        assert_eq!(&Symbol::<Keyword>::get("LOOP").unwrap()[..], "Loop");
+        drop((x, guard));
+        // and are found in the pool by other paths too
+        let parts = Symbol::<Keyword>::intern_split("if/while", '/').unwrap();
+        assert_eq!(parts[0].as_ptr(), known.as_ptr());
+        assert_eq!(&parts[1][..], "while");
+    }
+
+    #[test]
+    fn shared() {
+        let x: Symbol<AnyString> = GET.clone();
+        assert_eq!(x, Symbol::from("static_get"));
+        assert_eq!(x.as_ptr(), Symbol::<AnyString>::from("static_get")
+            .as_ptr());
+        assert_eq!(GET.as_ptr(), GET.get().as_ptr());
+    }
+}
@@ -0,0 +1,103 @@
+use proptest::arbitrary::{any, Arbitrary};
+use proptest::strategy::{BoxedStrategy, Strategy};
+use proptest::string::string_regex;
+
+use {Validator, Symbol};
+
+
+/// Generates valid symbols
+///
+/// Strings are generated from `Validator::PATTERN` if it's set, or are
+/// arbitrary strings otherwise, and then only the valid ones are kept. So
+/// validators which reject most strings should set `PATTERN`, or proptest
+/// gives up after too many rejected values.
+impl<V: Validator + ?Sized> Arbitrary for Symbol<V> {
+    type Parameters = ();
+    type Strategy = BoxedStrategy<Symbol<V>>;
+
+    fn arbitrary_with(_: ()) -> Self::Strategy {
+        let strings = V::PATTERN
+            .and_then(|pattern| string_regex(unanchored(pattern)).ok())
+            .map(|s| s.boxed())
+            .unwrap_or_else(|| any::<String>().boxed());
+        strings
+            .prop_filter_map("invalid symbol", |s| s.parse().ok())
+            .boxed()
+    }
+}
+
+/// Strips `^` and `$`, as anchors are not supported by `string_regex`
+///
+/// Generated strings match the whole pattern, so they match the anchored
+/// pattern too.
+fn unanchored(pattern: &str) -> &str {
+    let pattern = pattern.strip_prefix('^').unwrap_or(pattern);
+    match pattern.strip_suffix('$') {
+        Some(p) if !p.ends_with('\\') => p,
+        _ => pattern,
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::io;
+    use proptest::prelude::*;
+    use {Validator, Symbol};
+    use validators::NonEmpty;
+    use super::unanchored;
+
+    struct AnyString;
+
+    impl Validator for AnyString {
+        // Use an error from standard library to make example shorter
+        type Err = ::std::string::ParseError;
+        fn validate_symbol(_: &str) -> Result<(), Self::Err> {
+            Ok(())
+        }
+    }
+
+    struct Ident;
+
+    impl Validator for Ident {
+        type Err = io::Error;
+        const PATTERN: Option<&'static str> = Some("^[a-z][a-z0-9_]{0,7}$");
+        fn validate_symbol(value: &str) -> Result<(), Self::Err> {
+            let mut chars = value.chars();
+            if !chars.next().is_some_and(|c| c.is_ascii_lowercase()) ||
+               !chars.all(|c| c.is_ascii_lowercase() || c.is_ascii_digit() ||
+                              c == '_') ||
+               value.len() > 8
+            {
+                return Err(io::Error::new(io::ErrorKind::InvalidData,
+                    "invalid identifier"));
+            }
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn anchors() {
+        assert_eq!(unanchored("^[a-z]+$"), "[a-z]+");
+        assert_eq!(unanchored("[a-z]+"), "[a-z]+");
+        assert_eq!(unanchored(r"^a\$"), r"a\$");
+    }
+
+    proptest! {
+        #[test]
+        fn any_string(sym in any::<Symbol<AnyString>>()) {
+            let parsed: Symbol<AnyString> = sym.parse().unwrap();
+            prop_assert_eq!(parsed.as_ptr(), sym.as_ptr());
+        }
+
+        #[test]
+        fn filtered(sym in any::<Symbol<NonEmpty>>()) {
+            prop_assert!(!sym.is_empty());
+        }
+
+        // would be rejected too often without a pattern
+        #[test]
+        fn pattern(sym in any::<Symbol<Ident>>()) {
+            prop_assert!(sym.len() <= 8);
+        }
+    }
+}
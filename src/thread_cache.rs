@@ -0,0 +1,167 @@
+//! Per-thread cache of recently interned values
+//!
+//! Consulted before the pool, so interning the same few strings over and
+//! over (log levels, event names) doesn't take any locks. The cache holds
+//! weak references, so it doesn't keep values alive (only their memory),
+//! and it's a small LRU list: a linear scan of a few entries is cheaper
+//! than hashing.
+use std::cell::RefCell;
+use std::ptr;
+use std::sync::{Arc, Weak};
+
+use base_type::{Buf, Value};
+use pool::{Item, Lock, Pool};
+
+
+/// Number of values cached by each thread (for all validator types)
+const SIZE: usize = 16;
+
+struct Entry {
+    pool: *const Pool,
+    key: Buf,
+    value: Weak<Value>,
+}
+
+thread_local! {
+    // Most recently used entries first
+    static CACHE: RefCell<Vec<Entry>> = const { RefCell::new(Vec::new()) };
+}
+
+/// Returns the value with the key if it's cached and still in the pool
+pub fn lookup(pool: &'static Pool, key: &str) -> Option<Arc<Value>> {
+    CACHE.try_with(|cache| {
+        let mut cache = cache.borrow_mut();
+        let idx = cache.iter()
+            .position(|e| ptr::eq(e.pool, pool) && e.key[..] == *key)?;
+        // values from older epochs were evicted, or the pool was cleared
+        match cache[idx].value.upgrade() {
+            Some(value) if value.epoch() == pool.epoch() => {
+                cache[..=idx].rotate_right(1);
+                Some(value)
+            }
+            _ => {
+                cache.remove(idx);
+                None
+            }
+        }
+    // thread-local storage is not available while thread is exiting
+    }).ok().flatten()
+}
+
+/// Remembers the value just interned into the pool
+pub fn insert(pool: &'static Pool, value: &Arc<Value>) {
+    let _ = CACHE.try_with(|cache| {
+        let mut cache = cache.borrow_mut();
+        if cache.len() >= SIZE {
+            cache.pop();
+        } else if cache.is_empty() {
+            // the only allocation made by the cache
+            cache.reserve_exact(SIZE);
+        }
+        cache.insert(0, Entry {
+            pool,
+            key: Value::entry_key(value),
+            value: Arc::downgrade(value),
+        });
+    });
+}
+
+#[cfg(test)]
+mod test {
+    use std::ptr;
+
+    use {Validator, Symbol, InternOptions};
+    use pool::{pool, Pool};
+    use super::CACHE;
+
+    struct AnyString;
+    type Atom = Symbol<AnyString>;
+
+    impl Validator for AnyString {
+        // Use an error from standard library to make example shorter
+        type Err = ::std::string::ParseError;
+        fn validate_symbol(_: &str) -> Result<(), Self::Err> {
+            Ok(())
+        }
+    }
+
+    fn cached(pool: &Pool, key: &str) -> bool {
+        CACHE.with(|cache| {
+            cache.borrow().iter()
+                .any(|e| ptr::eq(e.pool, pool) && e.key[..] == *key)
+        })
+    }
+
+    #[test]
+    fn no_lock() {
+        let a = Atom::from("thread_cache_a");
+        let guard = pool::<AnyString>().write("thread_cache_a");
+        // would deadlock if the pool was locked
+        let b = Atom::from("thread_cache_a");
+        drop(guard);
+        assert_eq!(a.as_ptr(), b.as_ptr());
+    }
+
+    #[test]
+    fn not_kept_alive() {
+        drop(Atom::from("thread_cache_b"));
+        assert_eq!(Atom::get("thread_cache_b"), None);
+        let (_, existed) = Atom::intern_tracked("thread_cache_b").unwrap();
+        assert!(!existed);
+    }
+
+    #[test]
+    fn cleared() {
+        struct Cleared;
+        impl Validator for Cleared {
+            type Err = ::std::string::ParseError;
+            fn validate_symbol(_: &str) -> Result<(), Self::Err> {
+                Ok(())
+            }
+        }
+        let a = Symbol::<Cleared>::from("thread_cache_c");
+        Symbol::<Cleared>::clear();
+        let b = Symbol::<Cleared>::from("thread_cache_c");
+        assert_ne!(a.as_ptr(), b.as_ptr());
+        assert_eq!(a, b);
+    }
+    #[test]
+    fn disabled_pool() {
+        struct Uncached;
+        impl Validator for Uncached {
+            type Err = ::std::string::ParseError;
+            fn validate_symbol(_: &str) -> Result<(), Self::Err> {
+                Ok(())
+            }
+        }
+        Symbol::<Uncached>::set_thread_cache(false);
+        let a = Symbol::<Uncached>::from("thread_cache_d");
+        let b = Atom::from("thread_cache_d");
+        assert!(!cached(pool::<Uncached>(), "thread_cache_d"));
+        assert!(cached(pool::<AnyString>(), "thread_cache_d"));
+        let guard = pool::<AnyString>().write("thread_cache_d");
+        assert_eq!(Atom::from("thread_cache_d").as_ptr(), b.as_ptr());
+        drop(guard);
+        let c = Symbol::<Uncached>::from("thread_cache_d");
+        assert_eq!(a.as_ptr(), c.as_ptr());
+        assert!(!cached(pool::<Uncached>(), "thread_cache_d"));
+    }
+
+    #[test]
+    fn scope() {
+        let pool = pool::<AnyString>();
+        let outer = Atom::scope(InternOptions::new().thread_cache(false));
+        let _a = Atom::from("thread_cache_e");
+        assert!(!cached(pool, "thread_cache_e"));
+        {
+            let _inner = Atom::scope(InternOptions::new());
+            let _b = Atom::from("thread_cache_f");
+            assert!(cached(pool, "thread_cache_f"));
+        }
+        let _c = Atom::from("thread_cache_g");
+        assert!(!cached(pool, "thread_cache_g"));
+        drop(outer);
+        let _a = Atom::from("thread_cache_e");
+        assert!(cached(pool, "thread_cache_e"));
+    }
+}
@@ -1,7 +1,10 @@
-use std::fmt;
-use std::error::Error;
+use alloc::borrow::Cow;
+use core::fmt;
+use core::cmp;
+use core::sync::atomic::{AtomicUsize, Ordering};
 
-use Symbol;
+use {Symbol, StaticAtoms, ValidationError, TooLong};
+use compat::prelude::*;
 
 
 /// This is validator trait you should implement for your own symbols
@@ -11,11 +14,547 @@ use Symbol;
 /// 1. Validates that atom contains only value you expect it to contain
 /// 2. Identifies the type i.e. `type S1 = Symbol<V1>` and
 ///    `type S2 = Symbol<V2>` are different and incompatible types
-/// 3. Allows to override `Display` trait for your own symbol
-pub trait Validator {
-    type Err: Error;
-    fn validate_symbol(&str) -> Result<(), Self::Err>;
-    fn display(value: &Symbol<Self>, fmt: &mut fmt::Formatter) -> fmt::Result {
+/// 3. Allows to override `Debug` and `Display` traits for your own symbol
+///
+/// Each validator type has its own pool of interned values, identified by
+/// its `TypeId`, hence the `'static` bound.
+pub trait Validator: 'static {
+    /// Error of invalid values
+    ///
+    /// Doesn't have to implement `Error`, so e.g. `anyhow::Error` or
+    /// `Box<dyn Error + Send + Sync>` can be used. `SymbolParseError` and
+    /// other errors wrapping it implement `Error` only if it does.
+    type Err: fmt::Display + fmt::Debug;
+    /// Maximum length of the symbol in bytes
+    ///
+    /// Longer values are rejected before `validate_symbol` is called, with
+    /// the error made by `TOO_LONG`, which must be set too.
+    const MAX_LEN: Option<usize> = None;
+    /// Makes the error for values longer than allowed
+    ///
+    /// Used for both `MAX_LEN` and `global_max_len()`. Setting `MAX_LEN`
+    /// without it fails to compile. If it's not set, the global limit is
+    /// only enforced by the methods which return errors of this crate,
+    /// like `Symbol::parse_limited` and `Symbol::try_intern`, as there is
+    /// no way to make `Self::Err` for it.
+    ///
+    /// ```
+    /// # use std::fmt;
+    /// # use string_intern::{Validator, Symbol, TooLong};
+    /// #[derive(Debug)]
+    /// struct TagTooLong(usize);
+    /// # impl fmt::Display for TagTooLong {
+    /// #     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+    /// #         write!(f, "tag of {} bytes is too long", self.0)
+    /// #     }
+    /// # }
+    /// struct Tag;
+    /// impl Validator for Tag {
+    ///     type Err = TagTooLong;
+    ///     const MAX_LEN: Option<usize> = Some(8);
+    ///     const TOO_LONG: Option<fn(TooLong) -> TagTooLong> =
+    ///         Some(|e| TagTooLong(e.len));
+    ///     fn validate_symbol(_: &str) -> Result<(), Self::Err> { Ok(()) }
+    /// }
+    /// let err = "long_tag_".parse::<Symbol<Tag>>().unwrap_err();
+    /// assert!(matches!(err, TagTooLong(9)));
+    /// ```
+    ///
+    /// Setting `MAX_LEN` alone fails to compile:
+    ///
+    /// ```compile_fail
+    /// # use string_intern::{Validator, Symbol};
+    /// struct Tag;
+    /// impl Validator for Tag {
+    ///     type Err = ::std::string::ParseError;
+    ///     const MAX_LEN: Option<usize> = Some(8);
+    ///     fn validate_symbol(_: &str) -> Result<(), Self::Err> { Ok(()) }
+    /// }
+    /// let tag = "tag".parse::<Symbol<Tag>>();
+    /// ```
+    const TOO_LONG: Option<fn(TooLong) -> Self::Err> = None;
+    /// Serialize symbols as bytes rather than strings to binary formats
+    ///
+    /// Only formats which are not human readable (as reported by their
+    /// serializer and deserializer) are affected, which is useful for the
+    /// ones that distinguish byte strings and text. Symbols are accepted
+    /// both as strings and as bytes on deserialization anyway. See also
+    /// `SymbolBytes`, which uses bytes for every format.
+    const BINARY_AS_BYTES: bool = false;
+    /// Regular expression all valid values match
+    ///
+    /// Only describes the values, e.g. as `pattern` of the JSON schema with
+    /// the `schemars` feature, `validate_symbol` must still check it.
+    /// Validators made by `regex_validator!` set it.
+    const PATTERN: Option<&'static str> = None;
+    fn validate_symbol(value: &str) -> Result<(), Self::Err>;
+    /// Converts the value to the canonical form before interning
+    ///
+    /// Called before validation and before every lookup in the pool, so
+    /// that e.g. `Example.COM.` and `example.com` are the same symbol.
+    /// Returns the value unchanged by default. Comparisons of symbols
+    /// with plain strings (`PartialEq<str>`) don't normalize the string.
+    /// With the `unicode-normalization` feature, `validators::nfc` can be
+    /// used here.
+    fn normalize(value: &str) -> Cow<'_, str> {
+        Cow::Borrowed(value)
+    }
+    /// Returns the key the value is looked up by in the pool
+    ///
+    /// Values with the same key are the same symbol: `Hash`, `Eq` and
+    /// `PartialEq<str>` use the key, while the value interned first is
+    /// kept for display. Unlike `normalize` this doesn't change the value
+    /// itself, see `validators::CaseInsensitive`. Returns the value
+    /// unchanged by default. When overriding it, `compare` must return
+    /// `Equal` for values with the same key. Symbols borrow as their keys
+    /// (`Borrow<str>`), so lookups by `&str` in hash maps of symbols only
+    /// work with strings that are keys already.
+    fn fold(value: &str) -> Cow<'_, str> {
+        Cow::Borrowed(value)
+    }
+    /// Compares values for `Ord` and `PartialOrd` of the symbol
+    ///
+    /// Byte-wise comparison by default. This only affects ordering,
+    /// equality and hashing use the key returned by `fold`. To keep `Ord`
+    /// consistent with `Eq`, the method must return `Equal` only for equal
+    /// keys, e.g. case-insensitive comparison should break ties by
+    /// comparing bytes. Note that lookups by `&str` in a `BTreeMap` of
+    /// symbols only work with the default ordering.
+    fn compare(a: &str, b: &str) -> cmp::Ordering {
+        a.cmp(b)
+    }
+    /// Returns the set of well-known values of this type
+    ///
+    /// Interning or looking up any of these values doesn't touch the pool,
+    /// see `StaticAtoms`. There is no such set by default.
+    fn static_atoms() -> Option<&'static StaticAtoms<Self>> {
+        None
+    }
+    /// Formats the symbol for `fmt::Debug`, quoted by default
+    fn debug(value: &Symbol<Self>, fmt: &mut fmt::Formatter) -> fmt::Result {
         write!(fmt, "i{:?}", value.as_ref())
     }
+    /// Formats the symbol for `fmt::Display`, the bare value by default
+    fn display(value: &Symbol<Self>, fmt: &mut fmt::Formatter) -> fmt::Result {
+        fmt::Display::fmt(value.as_ref(), fmt)
+    }
+}
+
+/// Declare a validator type and a symbol type alias in one go
+///
+/// Declares a unit struct for the validator with the same visibility as
+/// the alias, and implements `Validator` for it. The body of `validate`
+/// is the body of `Validator::validate_symbol`, and the optional `display`
+/// is the body of `Validator::display`.
+///
+/// # Example
+///
+/// ```
+/// # #[macro_use] extern crate string_intern;
+/// # use std::fmt;
+/// # use string_intern::validators::EmptyError;
+/// define_symbol_type! {
+///     /// Name of a user
+///     pub type UserName = Symbol<UserNameValidator>;
+///     validate |value| -> Result<(), EmptyError> {
+///         if value.is_empty() {
+///             return Err(EmptyError);
+///         }
+///         Ok(())
+///     }
+///     display |sym, f| {
+///         write!(f, "@{}", &sym[..])
+///     }
+/// }
+///
+/// # fn main() {
+/// let name = UserName::from("alice");
+/// assert_eq!(name.to_string(), "@alice");
+/// assert!("".parse::<UserName>().is_err());
+/// # }
+/// ```
+#[macro_export]
+macro_rules! define_symbol_type {
+    ($(#[$meta:meta])* $vis:vis type $alias:ident = Symbol<$validator:ident>;
+     validate |$value:ident| -> Result<(), $err:ty> $validate:block
+     $(display |$sym:ident, $fmt:ident| $display:block)?)
+    => {
+        #[doc = concat!("Validator of `", stringify!($alias), "`")]
+        $vis struct $validator;
+
+        impl $crate::Validator for $validator {
+            type Err = $err;
+            fn validate_symbol($value: &str) -> Result<(), Self::Err>
+                $validate
+            $(
+                fn display($sym: &$crate::Symbol<Self>,
+                    $fmt: &mut $crate::__fmt::Formatter)
+                    -> $crate::__fmt::Result
+                    $display
+            )?
+        }
+
+        $(#[$meta])*
+        $vis type $alias = $crate::Symbol<$validator>;
+    };
+}
+
+static GLOBAL_MAX_LEN: AtomicUsize = AtomicUsize::new(usize::MAX);
+
+/// Set the limit for the length (in bytes) of any interned value
+///
+/// This is a safeguard against accidentally interning huge strings, which
+/// then live as long as any reference to them. It applies to all validator
+/// types in addition to `Validator::MAX_LEN`. Longer values are rejected
+/// with the error made by `Validator::TOO_LONG`. Validators that don't set
+/// it can't report the error from `FromStr`, so for them only the methods
+/// returning errors of this crate (like `Symbol::parse_limited`) check the
+/// limit.
+///
+/// There is no limit by default. Pass `None` to remove the limit.
+pub fn set_global_max_len(max_len: Option<usize>) {
+    GLOBAL_MAX_LEN.store(max_len.unwrap_or(usize::MAX), Ordering::Relaxed);
+}
+
+/// Returns the limit set by `set_global_max_len`
+pub fn global_max_len() -> Option<usize> {
+    match GLOBAL_MAX_LEN.load(Ordering::Relaxed) {
+        usize::MAX => None,
+        x => Some(x),
+    }
+}
+
+/// Normalizes the value and runs all the checks declared by the validator
+///
+/// Returns the normalized value which should be interned.
+pub fn validate<V: Validator + ?Sized>(value: &str)
+    -> Result<Cow<'_, str>, V::Err>
+{
+    match validate_limited::<V>(value) {
+        Ok(value) => Ok(value),
+        Err(ValidationError::Invalid(e)) => Err(e),
+        Err(ValidationError::TooLong(e)) => match V::TOO_LONG {
+            Some(too_long) => Err(too_long(e)),
+            // only the global limit gets here, see `set_global_max_len`
+            None => {
+                let value = V::normalize(value);
+                V::validate_symbol(&value)?;
+                Ok(value)
+            }
+        },
+    }
+}
+
+/// Same as `validate` but always checks length limits
+pub(crate) fn validate_limited<V: Validator + ?Sized>(value: &str)
+    -> Result<Cow<'_, str>, ValidationError<V>>
+{
+    const {
+        assert!(V::MAX_LEN.is_none() || V::TOO_LONG.is_some(),
+            "`Validator::TOO_LONG` must be set along with `MAX_LEN`");
+    }
+    let value = V::normalize(value);
+    let global = GLOBAL_MAX_LEN.load(Ordering::Relaxed);
+    let max_len = V::MAX_LEN.map_or(global, |x| x.min(global));
+    if value.len() > max_len {
+        return Err(ValidationError::TooLong(TooLong {
+            len: value.len(),
+            max_len,
+        }));
+    }
+    V::validate_symbol(&value).map_err(ValidationError::Invalid)?;
+    Ok(value)
+}
+
+/// Returns the key returned by `Validator::fold` if it's not the value itself
+pub(crate) fn fold<V: Validator + ?Sized>(value: &str) -> Option<String> {
+    match V::fold(value) {
+        Cow::Borrowed(key) if key.as_ptr() == value.as_ptr() &&
+            key.len() == value.len() => None,
+        key => Some(key.into_owned()),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::borrow::Cow;
+    use std::fmt;
+    use std::error::Error;
+    use {Validator, Symbol, ValidationError, TooLong};
+
+    #[derive(Debug)]
+    enum ShortError {
+        TooLong(usize),
+        Space,
+    }
+
+    impl fmt::Display for ShortError {
+        fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+            match *self {
+                ShortError::TooLong(n) => write!(f, "{} bytes is too long", n),
+                ShortError::Space => f.write_str("space is not allowed"),
+            }
+        }
+    }
+
+    impl Error for ShortError {}
+
+    struct ShortString;
+    type Short = Symbol<ShortString>;
+
+    impl Validator for ShortString {
+        type Err = ShortError;
+        const MAX_LEN: Option<usize> = Some(8);
+        const TOO_LONG: Option<fn(TooLong) -> ShortError> =
+            Some(|e| ShortError::TooLong(e.len));
+        fn validate_symbol(s: &str) -> Result<(), Self::Err> {
+            if s.contains(' ') {
+                return Err(ShortError::Space);
+            }
+            Ok(())
+        }
+    }
+
+    struct Tagged;
+
+    impl Validator for Tagged {
+        type Err = ::std::string::ParseError;
+        fn validate_symbol(_: &str) -> Result<(), Self::Err> {
+            Ok(())
+        }
+        fn debug(value: &Symbol<Self>, fmt: &mut fmt::Formatter)
+            -> fmt::Result
+        {
+            write!(fmt, "Tag({})", value.as_ref())
+        }
+    }
+
+    struct Upper;
+
+    impl Validator for Upper {
+        type Err = ::std::string::ParseError;
+        fn validate_symbol(_: &str) -> Result<(), Self::Err> {
+            Ok(())
+        }
+        fn display(value: &Symbol<Self>, fmt: &mut fmt::Formatter)
+            -> fmt::Result
+        {
+            fmt.write_str(&value.to_uppercase())
+        }
+    }
+
+    struct Name;
+
+    impl Validator for Name {
+        type Err = ::std::string::ParseError;
+        fn validate_symbol(_: &str) -> Result<(), Self::Err> {
+            Ok(())
+        }
+        fn compare(a: &str, b: &str) -> ::std::cmp::Ordering {
+            a.to_lowercase().cmp(&b.to_lowercase()).then_with(|| a.cmp(b))
+        }
+    }
+
+    struct Host;
+
+    impl Validator for Host {
+        type Err = ShortError;
+        const MAX_LEN: Option<usize> = Some(8);
+        const TOO_LONG: Option<fn(TooLong) -> ShortError> =
+            Some(|e| ShortError::TooLong(e.len));
+        fn validate_symbol(s: &str) -> Result<(), Self::Err> {
+            if s.contains(' ') {
+                return Err(ShortError::Space);
+            }
+            Ok(())
+        }
+        fn normalize(value: &str) -> Cow<'_, str> {
+            let value = value.strip_suffix('.').unwrap_or(value);
+            if value.bytes().any(|b| b.is_ascii_uppercase()) {
+                Cow::Owned(value.to_ascii_lowercase())
+            } else {
+                Cow::Borrowed(value)
+            }
+        }
+    }
+
+    struct Boxed;
+
+    impl Validator for Boxed {
+        type Err = Box<dyn Error + Send + Sync>;
+        fn validate_symbol(s: &str) -> Result<(), Self::Err> {
+            if s.is_empty() {
+                return Err("empty value".into());
+            }
+            Ok(())
+        }
+    }
+
+    define_symbol_type! {
+        type Word = Symbol<WordValidator>;
+        validate |value| -> Result<(), ShortError> {
+            if value.contains(' ') {
+                return Err(ShortError::Space);
+            }
+            Ok(())
+        }
+    }
+
+    define_symbol_type! {
+        type Quoted = Symbol<QuotedValidator>;
+        validate |_value| -> Result<(), ::std::string::ParseError> { Ok(()) }
+        display |sym, f| { write!(f, "'{}'", &sym[..]) }
+    }
+
+    #[test]
+    fn define_symbol_type() {
+        assert_eq!(&Word::from("word")[..], "word");
+        assert!(matches!("two words".parse::<Word>(), Err(ShortError::Space)));
+        assert_eq!(format!("{}", Word::from("word")), "word");
+        assert_eq!(format!("{}", Quoted::from("word")), "'word'");
+        assert_eq!(format!("{:?}", Quoted::from("word")), "i\"word\"");
+    }
+
+    #[test]
+    fn normalize() {
+        type H = Symbol<Host>;
+        let x = H::from("Ex.COM.");
+        assert_eq!(&x[..], "ex.com");
+        assert_eq!(x.as_ptr(), H::from("ex.com").as_ptr());
+        assert_eq!(x, "EX.com".parse::<H>().unwrap());
+        assert_eq!(H::get("EX.COM"), Some(x.clone()));
+        assert_eq!(H::intern_split("A.b/ex.com.", '/').unwrap()[1], x);
+        // length is checked after normalization
+        assert!(H::from_string(String::from("ABCDEFGH.")).is_ok());
+        assert!(matches!("Ex .com".parse::<H>(), Err(ShortError::Space)));
+    }
+
+    #[test]
+    fn default_compare() {
+        assert!(Short::from("Apple") > Short::from("A"));
+        assert!(Short::from("Apple") < Short::from("banana"));
+        assert!(Short::from("apple") > Short::from("Banana"));
+    }
+
+    #[test]
+    fn custom_compare() {
+        type N = Symbol<Name>;
+        assert!(N::from("Apple") < N::from("banana"));
+        assert!(N::from("apple") < N::from("Banana"));
+        assert!(N::from("Apple") < "banana");
+        assert!("Banana" > N::from("apple"));
+        assert!(N::from("Apple") != N::from("apple"));
+        assert!(N::from("Apple") < N::from("apple"));
+        assert_eq!(N::from("Apple"), N::from("Apple"));
+        assert_eq!(N::from("Apple").as_ptr(), N::from("Apple").as_ptr());
+        let mut names = vec![N::from("b"), N::from("C"), N::from("a"),
+                             N::from("B")];
+        names.sort();
+        assert_eq!(names, vec!["a", "B", "b", "C"]);
+    }
+
+    #[test]
+    fn default_formatting() {
+        let x = Short::from("fmt_x");
+        assert_eq!(format!("{:?}", x), "i\"fmt_x\"");
+        assert_eq!(format!("{}", x), "fmt_x");
+        assert_eq!(format!("{:>7}", x), "  fmt_x");
+    }
+
+    #[test]
+    fn override_debug() {
+        let x = Symbol::<Tagged>::from("fmt_y");
+        assert_eq!(format!("{:?}", x), "Tag(fmt_y)");
+        assert_eq!(format!("{}", x), "fmt_y");
+    }
+
+    #[test]
+    fn override_display() {
+        let x = Symbol::<Upper>::from("fmt_z");
+        assert_eq!(format!("{:?}", x), "i\"fmt_z\"");
+        assert_eq!(format!("{}", x), "FMT_Z");
+    }
+
+    #[test]
+    fn max_len() {
+        assert!("abcdefgh".parse::<Short>().is_ok());
+        match "abcdefghi".parse::<Short>() {
+            Err(ShortError::TooLong(9)) => {}
+            r => panic!("unexpected result {:?}", r),
+        }
+        match Short::parse_limited("abcdefghi") {
+            Err(ValidationError::TooLong(TooLong { len: 9, max_len: 8 })) => {}
+            r => panic!("unexpected result {:?}", r),
+        }
+    }
+
+    #[test]
+    fn max_len_multibyte() {
+        // the limit is in bytes, not in characters
+        assert!("ééé".parse::<Short>().is_ok());
+        assert!("aaaaaaé".parse::<Short>().is_ok());
+        assert!("aaaaaaaé".parse::<Short>().is_err());
+        assert!("ééééé".parse::<Short>().is_err());
+    }
+
+    #[test]
+    fn global_max_len() {
+        use super::{set_global_max_len, global_max_len};
+        use validators::{NonEmpty, MaxLen, MaxLenError};
+        use TryInternError;
+        type N = Symbol<NonEmpty>;
+        assert_eq!(global_max_len(), None);
+        let huge = "x".repeat(2 << 20);
+        set_global_max_len(Some(1 << 20));
+        assert_eq!(global_max_len(), Some(1 << 20));
+        // the validator can't make an error for the limit, so it's only
+        // enforced by the methods returning errors of this crate
+        let limit = TooLong { len: 2 << 20, max_len: 1 << 20 };
+        match N::parse_limited(&huge) {
+            Err(ValidationError::TooLong(e)) => assert_eq!(e, limit),
+            r => panic!("unexpected result {:?}", r),
+        }
+        match N::try_intern(&huge) {
+            Err(TryInternError::TooLong(e)) => assert_eq!(e, limit),
+            r => panic!("unexpected result {:?}", r),
+        }
+        assert_eq!(N::get(&huge), None);
+        assert!(N::parse_limited(&huge[..1 << 20]).is_ok());
+        // validators which set `TOO_LONG` report it from `FromStr`
+        let long = "x".repeat((1 << 20) + 1);
+        match long.parse::<Symbol<MaxLen<NonEmpty, { 2 << 20 }>>>() {
+            Err(MaxLenError::TooLong(len)) => assert_eq!(len, long.len()),
+            r => panic!("unexpected result {:?}", r),
+        }
+        // validator's own limit is still smaller
+        match "123456789".parse::<Short>() {
+            Err(ShortError::TooLong(9)) => {}
+            r => panic!("unexpected result {:?}", r),
+        }
+        set_global_max_len(None);
+        assert_eq!(global_max_len(), None);
+        assert_eq!(&N::parse_limited(&huge).unwrap()[..], huge);
+    }
+
+    #[test]
+    fn max_len_checked_first() {
+        match "a b c d e".parse::<Short>() {
+            Err(ShortError::TooLong(9)) => {}
+            r => panic!("unexpected result {:?}", r),
+        }
+        match "a b".parse::<Short>() {
+            Err(ShortError::Space) => {}
+            r => panic!("unexpected result {:?}", r),
+        }
+    }
+
+    #[test]
+    fn not_std_error() {
+        type B = Symbol<Boxed>;
+        assert_eq!(&B::from("boxed")[..], "boxed");
+        let err = "".parse::<B>().unwrap_err();
+        assert_eq!(err.to_string(), "empty value");
+        let err = B::parse_with_context("").unwrap_err();
+        assert!(err.to_string().ends_with(": empty value"));
+    }
 }
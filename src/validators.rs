@@ -0,0 +1,764 @@
+//! Ready-made validators and checks to be used in `Validator::validate_symbol`
+//!
+//! Validator types, like `Hostname` or `Uuid`, can be used directly as
+//! `Symbol<Hostname>`. All the code using them shares the same pool, so
+//! when symbols need a distinct type (or their own pool), define your own
+//! validator calling the check function.
+//!
+//! # Example
+//!
+//! ```
+//! use string_intern::{Validator, Symbol};
+//! use string_intern::validators::{validate_ascii_identifier, IdentError};
+//!
+//! struct Ident;
+//!
+//! impl Validator for Ident {
+//!     type Err = IdentError;
+//!     fn validate_symbol(val: &str) -> Result<(), Self::Err> {
+//!         validate_ascii_identifier(val)
+//!     }
+//! }
+//!
+//! assert!("some_name".parse::<Symbol<Ident>>().is_ok());
+//! assert!("some-name".parse::<Symbol<Ident>>().is_err());
+//! ```
+use alloc::borrow::Cow;
+use core::cmp::Ordering;
+use core::fmt;
+use core::error::Error;
+use core::marker::PhantomData;
+#[cfg(feature = "regex")] use std::sync::OnceLock;
+
+#[cfg(feature = "regex")] use regex::Regex;
+use {Validator, TooLong};
+
+
+/// Error returned by `validate_ascii_identifier`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum IdentError {
+    /// Identifier is an empty string
+    Empty,
+    /// Identifier starts with a digit
+    LeadingDigit,
+    /// Identifier contains a byte at specified offset which is not
+    /// an ASCII letter, digit or underscore
+    InvalidChar(usize),
+}
+
+impl fmt::Display for IdentError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            IdentError::Empty => f.write_str("identifier is empty"),
+            IdentError::LeadingDigit => {
+                f.write_str("identifier must not start with a digit")
+            }
+            IdentError::InvalidChar(pos) => {
+                write!(f, "invalid character in identifier at byte {}", pos)
+            }
+        }
+    }
+}
+
+impl Error for IdentError {}
+
+/// Error returned by `validate_non_empty`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct EmptyError;
+
+impl fmt::Display for EmptyError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str("value is empty")
+    }
+}
+
+impl Error for EmptyError {}
+
+/// Error returned by `validate_hostname`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum HostnameError {
+    /// Hostname is an empty string
+    Empty,
+    /// Hostname is longer than 253 bytes
+    TooLong(usize),
+    /// Label at specified offset is empty, e.g. `a..b`
+    EmptyLabel(usize),
+    /// Label at specified offset is longer than 63 bytes
+    LabelTooLong(usize),
+    /// Label at specified offset starts or ends with a hyphen
+    Hyphen(usize),
+    /// Byte at specified offset is not an ASCII letter, digit or hyphen
+    InvalidChar(usize),
+}
+
+impl fmt::Display for HostnameError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            HostnameError::Empty => f.write_str("hostname is empty"),
+            HostnameError::TooLong(len) => {
+                write!(f, "hostname of {} bytes is too long", len)
+            }
+            HostnameError::EmptyLabel(pos) => {
+                write!(f, "empty label in hostname at byte {}", pos)
+            }
+            HostnameError::LabelTooLong(pos) => {
+                write!(f, "label in hostname at byte {} is too long", pos)
+            }
+            HostnameError::Hyphen(pos) => {
+                write!(f, "label in hostname at byte {} \
+                    starts or ends with a hyphen", pos)
+            }
+            HostnameError::InvalidChar(pos) => {
+                write!(f, "invalid character in hostname at byte {}", pos)
+            }
+        }
+    }
+}
+
+impl Error for HostnameError {}
+
+/// Error returned by `validate_uuid`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum UuidError {
+    /// Value is not 36 bytes long
+    InvalidLength(usize),
+    /// Byte at specified offset is not a hex digit, or not a hyphen
+    /// where one is expected
+    InvalidChar(usize),
+}
+
+impl fmt::Display for UuidError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            UuidError::InvalidLength(len) => {
+                write!(f, "uuid must be 36 bytes, got {}", len)
+            }
+            UuidError::InvalidChar(pos) => {
+                write!(f, "invalid character in uuid at byte {}", pos)
+            }
+        }
+    }
+}
+
+impl Error for UuidError {}
+
+/// Error returned by `validate_email`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum EmailError {
+    /// There is no `@` in the value
+    MissingAt,
+    /// The part before `@` is empty
+    EmptyLocal,
+    /// The part before `@` is longer than 64 bytes
+    LocalTooLong,
+    /// Byte at specified offset is not allowed in the part before `@`
+    InvalidChar(usize),
+    /// The part after `@` is not a valid hostname
+    Domain(HostnameError),
+}
+
+impl fmt::Display for EmailError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            EmailError::MissingAt => f.write_str("email has no `@`"),
+            EmailError::EmptyLocal => f.write_str("email has empty user"),
+            EmailError::LocalTooLong => f.write_str("email user is too long"),
+            EmailError::InvalidChar(pos) => {
+                write!(f, "invalid character in email at byte {}", pos)
+            }
+            EmailError::Domain(ref e) => write!(f, "email domain: {}", e),
+        }
+    }
+}
+
+impl Error for EmailError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match *self {
+            EmailError::Domain(ref e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+/// Error of validators defined by `regex_validator!`
+#[cfg(feature = "regex")]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PatternError {
+    /// The pattern the value doesn't match
+    pub pattern: &'static str,
+}
+
+#[cfg(feature = "regex")]
+impl fmt::Display for PatternError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "value doesn't match pattern {:?}", self.pattern)
+    }
+}
+
+#[cfg(feature = "regex")]
+impl Error for PatternError {}
+
+/// Error of the `MaxLen` validator
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum MaxLenError<E> {
+    /// Value of specified length (in bytes) exceeds the limit
+    TooLong(usize),
+    /// Value is rejected by the inner validator
+    Invalid(E),
+}
+
+impl<E: fmt::Display> fmt::Display for MaxLenError<E> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            MaxLenError::TooLong(len) => {
+                write!(f, "value of {} bytes is too long", len)
+            }
+            MaxLenError::Invalid(ref e) => e.fmt(f),
+        }
+    }
+}
+
+impl<E: Error + 'static> Error for MaxLenError<E> {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match *self {
+            MaxLenError::Invalid(ref e) => Some(e),
+            MaxLenError::TooLong(_) => None,
+        }
+    }
+}
+
+/// Checks that value is an ASCII identifier: `[A-Za-z_][A-Za-z0-9_]*`
+///
+/// Checks bytes rather than unicode chars, so it is faster than the
+/// equivalent `char`-based code.
+pub fn validate_ascii_identifier(value: &str) -> Result<(), IdentError> {
+    let bytes = value.as_bytes();
+    match bytes.first() {
+        None => return Err(IdentError::Empty),
+        Some(b) if b.is_ascii_digit() => return Err(IdentError::LeadingDigit),
+        Some(_) => {}
+    }
+    match bytes.iter().position(|&b| !b.is_ascii_alphanumeric() && b != b'_')
+    {
+        Some(pos) => Err(IdentError::InvalidChar(pos)),
+        None => Ok(()),
+    }
+}
+
+/// Checks that value is not an empty string
+pub fn validate_non_empty(value: &str) -> Result<(), EmptyError> {
+    if value.is_empty() {
+        return Err(EmptyError);
+    }
+    Ok(())
+}
+
+/// Checks that value is a hostname as defined by RFC 1123
+///
+/// Labels separated by dots consist of ASCII letters, digits and hyphens,
+/// are 1 to 63 bytes long and don't start or end with a hyphen. Trailing
+/// dot is not allowed.
+pub fn validate_hostname(value: &str) -> Result<(), HostnameError> {
+    if value.is_empty() {
+        return Err(HostnameError::Empty);
+    }
+    if value.len() > 253 {
+        return Err(HostnameError::TooLong(value.len()));
+    }
+    let mut start = 0;
+    for label in value.split('.') {
+        let bytes = label.as_bytes();
+        if bytes.is_empty() {
+            return Err(HostnameError::EmptyLabel(start));
+        }
+        if bytes.len() > 63 {
+            return Err(HostnameError::LabelTooLong(start));
+        }
+        if bytes[0] == b'-' || bytes[bytes.len() - 1] == b'-' {
+            return Err(HostnameError::Hyphen(start));
+        }
+        if let Some(pos) = bytes.iter()
+            .position(|&b| !b.is_ascii_alphanumeric() && b != b'-')
+        {
+            return Err(HostnameError::InvalidChar(start + pos));
+        }
+        start += label.len() + 1;
+    }
+    Ok(())
+}
+
+/// Checks that value is a hyphenated UUID, e.g.
+/// `67e55044-10b1-426f-9247-bb680e5fe0c8`
+///
+/// Both lowercase and uppercase hex digits are accepted.
+pub fn validate_uuid(value: &str) -> Result<(), UuidError> {
+    let bytes = value.as_bytes();
+    if bytes.len() != 36 {
+        return Err(UuidError::InvalidLength(bytes.len()));
+    }
+    for (pos, &b) in bytes.iter().enumerate() {
+        let ok = match pos {
+            8 | 13 | 18 | 23 => b == b'-',
+            _ => b.is_ascii_hexdigit(),
+        };
+        if !ok {
+            return Err(UuidError::InvalidChar(pos));
+        }
+    }
+    Ok(())
+}
+
+/// Checks that value looks like an email address: `user@example.com`
+///
+/// This is a rough check rather than RFC 5322 parser: the user part may
+/// contain any printable characters except `@`, and the domain must be
+/// a valid hostname.
+pub fn validate_email(value: &str) -> Result<(), EmailError> {
+    let at = value.find('@').ok_or(EmailError::MissingAt)?;
+    let (local, domain) = (&value[..at], &value[at + 1..]);
+    if local.is_empty() {
+        return Err(EmailError::EmptyLocal);
+    }
+    if local.len() > 64 {
+        return Err(EmailError::LocalTooLong);
+    }
+    if let Some(pos) = local.bytes().position(|b| b.is_ascii_control() ||
+                                                  b == b' ')
+    {
+        return Err(EmailError::InvalidChar(pos));
+    }
+    validate_hostname(domain).map_err(EmailError::Domain)
+}
+
+/// Validator of non-empty values
+pub struct NonEmpty;
+
+impl Validator for NonEmpty {
+    type Err = EmptyError;
+    fn validate_symbol(value: &str) -> Result<(), Self::Err> {
+        validate_non_empty(value)
+    }
+}
+
+/// Validator of ASCII identifiers, see `validate_ascii_identifier`
+pub struct Identifier;
+
+impl Validator for Identifier {
+    type Err = IdentError;
+    fn validate_symbol(value: &str) -> Result<(), Self::Err> {
+        validate_ascii_identifier(value)
+    }
+}
+
+/// Validator of hostnames, see `validate_hostname`
+///
+/// Hostnames are compared case-sensitively, use
+/// `CaseInsensitive<Hostname>` to ignore case.
+pub struct Hostname;
+
+impl Validator for Hostname {
+    type Err = HostnameError;
+    const MAX_LEN: Option<usize> = Some(253);
+    const TOO_LONG: Option<fn(TooLong) -> Self::Err> =
+        Some(|e| HostnameError::TooLong(e.len));
+    fn validate_symbol(value: &str) -> Result<(), Self::Err> {
+        validate_hostname(value)
+    }
+}
+
+/// Validator of hyphenated UUIDs, see `validate_uuid`
+pub struct Uuid;
+
+impl Validator for Uuid {
+    type Err = UuidError;
+    const TOO_LONG: Option<fn(TooLong) -> Self::Err> =
+        Some(|e| UuidError::InvalidLength(e.len));
+    fn validate_symbol(value: &str) -> Result<(), Self::Err> {
+        validate_uuid(value)
+    }
+}
+
+/// Validator of email addresses, see `validate_email`
+pub struct Email;
+
+impl Validator for Email {
+    type Err = EmailError;
+    fn validate_symbol(value: &str) -> Result<(), Self::Err> {
+        validate_email(value)
+    }
+}
+
+/// Limits the length of the values of `V` to `N` bytes
+///
+/// ```
+/// # use string_intern::Symbol;
+/// # use string_intern::validators::{MaxLen, NonEmpty};
+/// type Tag = Symbol<MaxLen<NonEmpty, 8>>;
+/// assert!("short".parse::<Tag>().is_ok());
+/// assert!("too_long_tag".parse::<Tag>().is_err());
+/// assert!("".parse::<Tag>().is_err());
+/// ```
+pub struct MaxLen<V: ?Sized, const N: usize>(PhantomData<V>);
+
+impl<V: Validator + ?Sized, const N: usize> Validator for MaxLen<V, N> {
+    type Err = MaxLenError<V::Err>;
+    const MAX_LEN: Option<usize> = match V::MAX_LEN {
+        Some(len) if len < N => Some(len),
+        _ => Some(N),
+    };
+    const TOO_LONG: Option<fn(TooLong) -> Self::Err> =
+        Some(|e| MaxLenError::TooLong(e.len));
+    const BINARY_AS_BYTES: bool = V::BINARY_AS_BYTES;
+    const PATTERN: Option<&'static str> = V::PATTERN;
+    fn validate_symbol(value: &str) -> Result<(), Self::Err> {
+        V::validate_symbol(value).map_err(MaxLenError::Invalid)
+    }
+    fn normalize(value: &str) -> Cow<'_, str> {
+        V::normalize(value)
+    }
+    fn fold(value: &str) -> Cow<'_, str> {
+        V::fold(value)
+    }
+    fn compare(a: &str, b: &str) -> Ordering {
+        V::compare(a, b)
+    }
+}
+
+/// Checks the value against `pattern`, compiled once into `regex`
+///
+/// Used by `regex_validator!`. Panics if the pattern is invalid.
+#[cfg(feature = "regex")]
+#[doc(hidden)]
+pub fn match_pattern(regex: &OnceLock<Regex>, pattern: &'static str,
+    value: &str)
+    -> Result<(), PatternError>
+{
+    let regex = regex.get_or_init(|| {
+        Regex::new(pattern).expect("invalid pattern in regex_validator!")
+    });
+    if !regex.is_match(value) {
+        return Err(PatternError { pattern });
+    }
+    Ok(())
+}
+
+/// Define a validator type accepting values that match a regex
+///
+/// The regex is compiled on first use, an invalid pattern panics then.
+/// Note that the pattern should usually be anchored with `^` and `$`,
+/// otherwise it matches any value containing a match. The error type is
+/// `validators::PatternError`, and the pattern is `Validator::PATTERN`.
+///
+/// # Example
+///
+/// ```
+/// # #[macro_use] extern crate string_intern;
+/// # use string_intern::Symbol;
+/// regex_validator!(pub Slug, r"^[a-z][a-z0-9\-]*$");
+///
+/// # fn main() {
+/// assert!("my-slug".parse::<Symbol<Slug>>().is_ok());
+/// assert!("My Slug".parse::<Symbol<Slug>>().is_err());
+/// # }
+/// ```
+#[cfg(feature = "regex")]
+#[macro_export]
+macro_rules! regex_validator {
+    ($(#[$meta:meta])* $vis:vis $name:ident, $pattern:expr $(,)*) => {
+        $(#[$meta])*
+        $vis struct $name;
+
+        impl $crate::Validator for $name {
+            type Err = $crate::validators::PatternError;
+            const PATTERN: Option<&'static str> = Some($pattern);
+            fn validate_symbol(value: &str) -> Result<(), Self::Err> {
+                static REGEX: ::std::sync::OnceLock<
+                    $crate::validators::__Regex> =
+                    ::std::sync::OnceLock::new();
+                $crate::validators::match_pattern(&REGEX, $pattern, value)
+            }
+        }
+    };
+}
+
+#[cfg(feature = "regex")]
+#[doc(hidden)]
+pub use regex::Regex as __Regex;
+
+/// Converts the value to Unicode Normalization Form C
+///
+/// To be called from `Validator::normalize`, so that the same text typed
+/// as composed (`é`) or decomposed (`e` and a combining accent) characters
+/// is the same symbol. Nothing is allocated if the value is in NFC already.
+///
+/// ```
+/// # use std::borrow::Cow;
+/// # use string_intern::{Validator, Symbol};
+/// struct UserName;
+///
+/// impl Validator for UserName {
+///     type Err = ::std::string::ParseError;
+///     fn validate_symbol(_: &str) -> Result<(), Self::Err> { Ok(()) }
+///     fn normalize(value: &str) -> Cow<'_, str> {
+///         string_intern::validators::nfc(value)
+///     }
+/// }
+///
+/// let composed = Symbol::<UserName>::from("Ren\u{e9}");
+/// let decomposed = Symbol::<UserName>::from("Rene\u{301}");
+/// assert_eq!(composed.as_ptr(), decomposed.as_ptr());
+/// ```
+#[cfg(feature = "unicode-normalization")]
+pub fn nfc(value: &str) -> Cow<'_, str> {
+    use unicode_normalization::{is_nfc_quick, IsNormalized};
+    use unicode_normalization::UnicodeNormalization;
+    if is_nfc_quick(value.chars()) == IsNormalized::Yes {
+        return Cow::Borrowed(value);
+    }
+    let normalized = value.nfc().collect::<String>();
+    if normalized == value {
+        Cow::Borrowed(value)
+    } else {
+        Cow::Owned(normalized)
+    }
+}
+
+/// Case-insensitive flavor of the symbols of `V`
+///
+/// Values that differ only in case are the same symbol, which keeps the
+/// spelling interned first. Validation and normalization are done by `V`,
+/// but the symbols have their own pool.
+///
+/// Case is folded by `str::to_lowercase`, which isn't full Unicode case
+/// folding (e.g. `ß` and `SS` are different symbols).
+///
+/// ```
+/// # use string_intern::{Validator, Symbol};
+/// # use string_intern::validators::CaseInsensitive;
+/// # struct Header;
+/// # impl Validator for Header {
+/// #     type Err = ::std::string::ParseError;
+/// #     fn validate_symbol(_: &str) -> Result<(), Self::Err> { Ok(()) }
+/// # }
+/// type HeaderName = Symbol<CaseInsensitive<Header>>;
+/// let a = HeaderName::from("Content-Type");
+/// assert_eq!(a, HeaderName::from("content-type"));
+/// assert_eq!(HeaderName::from("CONTENT-TYPE").as_ref(), "Content-Type");
+/// ```
+pub struct CaseInsensitive<V: ?Sized>(PhantomData<V>);
+
+fn lowercase(value: &str) -> Cow<'_, str> {
+    if value.is_ascii() {
+        if value.bytes().any(|b| b.is_ascii_uppercase()) {
+            Cow::Owned(value.to_ascii_lowercase())
+        } else {
+            Cow::Borrowed(value)
+        }
+    } else {
+        let lower = value.to_lowercase();
+        if lower == value {
+            Cow::Borrowed(value)
+        } else {
+            Cow::Owned(lower)
+        }
+    }
+}
+
+impl<V: Validator + ?Sized> Validator for CaseInsensitive<V> {
+    type Err = V::Err;
+    const MAX_LEN: Option<usize> = V::MAX_LEN;
+    const TOO_LONG: Option<fn(TooLong) -> Self::Err> = V::TOO_LONG;
+    const BINARY_AS_BYTES: bool = V::BINARY_AS_BYTES;
+    const PATTERN: Option<&'static str> = V::PATTERN;
+    fn validate_symbol(value: &str) -> Result<(), Self::Err> {
+        V::validate_symbol(value)
+    }
+    fn normalize(value: &str) -> Cow<'_, str> {
+        V::normalize(value)
+    }
+    fn fold(value: &str) -> Cow<'_, str> {
+        lowercase(value)
+    }
+    fn compare(a: &str, b: &str) -> Ordering {
+        lowercase(a).cmp(&lowercase(b))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::collections::HashSet;
+    use {Validator, Symbol, Interner};
+    use super::{validate_ascii_identifier, IdentError, CaseInsensitive};
+    use super::{validate_hostname, HostnameError, validate_uuid, UuidError};
+    use super::{validate_email, EmailError, validate_non_empty, EmptyError};
+    use super::{Hostname, MaxLen, MaxLenError, NonEmpty, Uuid};
+
+    struct AnyString;
+
+    impl Validator for AnyString {
+        type Err = ::std::string::ParseError;
+        fn validate_symbol(_: &str) -> Result<(), Self::Err> {
+            Ok(())
+        }
+    }
+
+    type Name = Symbol<CaseInsensitive<AnyString>>;
+
+    #[test]
+    fn non_empty() {
+        assert_eq!(validate_non_empty("x"), Ok(()));
+        assert_eq!(validate_non_empty(""), Err(EmptyError));
+        assert!("".parse::<Symbol<NonEmpty>>().is_err());
+    }
+
+    #[test]
+    fn hostname() {
+        assert_eq!(validate_hostname("localhost"), Ok(()));
+        assert_eq!(validate_hostname("a-1.Example.com"), Ok(()));
+        assert_eq!(validate_hostname("1.2.3.4"), Ok(()));
+        assert_eq!(validate_hostname(""), Err(HostnameError::Empty));
+        assert_eq!(validate_hostname("a..b"),
+                   Err(HostnameError::EmptyLabel(2)));
+        assert_eq!(validate_hostname("a.com."),
+                   Err(HostnameError::EmptyLabel(6)));
+        assert_eq!(validate_hostname("a.-b"), Err(HostnameError::Hyphen(2)));
+        assert_eq!(validate_hostname("a.b-"), Err(HostnameError::Hyphen(2)));
+        assert_eq!(validate_hostname("a.b_c"),
+                   Err(HostnameError::InvalidChar(3)));
+        assert_eq!(validate_hostname(&"a".repeat(64)),
+                   Err(HostnameError::LabelTooLong(0)));
+        let long = vec!["a".repeat(63); 4].join(".");
+        assert!(matches!(long.parse::<Symbol<Hostname>>(),
+                         Err(HostnameError::TooLong(255))));
+    }
+
+    #[test]
+    fn uuid() {
+        assert_eq!(validate_uuid("67e55044-10b1-426f-9247-bb680e5fe0c8"),
+                   Ok(()));
+        assert_eq!(validate_uuid("67E55044-10B1-426F-9247-BB680E5FE0C8"),
+                   Ok(()));
+        assert_eq!(validate_uuid("67e5504410b1426f9247bb680e5fe0c8"),
+                   Err(UuidError::InvalidLength(32)));
+        assert_eq!(validate_uuid("67e55044-10b1-426f-9247_bb680e5fe0c8"),
+                   Err(UuidError::InvalidChar(23)));
+        assert_eq!(validate_uuid("g7e55044-10b1-426f-9247-bb680e5fe0c8"),
+                   Err(UuidError::InvalidChar(0)));
+        assert!("67e55044-10b1-426f-9247-bb680e5fe0c8"
+                .parse::<Symbol<Uuid>>().is_ok());
+    }
+
+    #[test]
+    fn email() {
+        assert_eq!(validate_email("user.name+tag@example.com"), Ok(()));
+        assert_eq!(validate_email("user"), Err(EmailError::MissingAt));
+        assert_eq!(validate_email("@example.com"), Err(EmailError::EmptyLocal));
+        assert_eq!(validate_email("a b@example.com"),
+                   Err(EmailError::InvalidChar(1)));
+        assert_eq!(validate_email("a@b@example.com"),
+                   Err(EmailError::Domain(HostnameError::InvalidChar(1))));
+        assert_eq!(validate_email(&format!("{}@x", "a".repeat(65))),
+                   Err(EmailError::LocalTooLong));
+    }
+
+    #[test]
+    fn max_len() {
+        type Short = Symbol<MaxLen<NonEmpty, 4>>;
+        type Shorter = Symbol<MaxLen<MaxLen<NonEmpty, 2>, 4>>;
+        assert!("abcd".parse::<Short>().is_ok());
+        assert!(matches!("abcde".parse::<Short>(),
+                         Err(MaxLenError::TooLong(5))));
+        assert!(matches!("".parse::<Short>(),
+                         Err(MaxLenError::Invalid(EmptyError))));
+        assert!(matches!("abc".parse::<Shorter>(),
+                         Err(MaxLenError::TooLong(3))));
+    }
+
+    #[test]
+    fn case_insensitive() {
+        let a = Name::from("Ci_Name");
+        let b = Name::from("CI_NAME");
+        assert_eq!(a.as_ptr(), b.as_ptr());
+        assert_eq!(&b[..], "Ci_Name");
+        assert_eq!(a, "ci_name");
+        assert_eq!(Name::get("ci_NAME"), Some(a.clone()));
+        assert_eq!(Name::from("Ci_Été").as_ptr(),
+                   Name::from("ci_éTÉ").as_ptr());
+        assert!(Name::from("ci_a") < Name::from("CI_B"));
+        let set = vec![a.clone(), b.clone()].into_iter()
+            .collect::<HashSet<_>>();
+        assert_eq!(set.len(), 1);
+        // looked up by the key, which agrees with `Hash`
+        assert!(set.contains("ci_name"));
+        assert!(set.contains(&String::from("ci_name")));
+        assert!(!set.contains("Ci_Name"));
+        // the case-sensitive pool is separate
+        assert_eq!(&Symbol::<AnyString>::from("CI_NAME")[..], "CI_NAME");
+    }
+
+    #[test]
+    fn case_insensitive_other_pool() {
+        let pool = Interner::<CaseInsensitive<AnyString>>::new();
+        let local = pool.intern("CI_OTHER").unwrap();
+        assert_eq!(&local[..], "CI_OTHER");
+        assert_eq!(pool.get("ci_other"), Some(local.clone()));
+        assert_eq!(local, Name::from("ci_Other"));
+        assert_eq!(pool.len(), 1);
+    }
+
+    #[test]
+    #[cfg(feature = "unicode-normalization")]
+    fn nfc() {
+        use std::borrow::Cow;
+        use super::nfc;
+        assert!(matches!(nfc("plain"), Cow::Borrowed("plain")));
+        assert!(matches!(nfc("caf\u{e9}"), Cow::Borrowed(_)));
+        assert_eq!(nfc("cafe\u{301}"), "caf\u{e9}");
+        assert_eq!(nfc("\u{212b}"), "\u{c5}");
+    }
+
+    #[cfg(feature = "regex")]
+    regex_validator!(Lower, r"^[a-z]+$");
+
+    #[test]
+    #[cfg(feature = "regex")]
+    fn regex() {
+        use super::PatternError;
+        assert!("abc".parse::<Symbol<Lower>>().is_ok());
+        assert_eq!("aBc".parse::<Symbol<Lower>>().unwrap_err(),
+                   PatternError { pattern: "^[a-z]+$" });
+        assert!("".parse::<Symbol<Lower>>().is_err());
+        assert_eq!(Lower::PATTERN, Some("^[a-z]+$"));
+        assert_eq!(MaxLen::<Lower, 8>::PATTERN, Some("^[a-z]+$"));
+    }
+
+    #[test]
+    fn case_insensitive_drop() {
+        drop(Name::from("CI_Dropped"));
+        assert_eq!(Name::get("ci_dropped"), None);
+        assert_eq!(&Name::from("ci_dropped")[..], "ci_dropped");
+    }
+
+    #[test]
+    fn identifier() {
+        assert_eq!(validate_ascii_identifier("x"), Ok(()));
+        assert_eq!(validate_ascii_identifier("_"), Ok(()));
+        assert_eq!(validate_ascii_identifier("hello_World_42"), Ok(()));
+        assert_eq!(validate_ascii_identifier("_1"), Ok(()));
+    }
+
+    #[test]
+    fn invalid_identifier() {
+        assert_eq!(validate_ascii_identifier(""), Err(IdentError::Empty));
+        assert_eq!(validate_ascii_identifier("1x"),
+                   Err(IdentError::LeadingDigit));
+        assert_eq!(validate_ascii_identifier("a b"),
+                   Err(IdentError::InvalidChar(1)));
+        assert_eq!(validate_ascii_identifier("a-b"),
+                   Err(IdentError::InvalidChar(1)));
+        assert_eq!(validate_ascii_identifier("abé"),
+                   Err(IdentError::InvalidChar(2)));
+        assert_eq!(validate_ascii_identifier("é"),
+                   Err(IdentError::InvalidChar(0)));
+    }
+}
@@ -0,0 +1,179 @@
+use core::fmt;
+use core::hash::{Hash, Hasher};
+use core::marker::PhantomData;
+use alloc::sync::{Arc, Weak};
+
+#[cfg(feature = "serde")] use serde::ser::{Serialize, Serializer};
+#[cfg(feature = "serde")] use serde::de::{Deserialize, Deserializer};
+use {Validator, Symbol};
+use base_type::Value;
+
+
+/// A weak reference to a symbol
+///
+/// It doesn't keep the value alive, so the value is removed from the pool
+/// when all `Symbol`s are dropped, even if weak references still exist.
+// `None` for `WeakSymbol::new`, as there is no dangling `Weak` of an
+// unsized value
+pub struct WeakSymbol<V: Validator + ?Sized>(
+    Option<Weak<Value>>, PhantomData<V>);
+
+impl<V: Validator + ?Sized> Symbol<V> {
+    /// Create a weak reference to this symbol
+    pub fn downgrade(&self) -> WeakSymbol<V> {
+        WeakSymbol(Some(Arc::downgrade(&self.0)), PhantomData)
+    }
+}
+
+impl<V: Validator + ?Sized> WeakSymbol<V> {
+    /// Create a weak reference that never upgrades
+    pub fn new() -> WeakSymbol<V> {
+        WeakSymbol(None, PhantomData)
+    }
+    /// Returns the symbol if it's still alive
+    pub fn upgrade(&self) -> Option<Symbol<V>> {
+        self.0.as_ref()?.upgrade().map(|v| Symbol(v, PhantomData))
+    }
+    /// Returns `true` if some symbol still refers to the value
+    ///
+    /// Unlike `upgrade().is_some()` this doesn't touch the pool even if
+    /// the value is dropped concurrently.
+    pub fn is_alive(&self) -> bool {
+        self.0.as_ref().is_some_and(|w| w.strong_count() > 0)
+    }
+}
+
+// Compared by identity rather than by value, so references stay equal
+// (and keep the same hash) after the value is dropped, which is needed for
+// weak references used as keys of a cache
+impl<V: Validator + ?Sized> PartialEq for WeakSymbol<V> {
+    fn eq(&self, other: &WeakSymbol<V>) -> bool {
+        match (&self.0, &other.0) {
+            (Some(a), Some(b)) => a.ptr_eq(b),
+            (a, b) => a.is_none() && b.is_none(),
+        }
+    }
+}
+impl<V: Validator + ?Sized> Eq for WeakSymbol<V> {}
+
+impl<V: Validator + ?Sized> Hash for WeakSymbol<V> {
+    fn hash<H: Hasher>(&self, hasher: &mut H) {
+        self.0.as_ref().map(|w| w.as_ptr() as *const ()).hash(hasher)
+    }
+}
+
+impl<V: Validator + ?Sized> Default for WeakSymbol<V> {
+    fn default() -> WeakSymbol<V> {
+        WeakSymbol::new()
+    }
+}
+
+impl<V: Validator + ?Sized> Clone for WeakSymbol<V> {
+    fn clone(&self) -> WeakSymbol<V> {
+        WeakSymbol(self.0.clone(), PhantomData)
+    }
+}
+
+impl<V: Validator + ?Sized> fmt::Debug for WeakSymbol<V> {
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+        match self.upgrade() {
+            Some(sym) => write!(fmt, "WeakSymbol({:?})", sym),
+            None => fmt.write_str("WeakSymbol(<dead>)"),
+        }
+    }
+}
+
+/// Serialized as `Option<Symbol>`: the value if it's alive or `None`
+#[cfg(feature = "serde")]
+impl<V: Validator + ?Sized> Serialize for WeakSymbol<V> {
+    fn serialize<S: Serializer>(&self, serializer: S)
+        -> Result<S::Ok, S::Error>
+    {
+        self.upgrade().serialize(serializer)
+    }
+}
+
+/// Deserialized from `Option<Symbol>`
+///
+/// The value is interned and downgraded, so the resulting reference is alive
+/// only if the same symbol is held elsewhere at that time.
+#[cfg(feature = "serde")]
+impl<'de, V: Validator + ?Sized> Deserialize<'de> for WeakSymbol<V> {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+        where D: Deserializer<'de>
+    {
+        Ok(Option::<Symbol<V>>::deserialize(deserializer)?
+            .map(|s| s.downgrade())
+            .unwrap_or_default())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use {Validator, Symbol, WeakSymbol};
+
+    struct AnyString;
+    type Atom = Symbol<AnyString>;
+
+    impl Validator for AnyString {
+        // Use an error from standard library to make example shorter
+        type Err = ::std::string::ParseError;
+        fn validate_symbol(_: &str) -> Result<(), Self::Err> {
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn upgrade() {
+        let x = Atom::from("weak_upgrade");
+        let w = x.downgrade();
+        assert_eq!(w.upgrade(), Some(x.clone()));
+        drop(x);
+        assert_eq!(w.upgrade(), None);
+        assert_eq!(WeakSymbol::<AnyString>::new().upgrade(), None);
+    }
+
+    #[test]
+    fn cache_key() {
+        use std::collections::HashMap;
+        let x = Atom::from("weak_cache_a");
+        let y = Atom::from("weak_cache_b");
+        let mut cache = HashMap::new();
+        cache.insert(x.downgrade(), 1);
+        cache.insert(y.downgrade(), 2);
+        assert_eq!(cache.get(&x.downgrade()), Some(&1));
+        assert!(x.downgrade().is_alive());
+        let w = y.downgrade();
+        drop(y);
+        assert!(!w.is_alive());
+        assert_eq!(cache.get(&w), Some(&2));
+        cache.retain(|k, _| k.is_alive());
+        assert_eq!(cache.len(), 1);
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn serde_alive() {
+        use serde_json;
+        let x = Atom::from("weak_serde_alive");
+        let data = serde_json::to_string(&x.downgrade()).unwrap();
+        assert_eq!(data, r#""weak_serde_alive""#);
+        let w: WeakSymbol<AnyString> = serde_json::from_str(&data).unwrap();
+        assert_eq!(w.upgrade(), Some(x));
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn serde_dead() {
+        use serde_json;
+        let w = Atom::from("weak_serde_dead").downgrade();
+        let data = serde_json::to_string(&w).unwrap();
+        assert_eq!(data, "null");
+        let w: WeakSymbol<AnyString> = serde_json::from_str(&data).unwrap();
+        assert_eq!(w.upgrade(), None);
+        // nothing holds the value, so it's dead right after deserializing
+        let w: WeakSymbol<AnyString> = serde_json::from_str(
+            r#""weak_serde_dead""#).unwrap();
+        assert_eq!(w.upgrade(), None);
+    }
+}
@@ -0,0 +1,50 @@
+use std::io::{self, Read, Write};
+
+use borsh::{BorshDeserialize, BorshSerialize};
+
+use {Validator, Symbol};
+
+
+/// Encoded the same way as `String`
+impl<V: Validator + ?Sized> BorshSerialize for Symbol<V> {
+    fn serialize<W: Write>(&self, writer: &mut W) -> io::Result<()> {
+        BorshSerialize::serialize(&self[..], writer)
+    }
+}
+
+/// Decoded as a `String`, then validated and interned
+impl<V: Validator + ?Sized> BorshDeserialize for Symbol<V> {
+    fn deserialize_reader<R: Read>(reader: &mut R) -> io::Result<Self> {
+        let value = String::deserialize_reader(reader)?;
+        Symbol::from_string(value).map_err(|e: V::Err| {
+            io::Error::new(io::ErrorKind::InvalidData, e.to_string())
+        })
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::io;
+    use borsh;
+    use Symbol;
+    use validators::Identifier;
+
+    #[test]
+    fn roundtrip() {
+        let sym = Symbol::<Identifier>::from("borsh_value");
+        let data = borsh::to_vec(&sym).unwrap();
+        assert_eq!(data, borsh::to_vec("borsh_value").unwrap());
+        let back: Symbol<Identifier> = borsh::from_slice(&data).unwrap();
+        assert_eq!(back.as_ptr(), sym.as_ptr());
+    }
+
+    #[test]
+    fn invalid() {
+        let data = borsh::to_vec("not an identifier").unwrap();
+        let err = borsh::from_slice::<Symbol<Identifier>>(&data)
+            .unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+        let data = borsh::to_vec(&vec![0xffu8]).unwrap();
+        assert!(borsh::from_slice::<Symbol<Identifier>>(&data).is_err());
+    }
+}
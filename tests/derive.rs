@@ -0,0 +1,36 @@
+//! Checks validators generated by `#[derive(SymbolValidator)]`
+#![cfg(feature = "derive")]
+#[macro_use] extern crate string_intern;
+
+use string_intern::{Validator, Symbol};
+
+#[derive(SymbolValidator)]
+#[symbol(max_len = 8, lowercase)]
+struct Tag;
+
+#[test]
+fn max_len_lowercase() {
+    let tag = "Some_Tag".parse::<Symbol<Tag>>().unwrap();
+    assert_eq!(&tag[..], "some_tag");
+    assert_eq!(<Tag as Validator>::MAX_LEN, Some(8));
+    assert_eq!("long_tag_".parse::<Symbol<Tag>>().unwrap_err(),
+               TagError::TooLong(9));
+    assert_eq!(TagError::TooLong(9).to_string(),
+               "value of 9 bytes is too long");
+}
+
+#[cfg(feature = "regex")]
+#[derive(SymbolValidator)]
+#[symbol(pattern = "^[a-z][a-z0-9-]*$")]
+pub struct Slug;
+
+#[test]
+#[cfg(feature = "regex")]
+fn pattern() {
+    assert!("a-1".parse::<Symbol<Slug>>().is_ok());
+    assert_eq!(<Slug as Validator>::PATTERN, Some("^[a-z][a-z0-9-]*$"));
+    assert_eq!("1-a".parse::<Symbol<Slug>>().unwrap_err(),
+               SlugError::Mismatch);
+    assert_eq!(SlugError::Mismatch.to_string(),
+               "value doesn't match pattern \"^[a-z][a-z0-9-]*$\"");
+}
@@ -0,0 +1,90 @@
+//! Checks that building and interning a string copies it only once
+//!
+//! The bytes are copied into the allocation of the value, nothing else
+//! should be as large as the value. This is a separate test binary because
+//! it installs a counting allocator.
+extern crate string_intern;
+
+use std::alloc::{GlobalAlloc, Layout, System};
+use std::cell::Cell;
+
+use string_intern::{Validator, Symbol};
+
+struct Counter;
+
+/// Length of the values, allocations at least this large are copies
+const LEN: usize = 4096;
+
+thread_local! {
+    // Counting per thread excludes allocations made by the test harness
+    static LARGE: Cell<usize> = const { Cell::new(0) };
+}
+
+fn large_allocations() -> usize {
+    LARGE.with(|c| c.get())
+}
+
+unsafe impl GlobalAlloc for Counter {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        if layout.size() >= LEN {
+            LARGE.with(|c| c.set(c.get() + 1));
+        }
+        System.alloc(layout)
+    }
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        System.dealloc(ptr, layout)
+    }
+    // growing a string being built is not a copy of the value
+    unsafe fn realloc(&self, ptr: *mut u8, layout: Layout, new_size: usize)
+        -> *mut u8
+    {
+        System.realloc(ptr, layout, new_size)
+    }
+}
+
+#[global_allocator]
+static GLOBAL: Counter = Counter;
+
+struct AnyString;
+
+impl Validator for AnyString {
+    type Err = ::std::string::ParseError;
+    fn validate_symbol(_: &str) -> Result<(), Self::Err> {
+        Ok(())
+    }
+}
+
+type Atom = Symbol<AnyString>;
+
+#[test]
+fn from_string() {
+    let value = "a".repeat(LEN);
+    let before = large_allocations();
+    let x = Atom::from_string(value).unwrap();
+    assert_eq!(large_allocations(), before + 1);
+    let y = Atom::from_string("a".repeat(LEN)).unwrap();
+    assert_eq!(y.as_ptr(), x.as_ptr());
+    assert_eq!("a".repeat(LEN).parse::<Atom>().unwrap().as_ptr(), x.as_ptr());
+    // the two strings above, but no more values
+    assert_eq!(large_allocations(), before + 3);
+}
+
+#[test]
+fn concat() {
+    let head = "b".repeat(LEN / 2);
+    let tail = "c".repeat(LEN / 2 - 1);
+    let before = large_allocations();
+    let x = Atom::concat([&head, &tail], "-").unwrap();
+    assert_eq!(large_allocations(), before + 1);
+    assert_eq!(x.len(), LEN);
+}
+
+#[test]
+fn intern_fmt() {
+    let head = "d".repeat(LEN / 2);
+    let tail = "e".repeat(LEN / 2 - 1);
+    let before = large_allocations();
+    let x = Atom::intern_fmt(format_args!("{}-{}", head, tail)).unwrap();
+    assert_eq!(large_allocations(), before + 1);
+    assert_eq!(x.len(), LEN);
+}
@@ -0,0 +1,118 @@
+//! Checks that interning doesn't allocate buffers which are thrown away
+//!
+//! This is a separate test binary because it installs a counting allocator.
+extern crate string_intern;
+
+use std::alloc::{GlobalAlloc, Layout, System};
+use std::cell::Cell;
+use std::sync::{Arc, Barrier};
+use std::thread;
+
+use string_intern::{Validator, Symbol};
+
+struct Counter;
+
+thread_local! {
+    // Counting per thread excludes allocations made by the test harness
+    static ALLOCATIONS: Cell<usize> = const { Cell::new(0) };
+}
+
+fn allocations() -> usize {
+    ALLOCATIONS.with(|c| c.get())
+}
+
+unsafe impl GlobalAlloc for Counter {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        ALLOCATIONS.with(|c| c.set(c.get() + 1));
+        System.alloc(layout)
+    }
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        System.dealloc(ptr, layout)
+    }
+}
+
+#[global_allocator]
+static GLOBAL: Counter = Counter;
+
+struct AnyString;
+
+impl Validator for AnyString {
+    type Err = ::std::string::ParseError;
+    fn validate_symbol(_: &str) -> Result<(), Self::Err> {
+        Ok(())
+    }
+}
+
+type Atom = Symbol<AnyString>;
+
+const THREADS: usize = 16;
+const STRINGS: usize = 1000;
+
+#[test]
+fn racing_parse() {
+    // make sure the pool exists before measuring
+    let _warm = Atom::from("warm_up");
+    let barrier = Arc::new(Barrier::new(THREADS + 1));
+    let names = Arc::new((0..STRINGS).map(|i| format!("racing_{}", i))
+        .collect::<Vec<_>>());
+    let threads = (0..THREADS).map(|_| {
+        let barrier = barrier.clone();
+        let names = names.clone();
+        thread::spawn(move || {
+            let mut symbols = Vec::with_capacity(STRINGS);
+            // pool is looked up once per thread
+            drop(Atom::from("warm_up"));
+            barrier.wait();
+            let before = allocations();
+            for name in names.iter() {
+                symbols.push(name.parse::<Atom>().unwrap());
+            }
+            (symbols, allocations() - before)
+        })
+    }).collect::<Vec<_>>();
+    barrier.wait();
+    let results = threads.into_iter()
+        .map(|t| t.join().unwrap())
+        .collect::<Vec<_>>();
+    let allocated: usize = results.iter().map(|&(_, n)| n).sum();
+    for (idx, name) in names.iter().enumerate() {
+        let first = results[0].0[idx].as_ptr();
+        assert_eq!(&results[0].0[idx][..], &name[..]);
+        assert!(results.iter().all(|(v, _)| v[idx].as_ptr() == first));
+    }
+    // A single value is one allocation (the header and the bytes), plus
+    // the table and the id slots are resized a few times (and the table is
+    // copied a few times with arc-swap), each shard is resized separately
+    let mut overhead = if cfg!(feature = "arc-swap") { 80 } else { 32 };
+    if cfg!(feature = "sharded") {
+        overhead *= 16;
+    }
+    assert!(allocated <= STRINGS + overhead,
+            "allocated {} times", allocated);
+}
+
+// Own pool, so the snapshot isn't refreshed because of other tests
+struct Repeated;
+
+impl Validator for Repeated {
+    type Err = ::std::string::ParseError;
+    fn validate_symbol(_: &str) -> Result<(), Self::Err> {
+        Ok(())
+    }
+}
+
+#[test]
+fn repeated_parse() {
+    type Rep = Symbol<Repeated>;
+    let keep = Rep::from("repeated");
+    // with arc-swap the snapshot is refreshed after a few misses
+    for _ in 0..100 {
+        drop("repeated".parse::<Rep>().unwrap());
+    }
+    let before = allocations();
+    for _ in 0..STRINGS {
+        let x = "repeated".parse::<Rep>().unwrap();
+        assert_eq!(x.as_ptr(), keep.as_ptr());
+    }
+    assert_eq!(allocations() - before, 0);
+}